@@ -60,6 +60,16 @@ class Config:
     pathRewritePlugin: list = None
     inputTextPlugin: list = None
     characterDefinitionFile: str = None
+    oovReading: dict = None
+    """
+    Reading estimation for out-of-vocabulary words, OOV words have no reading if it is not set.
+    
+    Keys:
+    
+    * alphabet: "letters" (default), "loanwords" or "none"
+    * loanwords: path to the loanword table, each line contains a word and its reading
+    * numbers: whether numbers get a spoken reading, true by default
+    """
 
     def as_jsons(self):
         """
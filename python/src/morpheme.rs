@@ -427,7 +427,7 @@ impl PyMorpheme {
 
     /// Returns word id of this word in the dictionary.
     #[pyo3(text_signature = "(self, /) -> int")]
-    fn word_id(&self, py: Python) -> u32 {
+    fn word_id(&self, py: Python) -> u64 {
         self.morph(py).word_id().as_raw()
     }
 
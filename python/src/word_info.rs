@@ -27,9 +27,9 @@ pub struct PyWordInfo {
     dictionary_form_word_id: i32,
     dictionary_form: String,
     reading_form: String,
-    a_unit_split: Vec<u32>,
-    b_unit_split: Vec<u32>,
-    word_structure: Vec<u32>,
+    a_unit_split: Vec<u64>,
+    b_unit_split: Vec<u64>,
+    word_structure: Vec<u64>,
     synonym_group_ids: Vec<u32>,
 }
 
@@ -52,7 +52,7 @@ impl From<WordInfo> for PyWordInfo {
             dictionary_form: copy_if_empty(word_info.dictionary_form, &word_info.surface),
             reading_form: copy_if_empty(word_info.reading_form, &word_info.surface),
             surface: word_info.surface,
-            // WordId is repr(transparent) with a single u32 field so transmute is safe
+            // WordId is repr(transparent) with a single u64 field so transmute is safe
             a_unit_split: unsafe { std::mem::transmute(word_info.a_unit_split) },
             b_unit_split: unsafe { std::mem::transmute(word_info.b_unit_split) },
             word_structure: unsafe { std::mem::transmute(word_info.word_structure) },
@@ -16,6 +16,7 @@
 
 use super::output::{SudachiOutput, Writer};
 use std::io::Write;
+use sudachi::analysis::lattice_view::LatticeFormat;
 use sudachi::analysis::stateful_tokenizer::StatefulTokenizer;
 use sudachi::analysis::stateless_tokenizer::DictionaryAccess;
 use sudachi::analysis::Mode;
@@ -26,6 +27,8 @@ use sudachi::sentence_splitter::{SentenceSplitter, SplitSentences};
 pub trait Analysis {
     fn analyze(&mut self, input: &str, writer: &mut Writer);
     fn set_subset(&mut self, subset: InfoSubset);
+    /// Output the lattice in the specified format instead of the analysis result
+    fn set_lattice_format(&mut self, format: Option<LatticeFormat>);
 }
 
 pub struct SplitSentencesOnly<'a> {
@@ -49,12 +52,17 @@ impl<'a> Analysis for SplitSentencesOnly<'a> {
     fn set_subset(&mut self, _subset: InfoSubset) {
         //noop
     }
+
+    fn set_lattice_format(&mut self, _format: Option<LatticeFormat>) {
+        //noop
+    }
 }
 
 pub struct AnalyzeNonSplitted<D: DictionaryAccess, O: SudachiOutput<D>> {
     output: O,
     analyzer: StatefulTokenizer<D>,
     morphemes: MorphemeList<D>,
+    lattice_format: Option<LatticeFormat>,
 }
 
 impl<D: DictionaryAccess + Clone, O: SudachiOutput<D>> AnalyzeNonSplitted<D, O> {
@@ -63,6 +71,7 @@ impl<D: DictionaryAccess + Clone, O: SudachiOutput<D>> AnalyzeNonSplitted<D, O>
             output,
             morphemes: MorphemeList::empty(dict.clone()),
             analyzer: StatefulTokenizer::create(dict, enable_debug, mode),
+            lattice_format: None,
         }
     }
 }
@@ -73,6 +82,13 @@ impl<D: DictionaryAccess, O: SudachiOutput<D>> Analysis for AnalyzeNonSplitted<D
         self.analyzer
             .do_tokenize()
             .unwrap_or_else(|e| panic!("tokenization failed, input: {}\n{}", input, e));
+        if let Some(format) = self.lattice_format {
+            self.analyzer
+                .lattice_view()
+                .and_then(|v| v.write(format, writer))
+                .expect("write lattice failed");
+            return;
+        }
         self.morphemes
             .collect_results(&mut self.analyzer)
             .expect("result collection failed");
@@ -84,6 +100,10 @@ impl<D: DictionaryAccess, O: SudachiOutput<D>> Analysis for AnalyzeNonSplitted<D
     fn set_subset(&mut self, subset: InfoSubset) {
         self.analyzer.set_subset(subset);
     }
+
+    fn set_lattice_format(&mut self, format: Option<LatticeFormat>) {
+        self.lattice_format = format;
+    }
 }
 
 pub struct AnalyzeSplitted<'a, D: DictionaryAccess + 'a, O: SudachiOutput<&'a D>> {
@@ -110,4 +130,8 @@ impl<'a, D: DictionaryAccess + 'a, O: SudachiOutput<&'a D>> Analysis for Analyze
     fn set_subset(&mut self, subset: InfoSubset) {
         self.inner.set_subset(subset)
     }
+
+    fn set_lattice_format(&mut self, format: Option<LatticeFormat>) {
+        self.inner.set_lattice_format(format)
+    }
 }
@@ -21,15 +21,22 @@ use std::path::{Path, PathBuf};
 use clap::{Args, Subcommand};
 use memmap2::Mmap;
 
+use crate::compare::{compare_main, CompareCmd};
 use sudachi::analysis::stateless_tokenizer::DictionaryAccess;
 use sudachi::config::Config;
-use sudachi::dic::build::report::DictPartReport;
+use sudachi::dic::build::decompile::Decompiler;
+use sudachi::dic::build::diff::DictDiff;
+use sudachi::dic::build::format::LexiconFormat;
+use sudachi::dic::build::lint::Linter;
+use sudachi::dic::build::report::{DictPartReport, ReportFormat};
 use sudachi::dic::build::DictBuilder;
+use sudachi::dic::connect::MatrixFormat;
 use sudachi::dic::dictionary::JapaneseDictionary;
 use sudachi::dic::grammar::Grammar;
-use sudachi::dic::header::HeaderVersion;
+use sudachi::dic::header::{Header, HeaderVersion};
 use sudachi::dic::lexicon::word_infos::WordInfo;
 use sudachi::dic::lexicon_set::LexiconSet;
+use sudachi::dic::verify::{verify_system, verify_user};
 use sudachi::dic::word_id::WordId;
 use sudachi::dic::DictionaryLoader;
 use sudachi::error::SudachiResult;
@@ -44,6 +51,11 @@ pub fn is_build_mode(subcommand: &Option<BuildCli>) -> bool {
                 BuildCli::System { .. } => "build",
                 BuildCli::User { .. } => "ubuild",
                 BuildCli::Dump { .. } => "dump",
+                BuildCli::Decompile { .. } => "decompile",
+                BuildCli::Verify { .. } => "verify",
+                BuildCli::Diff { .. } => "diff",
+                BuildCli::Compare(_) => "compare",
+                BuildCli::Lint { .. } => "lint",
             };
 
             !Path::new(&raw).exists()
@@ -60,9 +72,14 @@ pub(crate) enum BuildCli {
         #[command(flatten)]
         common: BuildCmd,
 
-        /// Path to matrix definition
+        /// Path to matrix definition (MeCab matrix.def or sparse matrix)
         #[arg(short, long)]
         matrix: PathBuf,
+
+        /// Storage format of the connection matrix: "dense" or "dedup"
+        /// (identical rows are stored once, smaller but slower; implies --compress)
+        #[arg(long = "matrix-format", default_value_t = MatrixFormat::Dense)]
+        matrix_format: MatrixFormat,
     },
 
     /// Builds user dictionary
@@ -90,6 +107,92 @@ pub(crate) enum BuildCli {
         #[arg(short = 's', long = "system")]
         system: Option<PathBuf>,
     },
+
+    /// Converts binary dictionary back to the csv lexicon
+    #[command(name = "decompile")]
+    Decompile {
+        /// target dictionary to decompile
+        dictionary: PathBuf,
+
+        /// Where to place csv lexicon
+        #[arg(short = 'o', long = "output")]
+        output: PathBuf,
+
+        /// Where to place connection matrix (system dictionary only)
+        #[arg(short = 'm', long = "matrix")]
+        matrix: Option<PathBuf>,
+
+        /// reference system dictionary.
+        /// required to decompile an user dictionary
+        #[arg(short = 's', long = "system")]
+        system: Option<PathBuf>,
+    },
+
+    /// Checks that the binary dictionary is consistent and not corrupted
+    #[command(name = "verify")]
+    Verify {
+        /// target dictionary to verify
+        dictionary: PathBuf,
+
+        /// reference system dictionary.
+        /// required to verify an user dictionary
+        #[arg(short = 's', long = "system")]
+        system: Option<PathBuf>,
+    },
+
+    /// Shows the difference between two binary dictionaries
+    #[command(name = "diff")]
+    Diff {
+        /// old version of the dictionary
+        old: PathBuf,
+
+        /// new version of the dictionary
+        new: PathBuf,
+
+        /// reference system dictionary.
+        /// required to compare user dictionaries
+        #[arg(short = 's', long = "system")]
+        system: Option<PathBuf>,
+
+        /// Output format: "text" or "json"
+        #[arg(short = 'f', long = "format", default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+
+        /// Where to write the difference, stdout by default
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+
+    /// Compares analysis results of two configurations on the same text
+    #[command(name = "compare")]
+    Compare(CompareCmd),
+
+    /// Checks csv lexicons for problems without building the dictionary
+    #[command(name = "lint")]
+    Lint {
+        /// Input csv files
+        inputs: Vec<PathBuf>,
+
+        /// reference system dictionary.
+        /// required to check user lexicons
+        #[arg(short = 's', long = "system")]
+        system: Option<PathBuf>,
+
+        /// Path to matrix definition, used to check system lexicons
+        #[arg(short, long)]
+        matrix: Option<PathBuf>,
+
+        #[command(flatten)]
+        format: FormatArgs,
+
+        /// Output format: "text" or "json"
+        #[arg(short = 'f', long = "output-format", default_value_t = ReportFormat::Text)]
+        output_format: ReportFormat,
+
+        /// Where to write found problems, stdout by default
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
 }
 
 #[derive(Args)]
@@ -105,11 +208,67 @@ pub(crate) struct BuildCmd {
     /// Description string to embed into dictionary
     #[arg(short, long, default_value = "")]
     description: String,
+
+    /// Embed the checksum of the dictionary contents,
+    /// it is checked by the verify command
+    #[arg(long)]
+    checksum: bool,
+
+    /// Number of threads to parse csv files and serialize word infos with,
+    /// 0 means the number of available CPUs. Built dictionaries do not depend on it
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+
+    /// Directory to cache parsed csv files in.
+    /// Unchanged csv files are taken from the cache instead of being parsed again
+    #[arg(long = "cache-dir")]
+    cache_dir: Option<PathBuf>,
+
+    /// Store word infos compressed: strings are deduplicated and numbers are varints.
+    /// Older versions of Sudachi can't read such dictionaries
+    #[arg(long)]
+    compress: bool,
+
+    #[command(flatten)]
+    format: FormatArgs,
+}
+
+#[derive(Args)]
+pub(crate) struct FormatArgs {
+    /// Format of input csv files: "sudachi", "mecab" (same as "ipadic") or "unidic"
+    #[arg(long = "input-format", default_value_t = LexiconFormat::Sudachi)]
+    input_format: LexiconFormat,
+
+    /// Column mapping for MeCab csv files, overrides the columns of the input format.
+    /// Comma-separated field=column pairs, e.g. "reading=12,lemma=*,pos=4/5/6/7/8/9"
+    #[arg(long = "columns")]
+    columns: Option<String>,
+}
+
+impl FormatArgs {
+    fn lexicon_format(&self) -> LexiconFormat {
+        match (&self.input_format, &self.columns) {
+            (format, None) => format.clone(),
+            (LexiconFormat::Mecab(columns), Some(spec)) => LexiconFormat::Mecab(
+                columns
+                    .clone()
+                    .with_overrides(spec)
+                    .unwrap_or_else(|e| panic!("invalid column mapping: {}", e)),
+            ),
+            (LexiconFormat::Sudachi, Some(_)) => {
+                panic!("column mapping can be used only with MeCab input formats")
+            }
+        }
+    }
 }
 
 pub fn build_main(subcommand: BuildCli) {
     match subcommand {
-        BuildCli::System { common, matrix } => build_system(common, matrix),
+        BuildCli::System {
+            common,
+            matrix,
+            matrix_format,
+        } => build_system(common, matrix, matrix_format),
         BuildCli::User { common, dictionary } => build_user(common, dictionary),
         BuildCli::Dump {
             dictionary,
@@ -117,12 +276,43 @@ pub fn build_main(subcommand: BuildCli) {
             output,
             system,
         } => dump_part(dictionary, system, part, output),
+        BuildCli::Decompile {
+            dictionary,
+            output,
+            matrix,
+            system,
+        } => decompile(dictionary, system, output, matrix),
+        BuildCli::Verify { dictionary, system } => verify(dictionary, system),
+        BuildCli::Diff {
+            old,
+            new,
+            system,
+            format,
+            output,
+        } => diff(old, new, system, format, output),
+        BuildCli::Compare(cmd) => compare_main(cmd),
+        BuildCli::Lint {
+            inputs,
+            system,
+            matrix,
+            format,
+            output_format,
+            output,
+        } => lint(inputs, system, matrix, format, output_format, output),
     }
 }
 
-fn build_system(mut cmd: BuildCmd, matrix: PathBuf) {
+fn build_system(mut cmd: BuildCmd, matrix: PathBuf, matrix_format: MatrixFormat) {
     let mut builder = DictBuilder::new_system();
+    builder.set_matrix_format(matrix_format);
     builder.set_description(std::mem::take(&mut cmd.description));
+    builder.set_checksum(cmd.checksum);
+    builder.set_compressed_word_infos(cmd.compress);
+    builder.set_lexicon_format(cmd.format.lexicon_format());
+    builder.set_threads(cmd.threads);
+    if let Some(dir) = cmd.cache_dir.as_ref() {
+        builder.set_cache_dir(dir);
+    }
     builder
         .read_conn(matrix.as_path())
         .expect("failed to read matrix");
@@ -148,6 +338,13 @@ fn build_user(mut cmd: BuildCmd, system: PathBuf) {
 
     let mut builder = DictBuilder::new_user(&dict);
     builder.set_description(std::mem::take(&mut cmd.description));
+    builder.set_checksum(cmd.checksum);
+    builder.set_compressed_word_infos(cmd.compress);
+    builder.set_lexicon_format(cmd.format.lexicon_format());
+    builder.set_threads(cmd.threads);
+    if let Some(dir) = cmd.cache_dir.as_ref() {
+        builder.set_cache_dir(dir);
+    }
     for d in cmd.inputs.iter() {
         builder
             .read_lexicon(d.as_path())
@@ -175,7 +372,16 @@ fn print_stats(report: &[DictPartReport]) {
             part.size(),
             unit,
             part.time().as_secs_f32()
-        )
+        );
+        for cost in part.estimated_costs() {
+            eprintln!(
+                "  {} (entry {}): cost {}, {} morphemes in system",
+                cost.surface, cost.index, cost.cost, cost.num_morphemes
+            );
+        }
+        for warning in part.warnings() {
+            eprintln!("  warning: {}", warning);
+        }
     }
 }
 
@@ -191,6 +397,185 @@ fn output_file(p: &Path) -> File {
         .unwrap_or_else(|e| panic!("failed to open {:?} for writing:\n{:?}", p, e))
 }
 
+fn decompile(dict: PathBuf, system: Option<PathBuf>, output: PathBuf, matrix: Option<PathBuf>) {
+    let file = File::open(dict).expect("open dict failed");
+    let data = unsafe { Mmap::map(&file) }.expect("mmap dict failed");
+    let loader =
+        unsafe { DictionaryLoader::read_any_dictionary(&data) }.expect("failed to load dictionary");
+
+    let system_data = system.map(|system_path| {
+        let file = File::open(system_path).expect("open system failed");
+        unsafe { Mmap::map(&file) }.expect("mmap system failed")
+    });
+    let system = system_data.as_ref().map(|data| {
+        DictionaryLoader::read_system_dictionary(data).expect("failed to load system dictionary")
+    });
+    let system_grammar = system.as_ref().and_then(|s| s.grammar.as_ref());
+
+    let decompiler =
+        Decompiler::new(&loader, system_grammar).expect("failed to prepare decompilation");
+
+    let mut writer = BufWriter::new(output_file(&output));
+    let entries = decompiler
+        .write_lexicon(&mut writer)
+        .expect("failed to write lexicon");
+    writer.flush().expect("failed to flush");
+    eprintln!("lexicon: {} entries", entries);
+
+    if let Some(matrix) = matrix {
+        let mut writer = BufWriter::new(output_file(&matrix));
+        let written = decompiler
+            .write_matrix(&mut writer)
+            .expect("failed to write matrix");
+        if !written {
+            panic!("user dictionary does not have connection matrix.")
+        }
+        writer.flush().expect("failed to flush");
+    }
+}
+
+fn verify(dict: PathBuf, system: Option<PathBuf>) {
+    let file = File::open(&dict).expect("open dict failed");
+    let data = unsafe { Mmap::map(&file) }.expect("mmap dict failed");
+    let header = Header::parse(&data).expect("failed to parse dictionary header");
+
+    let result = match header.version {
+        HeaderVersion::SystemDict(_) => verify_system(&data),
+        HeaderVersion::UserDict(_) => {
+            let system_path =
+                system.expect("system dictionary is required to verify user dictionary");
+            let file = File::open(system_path).expect("open system failed");
+            let system_data = unsafe { Mmap::map(&file) }.expect("mmap system failed");
+            let system = verify_system(&system_data).unwrap_or_else(|e| {
+                eprintln!("system dictionary is invalid: {}", e);
+                std::process::exit(1)
+            });
+            verify_user(&data, &system)
+        }
+    };
+
+    match result {
+        Ok(report) => {
+            eprintln!("words: {}", report.num_words);
+            eprintln!("indexed words: {}", report.num_indexed_words);
+            eprintln!("pos: {}", report.num_pos);
+            if let (HeaderVersion::SystemDict(_), (left, right)) =
+                (&report.header.version, report.matrix_size)
+            {
+                eprintln!("connection matrix: {}x{}", left, right);
+            }
+            match report.header.checksum {
+                Some(c) => eprintln!("checksum: {:016x} (ok)", c),
+                None => eprintln!("checksum: not present"),
+            }
+            println!("{:?}: ok", dict);
+        }
+        Err(e) => {
+            println!("{:?}: {}", dict, e);
+            std::process::exit(1)
+        }
+    }
+}
+
+fn diff(
+    old: PathBuf,
+    new: PathBuf,
+    system: Option<PathBuf>,
+    format: ReportFormat,
+    output: Option<PathBuf>,
+) {
+    let map = |p: &Path| {
+        let file = File::open(p).unwrap_or_else(|e| panic!("failed to open {:?}\n{:?}", p, e));
+        unsafe { Mmap::map(&file) }.unwrap_or_else(|e| panic!("failed to mmap {:?}\n{:?}", p, e))
+    };
+    let old_data = map(&old);
+    let new_data = map(&new);
+    let system_data = system.as_deref().map(map);
+
+    let old = unsafe { DictionaryLoader::read_any_dictionary(&old_data) }
+        .expect("failed to load old dictionary");
+    let new = unsafe { DictionaryLoader::read_any_dictionary(&new_data) }
+        .expect("failed to load new dictionary");
+    let system = system_data.as_ref().map(|data| {
+        DictionaryLoader::read_system_dictionary(data).expect("failed to load system dictionary")
+    });
+
+    let diff =
+        DictDiff::compare(&old, &new, system.as_ref()).expect("failed to compare dictionaries");
+    let mut writer: Box<dyn Write> = match output {
+        Some(p) => Box::new(BufWriter::new(output_file(&p))),
+        None => Box::new(BufWriter::new(std::io::stdout())),
+    };
+    diff.write(format, &mut writer)
+        .expect("failed to write difference");
+    writer.flush().expect("failed to flush");
+}
+
+fn lint(
+    inputs: Vec<PathBuf>,
+    system: Option<PathBuf>,
+    matrix: Option<PathBuf>,
+    format: FormatArgs,
+    output_format: ReportFormat,
+    output: Option<PathBuf>,
+) {
+    let system_data = system.map(|p| {
+        let file = File::open(&p).unwrap_or_else(|e| panic!("failed to open {:?}\n{:?}", p, e));
+        unsafe { Mmap::map(&file) }.unwrap_or_else(|e| panic!("failed to mmap {:?}\n{:?}", p, e))
+    });
+
+    let report = match system_data.as_ref() {
+        Some(data) => {
+            let system = DictionaryLoader::read_system_dictionary(data)
+                .expect("failed to load system dictionary")
+                .to_loaded()
+                .expect("system dictionary must have grammar");
+            let mut linter = Linter::new_user(&system);
+            if matrix.is_some() {
+                eprintln!("matrix is ignored, connection matrix of the system dictionary is used");
+            }
+            lint_inputs(&mut linter, &inputs, &format);
+            linter.lint()
+        }
+        None => {
+            let mut linter = Linter::new_system();
+            if let Some(m) = matrix {
+                linter
+                    .read_conn(m.as_path())
+                    .expect("failed to read matrix");
+            }
+            lint_inputs(&mut linter, &inputs, &format);
+            linter.lint()
+        }
+    }
+    .expect("failed to check lexicons");
+
+    let mut writer: Box<dyn Write> = match output {
+        Some(p) => Box::new(BufWriter::new(output_file(&p))),
+        None => Box::new(BufWriter::new(std::io::stdout())),
+    };
+    report
+        .write(&mut writer, output_format)
+        .expect("failed to write report");
+    writer.flush().expect("failed to flush");
+    if report.num_errors() != 0 {
+        std::process::exit(1);
+    }
+}
+
+fn lint_inputs<D: DictionaryAccess>(
+    linter: &mut Linter<D>,
+    inputs: &[PathBuf],
+    format: &FormatArgs,
+) {
+    linter.set_lexicon_format(format.lexicon_format());
+    for d in inputs.iter() {
+        linter
+            .read_lexicon(d.as_path())
+            .unwrap_or_else(|e| panic!("failed to read {:?}\n{:?}", d, e));
+    }
+}
+
 fn dump_part(dict: PathBuf, system: Option<PathBuf>, part: String, output: PathBuf) {
     let file = File::open(dict).expect("open dict failed");
     let data = unsafe { Mmap::map(&file) }.expect("mmap dict failed");
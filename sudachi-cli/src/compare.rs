@@ -0,0 +1,466 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+
+use crate::output::{Simple, SudachiOutput, Writer};
+use sudachi::analysis::stateful_tokenizer::StatefulTokenizer;
+use sudachi::analysis::stateless_tokenizer::DictionaryAccess;
+use sudachi::analysis::Mode;
+use sudachi::config::Config;
+use sudachi::dic::dictionary::JapaneseDictionary;
+use sudachi::prelude::MorphemeList;
+use sudachi::sentence_splitter::{SentenceSplitter, SplitSentences};
+
+#[derive(Args)]
+pub(crate) struct CompareCmd {
+    /// Input text file: If not present, read from STDIN
+    file: Option<PathBuf>,
+
+    /// Path to the setting file of the baseline (A) configuration
+    #[arg(long = "config-a")]
+    config_a: Option<PathBuf>,
+
+    /// Path to the setting file of the compared (B) configuration
+    #[arg(long = "config-b")]
+    config_b: Option<PathBuf>,
+
+    /// Path to the system dictionary of the A configuration
+    #[arg(long = "dict-a")]
+    dict_a: Option<PathBuf>,
+
+    /// Path to the system dictionary of the B configuration
+    #[arg(long = "dict-b")]
+    dict_b: Option<PathBuf>,
+
+    /// Split unit of the A configuration: "A", "B" or "C"
+    #[arg(long = "mode-a", default_value = "C")]
+    mode_a: Mode,
+
+    /// Split unit of the B configuration: "A", "B" or "C"
+    #[arg(long = "mode-b", default_value = "C")]
+    mode_b: Mode,
+
+    /// Path to the root directory of resources
+    #[arg(short = 'p', long = "resource_dir")]
+    resource_dir: Option<PathBuf>,
+
+    /// Output text file: If not present, use stdout
+    #[arg(short = 'o', long = "output")]
+    output_file: Option<PathBuf>,
+
+    /// Print only the summary, without the differing sentences
+    #[arg(short = 's', long = "summary-only")]
+    summary_only: bool,
+
+    /// Number of the most frequent changes of each type in the summary
+    #[arg(long = "top", default_value_t = 10)]
+    top: usize,
+}
+
+/// Type of a difference between two analyses of the same sentence
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum DiffKind {
+    /// Different segmentation
+    Boundary,
+    /// Same segment with different part of speech
+    Pos,
+    /// Same segment with different normalized form
+    Normalization,
+}
+
+impl Display for DiffKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let repr = match self {
+            DiffKind::Boundary => "boundary",
+            DiffKind::Pos => "pos",
+            DiffKind::Normalization => "normalization",
+        };
+        f.write_str(repr)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Change {
+    kind: DiffKind,
+    old: String,
+    new: String,
+}
+
+impl Change {
+    fn new(kind: DiffKind, old: String, new: String) -> Change {
+        Change { kind, old, new }
+    }
+}
+
+/// Compared fields of a morpheme, offsets are in bytes of the original sentence
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Token {
+    begin: usize,
+    end: usize,
+    surface: String,
+    pos: String,
+    normalized_form: String,
+}
+
+impl Token {
+    fn collect<D: DictionaryAccess>(morphemes: &MorphemeList<D>) -> Vec<Token> {
+        morphemes
+            .iter()
+            .map(|m| Token {
+                begin: m.begin(),
+                end: m.end(),
+                surface: m.surface().to_string(),
+                pos: m.part_of_speech().join(","),
+                normalized_form: m.normalized_form().to_owned(),
+            })
+            .collect()
+    }
+}
+
+fn join_surfaces(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| t.surface.as_str())
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Finds the differences between two analyses of the same sentence.
+///
+/// Segments which have the same boundaries are compared field by field,
+/// all other tokens are grouped into the smallest spans which end at the same offset
+/// and reported as boundary changes.
+fn compare_tokens(a: &[Token], b: &[Token]) -> Vec<Change> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let (ta, tb) = (&a[i], &b[j]);
+        if ta.begin == tb.begin && ta.end == tb.end {
+            if ta.pos != tb.pos {
+                result.push(Change::new(DiffKind::Pos, ta.pos.clone(), tb.pos.clone()));
+            }
+            if ta.normalized_form != tb.normalized_form {
+                result.push(Change::new(
+                    DiffKind::Normalization,
+                    format!("{}:{}", ta.surface, ta.normalized_form),
+                    format!("{}:{}", tb.surface, tb.normalized_form),
+                ));
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        let (start_a, start_b) = (i, j);
+        let (mut end_a, mut end_b) = (ta.end, tb.end);
+        i += 1;
+        j += 1;
+        while end_a != end_b {
+            if end_a < end_b && i < a.len() {
+                end_a = a[i].end;
+                i += 1;
+            } else if end_b < end_a && j < b.len() {
+                end_b = b[j].end;
+                j += 1;
+            } else {
+                break;
+            }
+        }
+        result.push(Change::new(
+            DiffKind::Boundary,
+            join_surfaces(&a[start_a..i]),
+            join_surfaces(&b[start_b..j]),
+        ));
+    }
+    if i < a.len() || j < b.len() {
+        result.push(Change::new(
+            DiffKind::Boundary,
+            join_surfaces(&a[i..]),
+            join_surfaces(&b[j..]),
+        ));
+    }
+    result
+}
+
+#[derive(Default)]
+struct KindSummary {
+    sentences: usize,
+    changes: HashMap<(String, String), usize>,
+}
+
+#[derive(Default)]
+struct Summary {
+    sentences: usize,
+    different: usize,
+    kinds: BTreeMap<DiffKind, KindSummary>,
+}
+
+impl Summary {
+    fn add(&mut self, changes: &[Change]) {
+        self.sentences += 1;
+        if changes.is_empty() {
+            return;
+        }
+        self.different += 1;
+        let mut seen = Vec::new();
+        for c in changes {
+            let kind = self.kinds.entry(c.kind).or_default();
+            if !seen.contains(&c.kind) {
+                seen.push(c.kind);
+                kind.sentences += 1;
+            }
+            *kind
+                .changes
+                .entry((c.old.clone(), c.new.clone()))
+                .or_default() += 1;
+        }
+    }
+
+    fn write<W: Write>(&self, w: &mut W, top: usize) -> io::Result<()> {
+        writeln!(
+            w,
+            "# sentences: {}, different: {}",
+            self.sentences, self.different
+        )?;
+        for (kind, summary) in self.kinds.iter() {
+            writeln!(w, "# {}: {} sentences", kind, summary.sentences)?;
+            let mut changes: Vec<_> = summary.changes.iter().collect();
+            // the most frequent first, ties are ordered by value for stable output
+            changes.sort_by(|(ka, a), (kb, b)| b.cmp(a).then_with(|| ka.cmp(kb)));
+            for ((old, new), count) in changes.into_iter().take(top) {
+                writeln!(w, "#   {} -> {}: {}", old, new, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Side<'a> {
+    tokenizer: StatefulTokenizer<&'a JapaneseDictionary>,
+    morphemes: MorphemeList<&'a JapaneseDictionary>,
+}
+
+impl<'a> Side<'a> {
+    fn new(dict: &'a JapaneseDictionary, mode: Mode) -> Self {
+        Side {
+            tokenizer: StatefulTokenizer::new(dict, mode),
+            morphemes: MorphemeList::empty(dict),
+        }
+    }
+
+    fn analyze(&mut self, sentence: &str) -> Vec<Token> {
+        self.tokenizer.reset().push_str(sentence);
+        self.tokenizer
+            .do_tokenize()
+            .unwrap_or_else(|e| panic!("tokenization failed, input: {}\n{}", sentence, e));
+        self.morphemes
+            .collect_results(&mut self.tokenizer)
+            .expect("result collection failed");
+        Token::collect(&self.morphemes)
+    }
+}
+
+fn load_dictionary(
+    config: Option<PathBuf>,
+    resource_dir: Option<PathBuf>,
+    dict: Option<PathBuf>,
+) -> JapaneseDictionary {
+    let config = Config::new(config, resource_dir, dict).expect("Failed to load config file");
+    JapaneseDictionary::from_cfg(&config)
+        .unwrap_or_else(|e| panic!("Failed to create dictionary: {:?}", e))
+}
+
+/// Analyzes the input with both configurations and reports the sentences
+/// which have different results, followed by the summary.
+///
+/// Sentences are split with the A configuration, so both analyze the same sentences.
+/// The process exits with 1 if there were any differences.
+pub(crate) fn compare_main(cmd: CompareCmd) {
+    let dict_a = load_dictionary(cmd.config_a, cmd.resource_dir.clone(), cmd.dict_a);
+    let dict_b = load_dictionary(cmd.config_b, cmd.resource_dir, cmd.dict_b);
+
+    let inner_reader: Box<dyn Read> = match cmd.file.as_ref() {
+        Some(input_path) => Box::new(
+            File::open(input_path)
+                .unwrap_or_else(|_| panic!("Failed to open input file {:?}", &input_path)),
+        ),
+        None => Box::new(io::stdin()),
+    };
+    let mut reader = BufReader::new(inner_reader);
+
+    let inner_writer: Box<dyn Write> = match &cmd.output_file {
+        Some(output_path) => Box::new(
+            File::create(output_path)
+                .unwrap_or_else(|_| panic!("Failed to open output file {:?}", &output_path)),
+        ),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer: Writer = BufWriter::new(inner_writer);
+
+    let splitter = SentenceSplitter::new().with_checker(dict_a.lexicon());
+    let mut side_a = Side::new(&dict_a, cmd.mode_a);
+    let mut side_b = Side::new(&dict_b, cmd.mode_b);
+    let output = Simple::new(false);
+    let mut summary = Summary::default();
+
+    let mut data = String::with_capacity(4 * 1024);
+    let mut line_no = 0;
+    while reader.read_line(&mut data).expect("readline failed") > 0 {
+        line_no += 1;
+        let line = data.trim_end_matches(['\r', '\n']);
+        for (_, sentence) in splitter.split(line) {
+            let tokens_a = side_a.analyze(sentence);
+            let tokens_b = side_b.analyze(sentence);
+            let changes = compare_tokens(&tokens_a, &tokens_b);
+            summary.add(&changes);
+            if changes.is_empty() || cmd.summary_only {
+                continue;
+            }
+            let mut kinds: Vec<_> = changes.iter().map(|c| c.kind).collect();
+            kinds.sort();
+            kinds.dedup();
+            let kinds: Vec<_> = kinds.iter().map(|k| k.to_string()).collect();
+            writeln!(writer, "# line {}: {}", line_no, kinds.join(", ")).expect("write failed");
+            writeln!(writer, "{}", sentence).expect("write failed");
+            writer.write_all(b"--- A\n").expect("write failed");
+            output
+                .write(&mut writer, &side_a.morphemes)
+                .expect("write result failed");
+            writer.write_all(b"+++ B\n").expect("write failed");
+            output
+                .write(&mut writer, &side_b.morphemes)
+                .expect("write result failed");
+        }
+        data.clear();
+    }
+
+    summary.write(&mut writer, cmd.top).expect("write failed");
+    writer.flush().expect("flush failed");
+    if summary.different != 0 {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(data: &[(&str, &str, &str)]) -> Vec<Token> {
+        let mut offset = 0;
+        data.iter()
+            .map(|(surface, pos, norm)| {
+                let begin = offset;
+                offset += surface.len();
+                Token {
+                    begin,
+                    end: offset,
+                    surface: surface.to_string(),
+                    pos: pos.to_string(),
+                    normalized_form: norm.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_analysis() {
+        let a = tokens(&[("東京", "名詞", "東京"), ("に", "助詞", "に")]);
+        assert!(compare_tokens(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn boundary_changes_are_grouped() {
+        let a = tokens(&[
+            ("京都", "名詞", "京都"),
+            ("府", "名詞", "府"),
+            ("に", "助詞", "に"),
+            ("行", "名詞", "行"),
+            ("く", "名詞", "く"),
+        ]);
+        let b = tokens(&[
+            ("京都府", "名詞", "京都府"),
+            ("に", "助詞", "に"),
+            ("行く", "動詞", "行く"),
+        ]);
+        assert_eq!(
+            vec![
+                Change::new(
+                    DiffKind::Boundary,
+                    "京都|府".to_owned(),
+                    "京都府".to_owned()
+                ),
+                Change::new(DiffKind::Boundary, "行|く".to_owned(), "行く".to_owned()),
+            ],
+            compare_tokens(&a, &b)
+        );
+    }
+
+    #[test]
+    fn overlapping_boundaries() {
+        let a = tokens(&[("ab", "x", "ab"), ("cd", "x", "cd")]);
+        let b = tokens(&[("a", "x", "a"), ("bc", "x", "bc"), ("d", "x", "d")]);
+        assert_eq!(
+            vec![Change::new(
+                DiffKind::Boundary,
+                "ab|cd".to_owned(),
+                "a|bc|d".to_owned()
+            )],
+            compare_tokens(&a, &b)
+        );
+    }
+
+    #[test]
+    fn field_changes() {
+        let a = tokens(&[("附属", "名詞", "附属"), ("に", "助詞", "に")]);
+        let b = tokens(&[("附属", "名詞", "付属"), ("に", "助詞,格助詞", "に")]);
+        assert_eq!(
+            vec![
+                Change::new(
+                    DiffKind::Normalization,
+                    "附属:附属".to_owned(),
+                    "附属:付属".to_owned()
+                ),
+                Change::new(DiffKind::Pos, "助詞".to_owned(), "助詞,格助詞".to_owned()),
+            ],
+            compare_tokens(&a, &b)
+        );
+    }
+
+    #[test]
+    fn summary_counts_sentences_by_kind() {
+        let mut summary = Summary::default();
+        let a = tokens(&[("京都", "名詞", "京都"), ("府", "名詞", "府")]);
+        let b = tokens(&[("京都府", "名詞", "京都府")]);
+        summary.add(&compare_tokens(&a, &b));
+        summary.add(&compare_tokens(&a, &b));
+        summary.add(&compare_tokens(&a, &a));
+        assert_eq!(3, summary.sentences);
+        assert_eq!(2, summary.different);
+        let boundary = &summary.kinds[&DiffKind::Boundary];
+        assert_eq!(2, boundary.sentences);
+        let mut out = Vec::new();
+        summary.write(&mut out, 10).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("#   京都|府 -> 京都府: 2\n"));
+    }
+}
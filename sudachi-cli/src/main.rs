@@ -16,6 +16,7 @@
 
 mod analysis;
 mod build;
+mod compare;
 mod output;
 
 use std::fs::File;
@@ -27,6 +28,7 @@ use clap::Parser;
 
 use crate::analysis::{Analysis, AnalyzeNonSplitted, AnalyzeSplitted, SplitSentencesOnly};
 use crate::build::{build_main, is_build_mode, BuildCli};
+use sudachi::analysis::lattice_view::LatticeFormat;
 use sudachi::config::Config;
 use sudachi::dic::dictionary::JapaneseDictionary;
 use sudachi::prelude::*;
@@ -113,6 +115,10 @@ struct Cli {
     #[arg(long = "split-sentences", default_value = "yes")]
     split_sentences: SentenceSplitMode,
 
+    /// Output the lattice of each analyzed sentence instead of the result: "json" or "dot"
+    #[arg(long = "lattice-format")]
+    lattice_format: Option<LatticeFormat>,
+
     #[command(subcommand)]
     command: Option<BuildCli>,
 }
@@ -178,6 +184,7 @@ fn main() {
             AnalyzeNonSplitted::new(o, &dict, args.mode, args.enable_debug)
         }),
     };
+    analyzer.set_lattice_format(args.lattice_format);
 
     let mut data = String::with_capacity(4 * 1024);
     let is_stdout = args.output_file.is_none();
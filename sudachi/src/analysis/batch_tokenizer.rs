@@ -0,0 +1,180 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::analysis::node::ResultNode;
+use crate::analysis::stateful_tokenizer::StatefulTokenizer;
+use crate::analysis::stateless_tokenizer::DictionaryAccess;
+use crate::analysis::Mode;
+use crate::dic::subset::InfoSubset;
+use crate::error::SudachiResult;
+use crate::input_text::InputBuffer;
+use crate::prelude::MorphemeList;
+
+/// Analysis result which can be sent between threads
+struct RawResult {
+    input: InputBuffer,
+    path: Vec<ResultNode>,
+    subset: InfoSubset,
+}
+
+/// Tokenizer which analyzes a batch of texts on several threads.
+///
+/// All threads share the same dictionary.
+/// Created [`StatefulTokenizer`]s are kept in a pool and reused between calls.
+pub struct BatchTokenizer<D> {
+    dict: D,
+    mode: Mode,
+    subset: InfoSubset,
+    num_threads: usize,
+    pool: Mutex<Vec<StatefulTokenizer<D>>>,
+}
+
+impl<D> BatchTokenizer<D>
+where
+    D: DictionaryAccess + Clone + Send + Sync,
+{
+    /// Create a tokenizer which uses all available cores
+    pub fn new(dict: D, mode: Mode) -> Self {
+        let num_threads = std::thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1);
+        Self {
+            dict,
+            mode,
+            subset: InfoSubset::all(),
+            num_threads,
+            pool: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set the maximum number of threads used for the analysis
+    pub fn with_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads.max(1);
+        self
+    }
+
+    /// Set the subset of word info fields which will be loaded
+    pub fn with_subset(mut self, subset: InfoSubset) -> Self {
+        self.subset = subset;
+        self.pool
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    /// Analyze all inputs.
+    ///
+    /// Results are returned in the same order as inputs.
+    /// If analysis of any input fails, the first error (in the input order) is returned.
+    pub fn tokenize<S: AsRef<str> + Sync>(
+        &self,
+        inputs: &[S],
+    ) -> SudachiResult<Vec<MorphemeList<D>>> {
+        let num_threads = self.num_threads.min(inputs.len());
+        let next = AtomicUsize::new(0);
+
+        let mut results: Vec<Option<SudachiResult<RawResult>>> = Vec::new();
+        results.resize_with(inputs.len(), || None);
+
+        if num_threads <= 1 {
+            let mut tok = self.acquire();
+            for (input, slot) in inputs.iter().zip(results.iter_mut()) {
+                *slot = Some(Self::run(&mut tok, input.as_ref()));
+            }
+            self.release(tok);
+        } else {
+            let parts: Vec<Vec<(usize, SudachiResult<RawResult>)>> = std::thread::scope(|s| {
+                let workers: Vec<_> = (0..num_threads)
+                    .map(|_| s.spawn(|| self.worker(inputs, &next)))
+                    .collect();
+                workers
+                    .into_iter()
+                    .map(|w| w.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                    .collect()
+            });
+            for (idx, result) in parts.into_iter().flatten() {
+                results[idx] = Some(result);
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|r| {
+                let r = r.expect("all inputs must be analyzed")?;
+                Ok(MorphemeList::from_components(
+                    self.dict.clone(),
+                    r.input,
+                    r.path,
+                    r.subset,
+                ))
+            })
+            .collect()
+    }
+
+    fn worker<S: AsRef<str>>(
+        &self,
+        inputs: &[S],
+        next: &AtomicUsize,
+    ) -> Vec<(usize, SudachiResult<RawResult>)> {
+        let mut tok = self.acquire();
+        let mut results = Vec::new();
+        loop {
+            let idx = next.fetch_add(1, Ordering::Relaxed);
+            if idx >= inputs.len() {
+                break;
+            }
+            results.push((idx, Self::run(&mut tok, inputs[idx].as_ref())));
+        }
+        self.release(tok);
+        results
+    }
+
+    fn run(tok: &mut StatefulTokenizer<D>, input: &str) -> SudachiResult<RawResult> {
+        tok.reset().push_str(input);
+        tok.do_tokenize()?;
+        let mut result = RawResult {
+            input: InputBuffer::new(),
+            path: Vec::new(),
+            subset: InfoSubset::empty(),
+        };
+        tok.swap_result(&mut result.input, &mut result.path, &mut result.subset);
+        Ok(result)
+    }
+
+    fn acquire(&self) -> StatefulTokenizer<D> {
+        let pooled = self.pool.lock().ok().and_then(|mut p| p.pop());
+        pooled.unwrap_or_else(|| {
+            let mut tok = StatefulTokenizer::new(self.dict.clone(), self.mode);
+            tok.set_subset(self.subset);
+            tok
+        })
+    }
+
+    fn release(&self, tok: StatefulTokenizer<D>) {
+        if let Ok(mut pool) = self.pool.lock() {
+            pool.push(tok);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::ops::Range;
+
+use crate::error::{SudachiError, SudachiResult};
+use crate::input_text::InputBuffer;
+
+/// Partial annotation of the input text which restricts the analysis result.
+///
+/// All offsets are in bytes of the original (not modified by input text plugins) text
+/// and must lie on the character boundaries of the analyzed text.
+#[derive(Clone, Debug, Default)]
+pub struct Constraints {
+    words: Vec<(Range<usize>, Option<u16>)>,
+    boundaries: Vec<usize>,
+    non_boundaries: Vec<usize>,
+}
+
+impl Constraints {
+    pub fn new() -> Constraints {
+        Default::default()
+    }
+
+    /// The range must be analyzed as a single morpheme.
+    /// If `pos_id` is given, the morpheme must have this part of speech.
+    ///
+    /// If the dictionary does not contain a suitable word,
+    /// an OOV morpheme will be created for the range.
+    pub fn add_word(&mut self, range: Range<usize>, pos_id: Option<u16>) -> &mut Self {
+        self.words.push((range, pos_id));
+        self
+    }
+
+    /// There must be a morpheme boundary at the offset
+    pub fn add_boundary(&mut self, offset: usize) -> &mut Self {
+        self.boundaries.push(offset);
+        self
+    }
+
+    /// There must not be a morpheme boundary at the offset
+    pub fn add_non_boundary(&mut self, offset: usize) -> &mut Self {
+        self.non_boundaries.push(offset);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.boundaries.is_empty() && self.non_boundaries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.boundaries.clear();
+        self.non_boundaries.clear();
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Mark {
+    Free,
+    Boundary,
+    NonBoundary,
+}
+
+/// Constraints converted to the char indices of the current (modified) text
+#[derive(Default)]
+pub(crate) struct ResolvedConstraints {
+    active: bool,
+    marks: Vec<Mark>,
+    /// Number of required boundaries strictly before the char index
+    required_before: Vec<u32>,
+    /// (begin, end, pos_id) of words with forced part of speech
+    pos: Vec<(usize, usize, u16)>,
+}
+
+impl ResolvedConstraints {
+    /// Resolves user constraints together with single word ranges from OOV providers.
+    /// `words` are char ranges of the current text, they are ignored
+    /// if they conflict with user constraints or previous ranges.
+    pub fn resolve(
+        &mut self,
+        constraints: &Constraints,
+        words: &[Range<usize>],
+        input: &InputBuffer,
+    ) -> SudachiResult<()> {
+        self.active = !constraints.is_empty() || !words.is_empty();
+        self.marks.clear();
+        self.required_before.clear();
+        self.pos.clear();
+        if !self.active {
+            return Ok(());
+        }
+
+        let num_chars = input.current_chars().len();
+        self.marks.resize(num_chars + 1, Mark::Free);
+
+        for &offset in constraints.boundaries.iter() {
+            let idx = Self::char_idx(input, offset)?;
+            self.mark(idx, Mark::Boundary)?;
+        }
+        for &offset in constraints.non_boundaries.iter() {
+            let idx = Self::char_idx(input, offset)?;
+            self.mark(idx, Mark::NonBoundary)?;
+        }
+        for (range, pos) in constraints.words.iter() {
+            let begin = Self::char_idx(input, range.start)?;
+            let end = Self::char_idx(input, range.end)?;
+            if begin >= end {
+                return Err(SudachiError::InvalidRange(range.start, range.end));
+            }
+            self.mark(begin, Mark::Boundary)?;
+            self.mark(end, Mark::Boundary)?;
+            for idx in begin + 1..end {
+                self.mark(idx, Mark::NonBoundary)?;
+            }
+            if let Some(pos_id) = pos {
+                self.pos.push((begin, end, *pos_id));
+            }
+        }
+        for range in words {
+            self.mark_word_if_free(range.start, range.end);
+        }
+
+        let mut count = 0;
+        for mark in self.marks.iter() {
+            self.required_before.push(count);
+            if *mark == Mark::Boundary {
+                count += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts original byte offset to current char index
+    fn char_idx(input: &InputBuffer, offset: usize) -> SudachiResult<usize> {
+        let num_chars = input.current_chars().len();
+        // char -> original byte mapping is monotonic, use binary search
+        let (mut pos, mut end) = (0, num_chars + 1);
+        while pos < end {
+            let mid = (pos + end) / 2;
+            if input.to_orig_byte_idx(mid) < offset {
+                pos = mid + 1;
+            } else {
+                end = mid;
+            }
+        }
+        if pos <= num_chars && input.to_orig_byte_idx(pos) == offset {
+            Ok(pos)
+        } else {
+            Err(SudachiError::InvalidConstraint(format!(
+                "offset {} is not on a character boundary of the analyzed text",
+                offset
+            )))
+        }
+    }
+
+    /// Marks chars `[begin, end)` as a single word, if it is compatible with existing marks
+    fn mark_word_if_free(&mut self, begin: usize, end: usize) {
+        if begin >= end
+            || end >= self.marks.len()
+            || self.marks[begin] == Mark::NonBoundary
+            || self.marks[end] == Mark::NonBoundary
+            || self.marks[begin + 1..end].contains(&Mark::Boundary)
+        {
+            return;
+        }
+        for idx in [begin, end] {
+            if idx != 0 && idx + 1 != self.marks.len() {
+                self.marks[idx] = Mark::Boundary;
+            }
+        }
+        for mark in self.marks[begin + 1..end].iter_mut() {
+            *mark = Mark::NonBoundary;
+        }
+    }
+
+    fn mark(&mut self, idx: usize, mark: Mark) -> SudachiResult<()> {
+        // text borders are always boundaries
+        if idx == 0 || idx + 1 == self.marks.len() {
+            return if mark == Mark::NonBoundary {
+                Err(SudachiError::InvalidConstraint(format!(
+                    "text border {} can not be a non-boundary",
+                    idx
+                )))
+            } else {
+                Ok(())
+            };
+        }
+        match self.marks[idx] {
+            Mark::Free => {
+                self.marks[idx] = mark;
+                Ok(())
+            }
+            m if m == mark => Ok(()),
+            _ => Err(SudachiError::InvalidConstraint(format!(
+                "character {} is both a boundary and a non-boundary",
+                idx
+            ))),
+        }
+    }
+
+    /// Returns true if there are any constraints for the current input
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Checks if a node covering chars `[begin, end)` does not violate boundary constraints
+    #[inline]
+    pub fn allows_range(&self, begin: usize, end: usize) -> bool {
+        if !self.active {
+            return true;
+        }
+        self.marks[begin] != Mark::NonBoundary
+            && self.marks[end] != Mark::NonBoundary
+            && self.required_before[end] == self.required_before[begin + 1]
+    }
+
+    /// Returns part of speech which a node covering `[begin, end)` must have
+    #[inline]
+    pub fn required_pos(&self, begin: usize, end: usize) -> Option<u16> {
+        self.pos
+            .iter()
+            .find(|(b, e, _)| *b == begin && *e == end)
+            .map(|(_, _, p)| *p)
+    }
+}
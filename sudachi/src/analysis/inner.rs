@@ -19,23 +19,23 @@ use crate::dic::word_id::WordId;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct NodeIdx {
-    end: u16,
+    end: u32,
     index: u16,
 }
 
 impl NodeIdx {
     pub fn empty() -> NodeIdx {
         NodeIdx {
-            end: u16::MAX,
+            end: u32::MAX,
             index: u16::MAX,
         }
     }
 
-    pub fn new(end: u16, index: u16) -> NodeIdx {
+    pub fn new(end: u32, index: u16) -> NodeIdx {
         NodeIdx { end, index }
     }
 
-    pub fn end(&self) -> u16 {
+    pub fn end(&self) -> u32 {
         self.end
     }
 
@@ -46,8 +46,8 @@ impl NodeIdx {
 
 #[derive(Clone, Debug)]
 pub struct Node {
-    begin: u16,
-    end: u16,
+    begin: u32,
+    end: u32,
     left_id: u16,
     right_id: u16,
     cost: i16,
@@ -56,8 +56,8 @@ pub struct Node {
 
 impl Node {
     pub fn new(
-        begin: u16,
-        end: u16,
+        begin: u32,
+        end: u32,
         left_id: u16,
         right_id: u16,
         cost: i16,
@@ -73,10 +73,14 @@ impl Node {
         }
     }
 
-    pub fn set_range(&mut self, begin: u16, end: u16) {
+    pub fn set_range(&mut self, begin: u32, end: u32) {
         self.begin = begin;
         self.end = end;
     }
+
+    pub fn set_word_id(&mut self, word_id: WordId) {
+        self.word_id = word_id;
+    }
 }
 
 impl RightId for Node {
@@ -15,6 +15,9 @@
  */
 
 use crate::analysis::inner::{Node, NodeIdx};
+use crate::analysis::lattice_view::{
+    LatticeConnection, LatticeNodeKind, LatticeNodeView, LatticeView,
+};
 use crate::analysis::node::{LatticeNode, PathCost, RightId};
 use crate::dic::connect::ConnectionMatrix;
 use crate::dic::grammar::Grammar;
@@ -24,6 +27,8 @@ use crate::dic::word_id::WordId;
 use crate::error::SudachiResult;
 use crate::input_text::InputBuffer;
 use crate::prelude::SudachiError;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fmt::{Display, Formatter};
 use std::io::Write;
 
@@ -111,8 +116,8 @@ impl Lattice {
     /// Find EOS node -- finish the lattice construction
     pub fn connect_eos(&mut self, conn: &ConnectionMatrix) -> SudachiResult<()> {
         let len = self.size;
-        let eos_start = (len - 1) as u16;
-        let eos_end = (len - 1) as u16;
+        let eos_start = (len - 1) as u32;
+        let eos_end = (len - 1) as u32;
         let node = Node::new(eos_start, eos_end, 0, 0, 0, WordId::EOS);
         let (idx, cost) = self.connect_node(&node, conn);
         if cost == i32::MAX {
@@ -153,7 +158,7 @@ impl Lattice {
             let new_cost = l_node.total_cost() + connect_cost + node_cost;
             if new_cost < min_cost {
                 min_cost = new_cost;
-                prev_idx = NodeIdx::new(begin as u16, i as u16);
+                prev_idx = NodeIdx::new(begin as u32, i as u16);
             }
         }
 
@@ -194,9 +199,290 @@ impl Lattice {
             }
         }
     }
+
+    /// Compute marginal probabilities of the lattice nodes using the forward-backward algorithm.
+    /// The probability of a path is proportional to `exp(-cost / temperature)`.
+    ///
+    /// Results are laid out in the same way as the nodes: a probability of the node
+    /// with `idx` will be in `result[idx.end()][idx.index()]`.
+    pub fn fill_marginals(
+        &self,
+        conn: &ConnectionMatrix,
+        temperature: f32,
+        result: &mut Vec<Vec<f32>>,
+    ) {
+        Self::reset_vec(result, self.size);
+        if self.eos.is_none() {
+            return;
+        }
+
+        let scale = 1.0 / temperature as f64;
+        let empty = |ends: &Vec<VNode>| vec![f64::NEG_INFINITY; ends.len()];
+        let mut alpha: Vec<Vec<f64>> = self.ends[..self.size].iter().map(empty).collect();
+        let mut beta: Vec<Vec<f64>> = self.ends[..self.size].iter().map(empty).collect();
+
+        // forward pass, starting from BOS
+        alpha[0][0] = 0.0;
+        for end in 1..self.size {
+            for (i, r_node) in self.ends_full[end].iter().enumerate() {
+                let begin = r_node.begin();
+                let mut sum = f64::NEG_INFINITY;
+                for (j, l_node) in self.ends[begin].iter().enumerate() {
+                    let cost = conn.cost(l_node.right_id(), r_node.left_id()) as f64
+                        + r_node.cost() as f64;
+                    sum = log_add_exp(sum, alpha[begin][j] - cost * scale);
+                }
+                alpha[end][i] = sum;
+            }
+        }
+
+        // backward pass, starting from EOS (which has left_id = 0 and cost = 0)
+        let last = self.size - 1;
+        for (i, l_node) in self.ends[last].iter().enumerate() {
+            beta[last][i] = -(conn.cost(l_node.right_id(), 0) as f64) * scale;
+        }
+        for end in (1..self.size).rev() {
+            for (i, r_node) in self.ends_full[end].iter().enumerate() {
+                let suffix = beta[end][i] - r_node.cost() as f64 * scale;
+                if suffix == f64::NEG_INFINITY {
+                    continue;
+                }
+                let begin = r_node.begin();
+                for (j, l_node) in self.ends[begin].iter().enumerate() {
+                    let cost = conn.cost(l_node.right_id(), r_node.left_id()) as f64;
+                    beta[begin][j] = log_add_exp(beta[begin][j], suffix - cost * scale);
+                }
+            }
+        }
+
+        let log_z = beta[0][0];
+        for end in 0..self.size {
+            let probs = alpha[end].iter().zip(beta[end].iter()).map(|(a, b)| {
+                let p = (a + b - log_z).exp();
+                p.clamp(0.0, 1.0) as f32
+            });
+            result[end].extend(probs);
+        }
+    }
+
+    /// Create an iterator-like object which enumerates paths through the lattice
+    /// in the order of the increasing path cost.
+    /// The first returned path has the same cost as the path from [`Lattice::fill_top_path`].
+    pub fn n_best_paths<'a>(&'a self, conn: &'a ConnectionMatrix<'a>) -> NBestPaths<'a> {
+        NBestPaths::new(self, conn)
+    }
+}
+
+/// Computes `ln(exp(a) + exp(b))` without overflows
+#[inline]
+fn log_add_exp(a: f64, b: f64) -> f64 {
+    let (max, min) = if a > b { (a, b) } else { (b, a) };
+    if min == f64::NEG_INFINITY {
+        max
+    } else {
+        max + (min - max).exp().ln_1p()
+    }
+}
+
+/// Partial path of the n-best search: from the node to EOS
+struct NBestState {
+    idx: NodeIdx,
+    /// index of the next state towards EOS, `usize::MAX` for the EOS neighbors
+    next: usize,
+    /// cost of the path after the node, excluding the cost of the node itself
+    suffix_cost: i32,
+}
+
+/// Enumerates paths through the lattice from the best one.
+///
+/// Performs A* search from EOS to BOS.
+/// Viterbi path costs (from BOS to a node) are exact estimates of the remaining cost,
+/// so the paths are produced strictly in the order of their cost.
+pub struct NBestPaths<'a> {
+    lattice: &'a Lattice,
+    conn: &'a ConnectionMatrix<'a>,
+    states: Vec<NBestState>,
+    queue: BinaryHeap<Reverse<(i32, usize)>>,
+}
+
+impl<'a> NBestPaths<'a> {
+    fn new(lattice: &'a Lattice, conn: &'a ConnectionMatrix<'a>) -> Self {
+        let mut result = Self {
+            lattice,
+            conn,
+            states: Vec::new(),
+            queue: BinaryHeap::new(),
+        };
+        if lattice.eos.is_some() {
+            // EOS node has left_id = 0 and cost = 0
+            let end = lattice.size - 1;
+            for (i, l_node) in lattice.ends[end].iter().enumerate() {
+                let suffix_cost = conn.cost(l_node.right_id(), 0) as i32;
+                result.push(NodeIdx::new(end as u32, i as u16), usize::MAX, suffix_cost);
+            }
+        }
+        result
+    }
+
+    fn push(&mut self, idx: NodeIdx, next: usize, suffix_cost: i32) {
+        let vnode = &self.lattice.ends[idx.end() as usize][idx.index() as usize];
+        if !vnode.is_connected_to_bos() {
+            return;
+        }
+        let estimate = vnode.total_cost().saturating_add(suffix_cost);
+        self.queue.push(Reverse((estimate, self.states.len())));
+        self.states.push(NBestState {
+            idx,
+            next,
+            suffix_cost,
+        });
+    }
+
+    /// Fill the next best path (indices with path costs up to and including the node).
+    /// Unlike [`Lattice::fill_top_path`], the path is in the forward order.
+    /// Returns the total cost of the path or None if there are no paths left.
+    pub fn next_path(&mut self, result: &mut Vec<(NodeIdx, i32)>) -> Option<i32> {
+        while let Some(Reverse((total_cost, state_id))) = self.queue.pop() {
+            let state = &self.states[state_id];
+            let idx = state.idx;
+            if idx.end() == 0 {
+                // reached BOS
+                result.clear();
+                let mut sid = state.next;
+                while sid != usize::MAX {
+                    let s = &self.states[sid];
+                    result.push((s.idx, total_cost - s.suffix_cost));
+                    sid = s.next;
+                }
+                return Some(total_cost);
+            }
+
+            let (r_node, _) = self.lattice.node(idx);
+            let begin = r_node.begin();
+            let left_id = r_node.left_id();
+            let base_cost = state.suffix_cost + r_node.cost() as i32;
+            for i in 0..self.lattice.ends[begin].len() {
+                let l_node = &self.lattice.ends[begin][i];
+                let suffix_cost = base_cost + self.conn.cost(l_node.right_id(), left_id) as i32;
+                self.push(NodeIdx::new(begin as u32, i as u16), state_id, suffix_cost);
+            }
+        }
+        None
+    }
 }
 
 impl Lattice {
+    /// Create a structured view of all nodes of the lattice.
+    /// The lattice must be fully constructed (including EOS).
+    pub fn view(
+        &self,
+        input: &InputBuffer,
+        grammar: &Grammar,
+        lexicon: &LexiconSet,
+    ) -> SudachiResult<LatticeView> {
+        let conn = grammar.conn_matrix();
+        let eos_begin = self.size - 1;
+
+        // node id of the first node ending at the boundary
+        let mut offsets = Vec::with_capacity(self.size + 1);
+        let mut total = 0;
+        for nodes in &self.ends[..self.size] {
+            offsets.push(total);
+            total += nodes.len();
+        }
+        let eos_id = total;
+        let id_of = |idx: NodeIdx| offsets[idx.end() as usize] + idx.index() as usize;
+
+        let connections = |begin: usize, left_id: u16| -> Vec<LatticeConnection> {
+            self.ends[begin]
+                .iter()
+                .enumerate()
+                .filter(|(_, l)| l.is_connected_to_bos())
+                .map(|(i, l)| LatticeConnection {
+                    from: offsets[begin] + i,
+                    cost: conn.cost(l.right_id(), left_id),
+                })
+                .collect()
+        };
+
+        let mut nodes = Vec::with_capacity(total + 1);
+        nodes.push(LatticeNodeView {
+            id: 0,
+            kind: LatticeNodeKind::Bos,
+            begin: 0,
+            end: 0,
+            surface: String::new(),
+            word_id: WordId::BOS,
+            pos_id: None,
+            pos: Vec::new(),
+            left_id: 0,
+            right_id: 0,
+            word_cost: 0,
+            total_cost: Some(0),
+            best_prev: None,
+            connections: Vec::new(),
+            on_best_path: true,
+        });
+
+        for boundary in 1..self.size {
+            for (i, node) in self.ends_full[boundary].iter().enumerate() {
+                let (kind, pos_id) = if node.is_oov() {
+                    (LatticeNodeKind::Oov, node.word_id().word() as u16)
+                } else {
+                    let winfo = lexicon.get_word_info_subset(node.word_id(), InfoSubset::POS_ID)?;
+                    (LatticeNodeKind::Word, winfo.pos_id())
+                };
+                let total_cost = self.ends[boundary][i].total_cost;
+                let connected = total_cost != i32::MAX;
+                nodes.push(LatticeNodeView {
+                    id: nodes.len(),
+                    kind,
+                    begin: node.begin(),
+                    end: node.end(),
+                    surface: input.orig_slice_c(node.begin()..node.end()).to_owned(),
+                    word_id: node.word_id(),
+                    pos_id: Some(pos_id),
+                    pos: grammar.pos_list[pos_id as usize].clone(),
+                    left_id: node.left_id(),
+                    right_id: node.right_id(),
+                    word_cost: node.cost(),
+                    total_cost: connected.then_some(total_cost),
+                    best_prev: connected.then(|| id_of(self.indices[boundary][i])),
+                    connections: connections(node.begin(), node.left_id()),
+                    on_best_path: false,
+                });
+            }
+        }
+
+        nodes.push(LatticeNodeView {
+            id: eos_id,
+            kind: LatticeNodeKind::Eos,
+            begin: eos_begin,
+            end: eos_begin,
+            surface: String::new(),
+            word_id: WordId::EOS,
+            pos_id: None,
+            pos: Vec::new(),
+            left_id: 0,
+            right_id: 0,
+            word_cost: 0,
+            total_cost: self.eos.map(|(_, c)| c),
+            best_prev: self.eos.map(|(idx, _)| id_of(idx)),
+            connections: connections(eos_begin, 0),
+            on_best_path: self.eos.is_some(),
+        });
+
+        if self.eos.is_some() {
+            let mut prev = nodes[eos_id].best_prev;
+            while let Some(id) = prev {
+                nodes[id].on_best_path = true;
+                prev = nodes[id].best_prev;
+            }
+        }
+
+        Ok(LatticeView::new(nodes))
+    }
+
     pub fn dump<W: Write>(
         &self,
         input: &InputBuffer,
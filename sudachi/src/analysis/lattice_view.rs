@@ -0,0 +1,273 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::dic::word_id::WordId;
+use crate::error::SudachiResult;
+
+/// Kind of the lattice node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatticeNodeKind {
+    Bos,
+    Eos,
+    /// Word from one of the dictionaries
+    Word,
+    /// Word created by an OOV provider plugin
+    Oov,
+}
+
+impl LatticeNodeKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LatticeNodeKind::Bos => "bos",
+            LatticeNodeKind::Eos => "eos",
+            LatticeNodeKind::Word => "word",
+            LatticeNodeKind::Oov => "oov",
+        }
+    }
+}
+
+/// Connection from a preceding node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatticeConnection {
+    /// Id of the preceding node
+    pub from: usize,
+    /// Connection cost between the preceding node and this node
+    pub cost: i16,
+}
+
+/// Information about a single lattice node
+#[derive(Clone, Debug)]
+pub struct LatticeNodeView {
+    /// Index of the node in [`LatticeView::nodes`]
+    pub id: usize,
+    pub kind: LatticeNodeKind,
+    /// Begin of the node, in chars of the analyzed (modified by input text plugins) text
+    pub begin: usize,
+    /// End of the node, in chars of the analyzed (modified by input text plugins) text
+    pub end: usize,
+    /// Original text of the node, empty for BOS and EOS
+    pub surface: String,
+    pub word_id: WordId,
+    /// Part of speech id, None for BOS and EOS
+    pub pos_id: Option<u16>,
+    /// Part of speech components, empty for BOS and EOS
+    pub pos: Vec<String>,
+    pub left_id: u16,
+    pub right_id: u16,
+    /// Cost of the word itself
+    pub word_cost: i16,
+    /// Cost of the best path from BOS to this node (including it),
+    /// None if the node is not reachable from BOS
+    pub total_cost: Option<i32>,
+    /// Id of the predecessor on the best path from BOS
+    pub best_prev: Option<usize>,
+    /// Connections from all reachable preceding nodes
+    pub connections: Vec<LatticeConnection>,
+    /// The node is a part of the best path
+    pub on_best_path: bool,
+}
+
+/// Output format for [`LatticeView`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LatticeFormat {
+    Json,
+    /// Graphviz DOT
+    Dot,
+}
+
+impl FromStr for LatticeFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(LatticeFormat::Json),
+            "dot" => Ok(LatticeFormat::Dot),
+            _ => Err("lattice format must be one of \"json\" or \"dot\""),
+        }
+    }
+}
+
+impl Display for LatticeFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let repr = match self {
+            LatticeFormat::Json => "json",
+            LatticeFormat::Dot => "dot",
+        };
+        f.write_str(repr)
+    }
+}
+
+/// Snapshot of all lattice nodes, created by [`crate::analysis::lattice::Lattice::view`].
+///
+/// The first node is BOS and the last node is EOS, other nodes are
+/// ordered by their end and then by their insertion order.
+/// The view is empty if the input was empty.
+#[derive(Clone, Debug, Default)]
+pub struct LatticeView {
+    nodes: Vec<LatticeNodeView>,
+}
+
+impl LatticeView {
+    pub(crate) fn new(nodes: Vec<LatticeNodeView>) -> LatticeView {
+        LatticeView { nodes }
+    }
+
+    pub fn nodes(&self) -> &[LatticeNodeView] {
+        &self.nodes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Nodes of the best path, without BOS and EOS
+    pub fn best_path(&self) -> impl Iterator<Item = &LatticeNodeView> + '_ {
+        self.nodes.iter().filter(|n| {
+            n.on_best_path && !matches!(n.kind, LatticeNodeKind::Bos | LatticeNodeKind::Eos)
+        })
+    }
+
+    pub fn write<W: Write>(&self, format: LatticeFormat, out: &mut W) -> SudachiResult<()> {
+        match format {
+            LatticeFormat::Json => self.write_json(out),
+            LatticeFormat::Dot => self.write_dot(out),
+        }
+    }
+
+    /// Write the lattice as a single-line JSON object
+    pub fn write_json<W: Write>(&self, out: &mut W) -> SudachiResult<()> {
+        serde_json::to_writer(&mut *out, &self.to_json())?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Value {
+        let nodes: Vec<Value> = self
+            .nodes
+            .iter()
+            .map(|n| {
+                let word_id = match n.kind {
+                    LatticeNodeKind::Bos | LatticeNodeKind::Eos => Value::Null,
+                    LatticeNodeKind::Oov => json!([-1, n.word_id.word()]),
+                    LatticeNodeKind::Word => json!([n.word_id.dic(), n.word_id.word()]),
+                };
+                let connections: Vec<Value> = n
+                    .connections
+                    .iter()
+                    .map(|c| json!({"from": c.from, "cost": c.cost}))
+                    .collect();
+                json!({
+                    "id": n.id,
+                    "kind": n.kind.as_str(),
+                    "begin": n.begin,
+                    "end": n.end,
+                    "surface": n.surface,
+                    "word_id": word_id,
+                    "pos_id": n.pos_id,
+                    "pos": n.pos,
+                    "left_id": n.left_id,
+                    "right_id": n.right_id,
+                    "word_cost": n.word_cost,
+                    "total_cost": n.total_cost,
+                    "best_prev": n.best_prev,
+                    "on_best_path": n.on_best_path,
+                    "connections": connections,
+                })
+            })
+            .collect();
+        json!({ "nodes": nodes })
+    }
+
+    /// Write the lattice as a Graphviz DOT digraph.
+    /// Nodes and edges of the best path are highlighted.
+    pub fn write_dot<W: Write>(&self, out: &mut W) -> SudachiResult<()> {
+        writeln!(out, "digraph lattice {{")?;
+        writeln!(out, "  rankdir=LR;")?;
+        writeln!(out, "  node [shape=box];")?;
+        for n in self.nodes.iter() {
+            let label = match n.kind {
+                LatticeNodeKind::Bos => "BOS".to_owned(),
+                LatticeNodeKind::Eos => format!("EOS\\ntotal={}", OptCost(n.total_cost)),
+                _ => format!(
+                    "{}\\n{}\\n{} [{}, {})\\nl={} r={} cost={} total={}",
+                    escape_dot(&n.surface),
+                    escape_dot(&n.pos.join(",")),
+                    n.word_id,
+                    n.begin,
+                    n.end,
+                    n.left_id,
+                    n.right_id,
+                    n.word_cost,
+                    OptCost(n.total_cost)
+                ),
+            };
+            let style = if n.on_best_path {
+                ", color=red, penwidth=2"
+            } else {
+                ""
+            };
+            writeln!(out, "  n{} [label=\"{}\"{}];", n.id, label, style)?;
+        }
+        for n in self.nodes.iter() {
+            for c in n.connections.iter() {
+                let style = if n.on_best_path && n.best_prev == Some(c.from) {
+                    ", color=red, penwidth=2"
+                } else {
+                    ""
+                };
+                writeln!(
+                    out,
+                    "  n{} -> n{} [label=\"{}\"{}];",
+                    c.from, n.id, c.cost, style
+                )?;
+            }
+        }
+        writeln!(out, "}}")?;
+        Ok(())
+    }
+}
+
+struct OptCost(Option<i32>);
+
+impl Display for OptCost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(c) => write!(f, "{}", c),
+            None => f.write_str("inf"),
+        }
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\n' => result.push_str("\\n"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
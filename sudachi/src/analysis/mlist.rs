@@ -30,6 +30,8 @@ use std::rc::Rc;
 struct InputPart {
     input: InputBuffer,
     subset: InfoSubset,
+    /// Offset of the input in a larger text: (bytes, chars)
+    offset: (usize, usize),
 }
 
 impl Default for InputPart {
@@ -39,6 +41,7 @@ impl Default for InputPart {
         Self {
             input,
             subset: Default::default(),
+            offset: (0, 0),
         }
     }
 }
@@ -78,7 +81,11 @@ impl<T: DictionaryAccess> MorphemeList<T> {
         path: Vec<ResultNode>,
         subset: InfoSubset,
     ) -> Self {
-        let input = InputPart { input, subset };
+        let input = InputPart {
+            input,
+            subset,
+            offset: (0, 0),
+        };
         Self {
             dict,
             input: Rc::new(RefCell::new(input)),
@@ -94,6 +101,7 @@ impl<T: DictionaryAccess> MorphemeList<T> {
             Ok(mut i) => {
                 let mref = i.deref_mut();
                 analyzer.swap_result(&mut mref.input, self.nodes.mut_data(), &mut mref.subset);
+                mref.offset = (0, 0);
                 Ok(())
             }
             Err(_) => Err(SudachiError::MorphemeListBorrowed),
@@ -186,6 +194,25 @@ impl<T: DictionaryAccess> MorphemeList<T> {
         self.input.deref().borrow().subset
     }
 
+    /// Returns offset of the analyzed text in a larger text as (bytes, chars).
+    /// Morpheme offsets include it.
+    pub fn offset(&self) -> (usize, usize) {
+        self.input.deref().borrow().offset
+    }
+
+    /// Sets offset of the analyzed text in a larger text, in bytes and chars.
+    /// It will be added to the offsets of morphemes.
+    /// The offset is reset when the list takes new input, e.g. in [`MorphemeList::collect_results`].
+    pub fn set_offset(&mut self, bytes: usize, chars: usize) -> SudachiResult<()> {
+        match self.input.try_borrow_mut() {
+            Ok(mut i) => {
+                i.offset = (bytes, chars);
+                Ok(())
+            }
+            Err(_) => Err(SudachiError::MorphemeListBorrowed),
+        }
+    }
+
     pub fn copy_slice(&self, start: usize, end: usize, out: &mut Self) {
         let out_data = out.nodes.mut_data();
         out_data.extend_from_slice(&self.nodes.data[start..end]);
@@ -193,7 +220,9 @@ impl<T: DictionaryAccess> MorphemeList<T> {
 
     pub fn lookup(&mut self, query: &str, subset: InfoSubset) -> SudachiResult<usize> {
         let end_chars = {
-            let input = &mut self.input.borrow_mut().input;
+            let part = &mut *self.input.borrow_mut();
+            part.offset = (0, 0);
+            let input = &mut part.input;
             input.reset().push_str(query);
             input.start_build()?;
             input.build(self.dict.grammar())?;
@@ -226,6 +255,15 @@ impl<T: DictionaryAccess + Clone> MorphemeList<T> {
         }
     }
 
+    /// Creates a new morpheme list with the given nodes which shares the input with this one
+    pub(crate) fn with_shared_input(&self, path: Vec<ResultNode>) -> Self {
+        Self {
+            dict: self.dict.clone(),
+            input: self.input.clone(),
+            nodes: Nodes { data: path },
+        }
+    }
+
     /// Returns a new morpheme list splitting the morpheme with a given mode.
     /// Returns an empty list if there was no splits
     #[deprecated(note = "use split_into", since = "0.6.1")]
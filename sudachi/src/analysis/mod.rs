@@ -18,17 +18,23 @@ use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 use mlist::MorphemeList;
+use stateless_tokenizer::DictionaryAccess;
 
 use crate::error::SudachiResult;
 
+pub mod batch_tokenizer;
+pub mod constraints;
 pub mod created;
 mod inner;
 pub mod lattice;
+pub mod lattice_view;
 pub mod mlist;
 pub mod morpheme;
 pub mod node;
+pub mod numeric;
 pub mod stateful_tokenizer;
 pub mod stateless_tokenizer;
+pub mod streaming_tokenizer;
 
 pub use inner::Node;
 
@@ -102,4 +108,27 @@ pub trait Tokenize {
         mode: Mode,
         enable_debug: bool,
     ) -> SudachiResult<MorphemeList<Self::Dictionary>>;
+
+    /// Break text into at most `n` distinct analysis results with their path costs,
+    /// starting from the best one
+    ///
+    /// The default implementation returns only the result of [`Tokenize::tokenize`]
+    /// with its [internal cost](MorphemeList::get_internal_cost).
+    fn tokenize_n_best(
+        &self,
+        input: &str,
+        mode: Mode,
+        n: usize,
+        enable_debug: bool,
+    ) -> SudachiResult<Vec<(MorphemeList<Self::Dictionary>, i32)>>
+    where
+        Self::Dictionary: DictionaryAccess,
+    {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let result = self.tokenize(input, mode, enable_debug)?;
+        let cost = result.get_internal_cost();
+        Ok(vec![(result, cost)])
+    }
 }
@@ -15,6 +15,7 @@
  */
 
 use crate::analysis::node::{LatticeNode, PathCost, ResultNode};
+use crate::analysis::numeric::NumericValue;
 use crate::analysis::stateless_tokenizer::DictionaryAccess;
 use crate::dic::lexicon::word_infos::WordInfo;
 use crate::dic::word_id::WordId;
@@ -59,22 +60,22 @@ impl<'a, T: DictionaryAccess> Morpheme<'a, T> {
 
     /// Returns the begin index in bytes of the morpheme in the original text
     pub fn begin(&self) -> usize {
-        self.list.input().to_orig_byte_idx(self.node().begin())
+        self.list.input().to_orig_byte_idx(self.node().begin()) + self.list.offset().0
     }
 
     /// Returns the end index in bytes of the morpheme in the original text
     pub fn end(&self) -> usize {
-        self.list.input().to_orig_byte_idx(self.node().end())
+        self.list.input().to_orig_byte_idx(self.node().end()) + self.list.offset().0
     }
 
     /// Returns the codepoint offset of the morpheme begin in the original text
     pub fn begin_c(&self) -> usize {
-        self.list.input().to_orig_char_idx(self.node().begin())
+        self.list.input().to_orig_char_idx(self.node().begin()) + self.list.offset().1
     }
 
     /// Returns the codepoint offset of the morpheme begin in the original text
     pub fn end_c(&self) -> usize {
-        self.list.input().to_orig_char_idx(self.node().end())
+        self.list.input().to_orig_char_idx(self.node().end()) + self.list.offset().1
     }
 
     /// Returns a substring of the original text which corresponds to the morpheme
@@ -154,6 +155,27 @@ impl<'a, T: DictionaryAccess> Morpheme<'a, T> {
     pub fn total_cost(&self) -> i32 {
         return self.node().total_cost();
     }
+
+    /// Returns the probability of this morpheme among all analyses of the sentence,
+    /// computed from the lattice with the forward-backward algorithm.
+    ///
+    /// Unlike [`Morpheme::total_cost`], it is comparable across sentences.
+    /// Morphemes created by joining several lattice nodes get the smallest
+    /// confidence of the parts; split morphemes get the confidence of the whole word.
+    ///
+    /// Returns None if the tokenizer was not configured to compute confidence,
+    /// see [`StatefulTokenizer::set_confidence_temperature`](crate::analysis::stateful_tokenizer::StatefulTokenizer::set_confidence_temperature).
+    pub fn confidence(&self) -> Option<f32> {
+        self.node().confidence()
+    }
+
+    /// Returns the parsed numeric value and unit of the morpheme.
+    ///
+    /// Values are present for numbers recognized by `JoinNumericPlugin`,
+    /// units only when it is configured to join them (`joinUnits`).
+    pub fn numeric_value(&self) -> Option<&NumericValue> {
+        self.node().numeric_value()
+    }
 }
 
 impl<T: DictionaryAccess> std::fmt::Debug for Morpheme<'_, T> {
@@ -17,8 +17,10 @@
 use std::fmt;
 use std::iter::FusedIterator;
 use std::ops::Range;
+use std::sync::Arc;
 
 use crate::analysis::inner::Node;
+use crate::analysis::numeric::NumericValue;
 use crate::dic::lexicon::word_infos::{WordInfo, WordInfoData};
 use crate::dic::lexicon_set::LexiconSet;
 use crate::dic::subset::InfoSubset;
@@ -82,17 +84,20 @@ pub trait LatticeNode: RightId {
 pub struct ResultNode {
     inner: Node,
     total_cost: i32,
-    begin_bytes: u16,
-    end_bytes: u16,
+    begin_bytes: u32,
+    end_bytes: u32,
     word_info: WordInfo,
+    confidence: Option<f32>,
+    numeric_value: Option<Box<NumericValue>>,
+    parts: Option<Arc<[ResultNode]>>,
 }
 
 impl ResultNode {
     pub fn new(
         inner: Node,
         total_cost: i32,
-        begin_bytes: u16,
-        end_bytes: u16,
+        begin_bytes: u32,
+        end_bytes: u32,
         word_info: WordInfo,
     ) -> ResultNode {
         ResultNode {
@@ -101,6 +106,9 @@ impl ResultNode {
             begin_bytes,
             end_bytes,
             word_info,
+            confidence: None,
+            numeric_value: None,
+            parts: None,
         }
     }
 }
@@ -159,17 +167,73 @@ impl ResultNode {
         self.begin_bytes()..self.end_bytes()
     }
 
-    pub fn set_bytes_range(&mut self, begin: u16, end: u16) {
+    pub fn set_bytes_range(&mut self, begin: u32, end: u32) {
         self.begin_bytes = begin;
         self.end_bytes = end;
     }
 
-    pub fn set_char_range(&mut self, begin: u16, end: u16) {
+    pub fn set_char_range(&mut self, begin: u32, end: u32) {
         self.inner.set_range(begin, end)
     }
 
-    /// Returns number of splits in a specified mode
+    /// Returns the marginal probability of the node in the lattice.
+    /// It is present only if the tokenizer was configured to compute it.
+    pub fn confidence(&self) -> Option<f32> {
+        self.confidence
+    }
+
+    /// Replaces the reading form of the node
+    pub(crate) fn set_reading_form(&mut self, reading_form: String) {
+        let mut data: WordInfoData = std::mem::take(&mut self.word_info).into();
+        data.reading_form = reading_form;
+        self.word_info = data.into();
+    }
+
+    /// Replaces the part of speech of the node
+    pub(crate) fn set_pos_id(&mut self, pos_id: u16) {
+        let mut data: WordInfoData = std::mem::take(&mut self.word_info).into();
+        data.pos_id = pos_id;
+        self.word_info = data.into();
+    }
+
+    pub fn set_confidence(&mut self, confidence: Option<f32>) {
+        self.confidence = confidence;
+    }
+
+    /// Returns the parsed value of the number, if the node is a number joined by `JoinNumericPlugin`
+    pub fn numeric_value(&self) -> Option<&NumericValue> {
+        self.numeric_value.as_deref()
+    }
+
+    pub(crate) fn set_numeric_value(&mut self, value: Option<NumericValue>) {
+        self.numeric_value = value.map(Box::new);
+    }
+
+    /// Returns the nodes which were joined into this node by a plugin which keeps them,
+    /// empty for other nodes
+    pub fn parts(&self) -> &[ResultNode] {
+        self.parts.as_deref().unwrap_or(&[])
+    }
+
+    /// Sets the nodes which this node consists of, they are returned by [`ResultNode::split`]
+    pub(crate) fn set_parts(&mut self, parts: Arc<[ResultNode]>) {
+        self.parts = Some(parts);
+    }
+
+    /// Confidence of the node made from the nodes in the range:
+    /// the smallest confidence of the parts, which is an upper bound of their joint probability
+    fn joined_confidence(path: &[ResultNode]) -> Option<f32> {
+        path.iter()
+            .map(|n| n.confidence)
+            .try_fold(1.0f32, |acc, c| c.map(|c| acc.min(c)))
+    }
+
+    /// Returns number of splits in a specified mode.
+    /// Nodes with parts are split into their parts in A and B modes.
     pub fn num_splits(&self, mode: Mode) -> usize {
+        if mode != Mode::C && !self.parts().is_empty() {
+            return self.parts().len();
+        }
         match mode {
             Mode::A => self.word_info.a_unit_split().len(),
             Mode::B => self.word_info.b_unit_split().len(),
@@ -177,7 +241,8 @@ impl ResultNode {
         }
     }
 
-    /// Split the node with a specified mode using the dictionary data
+    /// Split the node with a specified mode using the dictionary data.
+    /// Nodes with parts are split into their parts, which are split further in the same mode.
     pub fn split<'a>(
         &'a self,
         mode: Mode,
@@ -191,16 +256,27 @@ impl ResultNode {
             Mode::C => panic!("splitting Node with Mode::C is not supported"),
         };
 
+        let mut parts = Vec::new();
+        for part in self.parts() {
+            if part.num_splits(mode) > 1 {
+                parts.extend(part.split(mode, lexicon, subset, text));
+            } else {
+                parts.push(part.clone());
+            }
+        }
+
         NodeSplitIterator {
+            parts: parts.into_iter(),
             splits,
+            confidence: self.confidence,
             index: 0,
             lexicon,
             subset,
             text,
             byte_offset: self.begin_bytes,
             byte_end: self.end_bytes,
-            char_offset: self.begin() as u16,
-            char_end: self.end() as u16,
+            char_offset: self.begin() as u32,
+            char_end: self.end() as u32,
         }
     }
 }
@@ -223,15 +299,17 @@ impl fmt::Display for ResultNode {
 }
 
 pub struct NodeSplitIterator<'a> {
+    parts: std::vec::IntoIter<ResultNode>,
     splits: &'a [WordId],
+    confidence: Option<f32>,
     lexicon: &'a LexiconSet<'a>,
     index: usize,
     subset: InfoSubset,
     text: &'a InputBuffer,
-    char_offset: u16,
-    byte_offset: u16,
-    char_end: u16,
-    byte_end: u16,
+    char_offset: u32,
+    byte_offset: u32,
+    char_end: u32,
+    byte_end: u32,
 }
 
 impl Iterator for NodeSplitIterator<'_> {
@@ -239,6 +317,10 @@ impl Iterator for NodeSplitIterator<'_> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(part) = self.parts.next() {
+            return Some(part);
+        }
+
         let idx = self.index;
         if idx >= self.splits.len() {
             return None;
@@ -259,7 +341,7 @@ impl Iterator for NodeSplitIterator<'_> {
         } else {
             let byte_end = byte_start as usize + word_info.head_word_length();
             let char_end = self.text.ch_idx(byte_end);
-            (char_end as u16, byte_end as u16)
+            (char_end as u32, byte_end as u32)
         };
 
         self.char_offset = char_end;
@@ -267,7 +349,8 @@ impl Iterator for NodeSplitIterator<'_> {
 
         let inner = Node::new(char_start, char_end, u16::MAX, u16::MAX, i16::MAX, word_id);
 
-        let node = ResultNode::new(inner, i32::MAX, byte_start, byte_end, word_info);
+        let mut node = ResultNode::new(inner, i32::MAX, byte_start, byte_end, word_info);
+        node.confidence = self.confidence;
 
         self.index += 1;
         Some(node)
@@ -275,7 +358,8 @@ impl Iterator for NodeSplitIterator<'_> {
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.splits.len(), Some(self.splits.len()))
+        let len = self.parts.len() + self.splits.len() - self.index;
+        (len, Some(len))
     }
 }
 
@@ -330,21 +414,22 @@ pub fn concat_nodes(
     };
 
     let inner = Node::new(
-        path[begin].begin() as u16,
-        path[end - 1].end() as u16,
+        path[begin].begin() as u32,
+        path[end - 1].end() as u32,
         u16::MAX,
         u16::MAX,
         i16::MAX,
         WordId::INVALID,
     );
 
-    let node = ResultNode::new(
+    let mut node = ResultNode::new(
         inner,
         path[end - 1].total_cost,
         path[begin].begin_bytes,
         path[end - 1].end_bytes,
         new_wi.into(),
     );
+    node.confidence = ResultNode::joined_confidence(&path[begin..end]);
 
     path[begin] = node;
     path.drain(begin + 1..end);
@@ -367,14 +452,27 @@ pub fn concat_oov_nodes(
     let mut surface = String::with_capacity(capa);
     let mut head_word_length: u16 = 0;
     let mut wid = WordId::from_raw(0);
+    // readings are joined only when OOV parts have estimated readings
+    let mut has_readings = true;
 
     for node in path[begin..end].iter() {
         let data = node.word_info().borrow_data();
         surface.push_str(&data.surface);
         head_word_length += data.head_word_length;
         wid = wid.max(node.word_id());
+        has_readings &= !data.reading_form.is_empty();
     }
 
+    let reading_form = if has_readings && wid.is_oov() {
+        let mut reading = String::with_capacity(capa * 2);
+        for node in path[begin..end].iter() {
+            reading.push_str(node.word_info().reading_form());
+        }
+        reading
+    } else {
+        String::new()
+    };
+
     if !wid.is_oov() {
         wid = WordId::new(wid.dic(), WordId::MAX_WORD);
     }
@@ -385,26 +483,28 @@ pub fn concat_oov_nodes(
         surface,
         head_word_length,
         pos_id,
+        reading_form,
         dictionary_form_word_id: -1,
         ..Default::default()
     };
 
     let inner = Node::new(
-        path[begin].begin() as u16,
-        path[end - 1].end() as u16,
+        path[begin].begin() as u32,
+        path[end - 1].end() as u32,
         u16::MAX,
         u16::MAX,
         i16::MAX,
         wid,
     );
 
-    let node = ResultNode::new(
+    let mut node = ResultNode::new(
         inner,
         path[end - 1].total_cost,
         path[begin].begin_bytes,
         path[end - 1].end_bytes,
         new_wi.into(),
     );
+    node.confidence = ResultNode::joined_confidence(&path[begin..end]);
 
     path[begin] = node;
     path.drain(begin + 1..end);
@@ -0,0 +1,107 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+/// Parsed value of a numeric morpheme, with its unit if it was joined with one.
+///
+/// Values are produced by `JoinNumericPlugin`: "三万円" has the value 30000 and the unit "円",
+/// "1,234.5km" has the value 1234.5 and the unit "km".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumericValue {
+    value: String,
+    unit: Option<String>,
+}
+
+impl NumericValue {
+    /// Creates the value from a decimal number: digits with an optional fractional part.
+    /// Returns None if `value` is not a decimal number.
+    pub fn new(value: String, unit: Option<String>) -> Option<NumericValue> {
+        let (int, frac) = match value.split_once('.') {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (value.as_str(), None),
+        };
+        let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if is_digits(int) && frac.map_or(true, is_digits) {
+            Some(NumericValue { value, unit })
+        } else {
+            None
+        }
+    }
+
+    /// Exact decimal representation of the value without grouping separators,
+    /// e.g. "30000" or "1234.5". Leading zeros of the original text are kept: "007"
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    /// The value as an integer, None if it has a fractional part or does not fit into i64
+    pub fn to_i64(&self) -> Option<i64> {
+        self.value.parse().ok()
+    }
+
+    /// The value as a floating point number, which can be inexact for large values
+    pub fn to_f64(&self) -> f64 {
+        // decimal numbers always parse, too large ones become infinity
+        self.value.parse().unwrap_or(f64::NAN)
+    }
+
+    /// Normalized form of the unit which follows the number: "円", "km", "%"
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let v = NumericValue::new("30000".to_owned(), Some("人".to_owned())).unwrap();
+        assert_eq!("30000", v.as_str());
+        assert_eq!(Some(30000), v.to_i64());
+        assert_eq!(30000.0, v.to_f64());
+        assert_eq!(Some("人"), v.unit());
+
+        let v = NumericValue::new("1234.5".to_owned(), None).unwrap();
+        assert_eq!(None, v.to_i64());
+        assert_eq!(1234.5, v.to_f64());
+        assert_eq!(None, v.unit());
+
+        let v = NumericValue::new("99999999999999999999".to_owned(), None).unwrap();
+        assert_eq!(None, v.to_i64());
+        assert_eq!(1e20, v.to_f64());
+    }
+
+    #[test]
+    fn only_decimal_numbers() {
+        for value in ["0", "007", "1.5", "0.25"] {
+            assert!(
+                NumericValue::new(value.to_owned(), None).is_some(),
+                "{}",
+                value
+            );
+        }
+        for value in [
+            "", "1.", ".5", "1.2.3", "-1", "+1", "1e5", "inf", "NaN", "1,000", "１",
+        ] {
+            assert!(
+                NumericValue::new(value.to_owned(), None).is_none(),
+                "{}",
+                value
+            );
+        }
+    }
+}
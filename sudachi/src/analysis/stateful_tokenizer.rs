@@ -14,17 +14,23 @@
  *  limitations under the License.
  */
 
+use std::ops::Range;
+
+use crate::analysis::constraints::{Constraints, ResolvedConstraints};
 use crate::analysis::created::CreatedWords;
 use crate::analysis::inner::{Node, NodeIdx};
 use crate::analysis::lattice::Lattice;
+use crate::analysis::lattice_view::LatticeView;
 use crate::analysis::node::{LatticeNode, ResultNode};
 use crate::analysis::stateless_tokenizer::{dump_path, split_path, DictionaryAccess};
 use crate::analysis::Mode;
+use crate::config::ConfigError;
 use crate::dic::category_type::CategoryType;
 use crate::dic::connect::ConnectionMatrix;
 use crate::dic::lexicon::word_infos::WordInfoData;
 use crate::dic::lexicon_set::LexiconSet;
 use crate::dic::subset::InfoSubset;
+use crate::dic::word_id::WordId;
 use crate::error::{SudachiError, SudachiResult};
 use crate::input_text::InputBuffer;
 use crate::input_text::InputTextIndex;
@@ -41,6 +47,11 @@ pub struct StatefulTokenizer<D> {
     top_path_ids: Vec<NodeIdx>,
     top_path: Option<Vec<ResultNode>>,
     subset: InfoSubset,
+    confidence_temperature: Option<f32>,
+    marginals: Vec<Vec<f32>>,
+    constraints: Constraints,
+    resolved_constraints: ResolvedConstraints,
+    single_words: Vec<Range<usize>>,
 }
 
 impl<D: DictionaryAccess + Clone> StatefulTokenizer<D> {
@@ -48,8 +59,73 @@ impl<D: DictionaryAccess + Clone> StatefulTokenizer<D> {
     pub fn dict_clone(&self) -> D {
         self.dictionary.clone()
     }
+
+    /// Analyze the current data and return at most `n` distinct analysis results
+    /// with their total path costs, starting from the best one.
+    ///
+    /// Path rewrite plugins are applied to each candidate.
+    /// Lattice paths which produce the same morphemes after rewriting are reported once.
+    /// All returned lists share the input of the tokenizer, so the tokenizer
+    /// must be [`reset`](StatefulTokenizer::reset) before the next analysis.
+    pub fn n_best(&mut self, n: usize) -> SudachiResult<Vec<(MorphemeList<D>, i32)>> {
+        if let Some(p) = self.top_path.as_mut() {
+            p.clear()
+        }
+
+        if n == 0 || !self.prepare_lattice()? {
+            return Ok(Vec::new());
+        }
+
+        let mut candidates = Vec::with_capacity(n);
+        let mut seen: Vec<Vec<(usize, usize, WordId, u16)>> = Vec::with_capacity(n);
+        let mut path_ids = Vec::new();
+        let conn = self.dictionary.grammar().conn_matrix();
+        let mut paths = self.lattice.n_best_paths(conn);
+        let max_paths = n.saturating_mul(N_BEST_SEARCH_FACTOR);
+        for _ in 0..max_paths {
+            let cost = match paths.next_path(&mut path_ids) {
+                None => break,
+                Some(c) => c,
+            };
+            let mut path = Vec::with_capacity(path_ids.len());
+            for &(pid, node_cost) in path_ids.iter() {
+                path.push(self.resolve_node(pid, node_cost)?);
+            }
+            let path = self.rewrite_path(path)?;
+            let key: Vec<_> = path
+                .iter()
+                .map(|n| (n.begin(), n.end(), n.word_id(), n.word_info().pos_id()))
+                .collect();
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            candidates.push((path, cost));
+            if candidates.len() == n {
+                break;
+            }
+        }
+
+        let input = std::mem::take(&mut self.input);
+        let mut result = Vec::with_capacity(candidates.len());
+        let mut iter = candidates.into_iter();
+        if let Some((path, cost)) = iter.next() {
+            let first =
+                MorphemeList::from_components(self.dictionary.clone(), input, path, self.subset);
+            for (path, cost) in iter {
+                result.push((first.with_shared_input(path), cost));
+            }
+            result.insert(0, (first, cost));
+        }
+        Ok(result)
+    }
 }
 
+/// N-best search stops after looking at `n * N_BEST_SEARCH_FACTOR` lattice paths.
+/// Many lattice paths can produce the same result after rewriting
+/// (e.g. numbers joined by JoinNumericPlugin), so this bounds the search time.
+const N_BEST_SEARCH_FACTOR: usize = 16;
+
 impl<D: DictionaryAccess> StatefulTokenizer<D> {
     /// Create a new non-debug stateful tokenizer
     pub fn new(dic: D, mode: Mode) -> Self {
@@ -68,6 +144,11 @@ impl<D: DictionaryAccess> StatefulTokenizer<D> {
             top_path_ids: Vec::new(),
             top_path: Some(Vec::new()),
             subset: InfoSubset::all(),
+            confidence_temperature: None,
+            marginals: Vec::new(),
+            constraints: Constraints::default(),
+            resolved_constraints: ResolvedConstraints::default(),
+            single_words: Vec::new(),
         }
     }
 
@@ -102,6 +183,46 @@ impl<D: DictionaryAccess> StatefulTokenizer<D> {
         std::mem::replace(&mut self.subset, new_subset | mode_subset)
     }
 
+    /// Enable computation of morpheme confidence (marginal probabilities) with the given
+    /// temperature, or disable it with None. Returns the current temperature.
+    ///
+    /// Path probabilities are proportional to `exp(-cost / temperature)`,
+    /// so higher temperatures make the distribution flatter.
+    /// Confidence is disabled by default because it has additional analysis cost.
+    ///
+    /// Returns an error and keeps the current temperature if the temperature
+    /// is not a finite positive number.
+    pub fn set_confidence_temperature(
+        &mut self,
+        temperature: Option<f32>,
+    ) -> SudachiResult<Option<f32>> {
+        if let Some(t) = temperature {
+            if !(t.is_finite() && t > 0.0) {
+                return Err(SudachiError::ConfigError(ConfigError::InvalidValue(
+                    "confidence temperature".to_owned(),
+                    format!("{} is not a finite positive number", t),
+                )));
+            }
+        }
+        Ok(std::mem::replace(
+            &mut self.confidence_temperature,
+            temperature,
+        ))
+    }
+
+    /// Set constraints (partial annotation) for the current data and return the previous ones.
+    ///
+    /// Only lattice nodes which are compatible with the constraints will be considered
+    /// during the analysis. Constraints are cleared by [`reset`](StatefulTokenizer::reset).
+    pub fn set_constraints(&mut self, constraints: Constraints) -> Constraints {
+        std::mem::replace(&mut self.constraints, constraints)
+    }
+
+    /// Borrow constraints for the current data mutably
+    pub fn constraints_mut(&mut self) -> &mut Constraints {
+        &mut self.constraints
+    }
+
     /// Prepare StatefulTokenizer for the next data.
     /// Data must be written in the returned reference.
     pub fn reset(&mut self) -> &mut String {
@@ -109,6 +230,7 @@ impl<D: DictionaryAccess> StatefulTokenizer<D> {
             p.clear()
         }
         self.oov.clear();
+        self.constraints.clear();
         self.input.reset()
     }
 
@@ -117,26 +239,58 @@ impl<D: DictionaryAccess> StatefulTokenizer<D> {
         &self.dictionary
     }
 
+    /// Create a structured view of the lattice of the last analysis.
+    ///
+    /// Must be called after [`Self::do_tokenize`] and before the results are
+    /// collected, because the collection takes the input away from the tokenizer.
+    /// Returns an empty view if there is no input.
+    pub fn lattice_view(&self) -> SudachiResult<LatticeView> {
+        if self.input.current().is_empty() {
+            return Ok(LatticeView::default());
+        }
+        let dict = &self.dictionary;
+        self.lattice
+            .view(&self.input, dict.grammar(), dict.lexicon())
+    }
+
     /// Perform the actual tokenization so the analysis result will be available
     /// for consumption
     pub fn do_tokenize(&mut self) -> SudachiResult<()> {
+        if !self.prepare_lattice()? {
+            return Ok(());
+        }
+
+        let mut path = self.resolve_best_path()?;
+        path = self.rewrite_path(path)?;
+        self.top_path = Some(path);
+
+        Ok(())
+    }
+
+    /// Build input and the lattice for the current data.
+    /// Returns false if the input was empty and there is nothing to analyze.
+    fn prepare_lattice(&mut self) -> SudachiResult<bool> {
         self.input.start_build()?;
         self.rewrite_input()?;
         self.input.build(self.dictionary.grammar())?;
 
         if self.input.current().is_empty() {
-            return Ok(());
+            return Ok(false);
         }
 
-        let debug = self.debug;
-
-        if debug {
+        if self.debug {
             println!("=== Input dump:\n{}", self.input.current());
         }
 
         self.build_lattice()?;
 
-        if debug {
+        if let Some(temperature) = self.confidence_temperature {
+            let conn = self.dictionary.grammar().conn_matrix();
+            self.lattice
+                .fill_marginals(conn, temperature, &mut self.marginals);
+        }
+
+        if self.debug {
             println!("=== Lattice dump:");
             let dict = &self.dictionary;
             let mut writer = std::io::stdout();
@@ -144,9 +298,12 @@ impl<D: DictionaryAccess> StatefulTokenizer<D> {
                 .dump(&self.input, dict.grammar(), dict.lexicon(), &mut writer)?;
         };
 
-        let mut path = self.resolve_best_path()?;
+        Ok(true)
+    }
 
-        if debug {
+    /// Apply path rewrite plugins and split the path with the current mode
+    fn rewrite_path(&self, mut path: Vec<ResultNode>) -> SudachiResult<Vec<ResultNode>> {
+        if self.debug {
             println!("=== Before Rewriting:");
             dump_path(&path);
         };
@@ -157,49 +314,63 @@ impl<D: DictionaryAccess> StatefulTokenizer<D> {
 
         path = split_path(&self.dictionary, path, self.mode, self.subset, &self.input)?;
 
-        if debug {
+        if self.debug {
             println!("=== After Rewriting:");
             dump_path(&path);
             println!("===");
         };
 
-        self.top_path = Some(path);
-
-        Ok(())
+        Ok(path)
     }
 
     /// Resolve the path (as ResultNodes) with the smallest cost
     fn resolve_best_path(&mut self) -> SudachiResult<Vec<ResultNode>> {
-        let lex = self.dictionary.lexicon();
         let mut path = self.top_path.take().unwrap_or_default();
-        self.lattice.fill_top_path(&mut self.top_path_ids);
-        self.top_path_ids.reverse();
-        for pid in self.top_path_ids.drain(..) {
-            let (inner, cost) = self.lattice.node(pid);
-            let wi = if inner.word_id().is_oov() {
-                let curr_slice = self.input.curr_slice_c(inner.char_range()).to_owned();
-                WordInfoData {
-                    pos_id: inner.word_id().word() as u16,
-                    surface: curr_slice,
-                    ..Default::default()
+        let mut path_ids = std::mem::take(&mut self.top_path_ids);
+        self.lattice.fill_top_path(&mut path_ids);
+        path_ids.reverse();
+        for pid in path_ids.drain(..) {
+            let (_, cost) = self.lattice.node(pid);
+            path.push(self.resolve_node(pid, cost)?);
+        }
+        self.top_path_ids = path_ids;
+        Ok(path)
+    }
+
+    /// Create ResultNode for the lattice node with the specified total path cost
+    fn resolve_node(&self, pid: NodeIdx, cost: i32) -> SudachiResult<ResultNode> {
+        let (inner, _) = self.lattice.node(pid);
+        let wi = if inner.word_id().is_oov() {
+            let curr_slice = self.input.curr_slice_c(inner.char_range()).to_owned();
+            let reading_form = match self.dictionary.grammar().oov_reading.as_ref() {
+                Some(r) if self.subset.contains(InfoSubset::READING_FORM) => {
+                    r.estimate(&curr_slice).unwrap_or_default()
                 }
-                .into()
-            } else {
-                lex.get_word_info_subset(inner.word_id(), self.subset)?
+                _ => String::new(),
             };
+            WordInfoData {
+                pos_id: inner.word_id().word() as u16,
+                surface: curr_slice,
+                reading_form,
+                ..Default::default()
+            }
+            .into()
+        } else {
+            self.dictionary
+                .lexicon()
+                .get_word_info_subset(inner.word_id(), self.subset)?
+        };
 
-            let byte_begin = self.input.to_curr_byte_idx(inner.begin());
-            let byte_end = self.input.to_curr_byte_idx(inner.end());
+        let byte_begin = self.input.to_curr_byte_idx(inner.begin());
+        let byte_end = self.input.to_curr_byte_idx(inner.end());
 
-            path.push(ResultNode::new(
-                inner.clone(),
-                cost,
-                byte_begin as u16,
-                byte_end as u16,
-                wi,
+        let mut node = ResultNode::new(inner.clone(), cost, byte_begin as u32, byte_end as u32, wi);
+        if self.confidence_temperature.is_some() {
+            node.set_confidence(Some(
+                self.marginals[pid.end() as usize][pid.index() as usize],
             ));
         }
-        Ok(path)
+        Ok(node)
     }
 
     /// Swap result data with the current analyzer
@@ -222,6 +393,12 @@ impl<D: DictionaryAccess> StatefulTokenizer<D> {
     }
 
     fn build_lattice(&mut self) -> SudachiResult<()> {
+        self.single_words.clear();
+        for p in self.dictionary.oov_provider_plugins() {
+            p.single_word_ranges(&self.input, &mut self.single_words)?;
+        }
+        self.resolved_constraints
+            .resolve(&self.constraints, &self.single_words, &self.input)?;
         let mut builder = LatticeBuilder {
             node_buffer: &mut self.oov,
             lattice: &mut self.lattice,
@@ -229,6 +406,7 @@ impl<D: DictionaryAccess> StatefulTokenizer<D> {
             oov_providers: self.dictionary.oov_provider_plugins(),
             lexicon: self.dictionary.lexicon(),
             input: &self.input,
+            constraints: &self.resolved_constraints,
         };
         builder.build_lattice()
     }
@@ -256,6 +434,7 @@ struct LatticeBuilder<'a> {
     input: &'a InputBuffer,
     lexicon: &'a LexiconSet<'a>,
     oov_providers: &'a [Box<dyn OovProviderPlugin + Sync + Send>],
+    constraints: &'a ResolvedConstraints,
 }
 
 impl<'a> LatticeBuilder<'a> {
@@ -279,13 +458,16 @@ impl<'a> LatticeBuilder<'a> {
                 let (left_id, right_id, cost) = self.lexicon.get_word_param(e.word_id);
                 let end_c = self.input.ch_idx(e.end);
                 let node = Node::new(
-                    ch_off as u16,
-                    end_c as u16,
+                    ch_off as u32,
+                    end_c as u32,
                     left_id as u16,
                     right_id as u16,
                     cost,
                     e.word_id,
                 );
+                if !self.is_allowed(&node)? {
+                    continue;
+                }
                 created = created.add_word((end_c - ch_off) as i64);
                 self.node_buffer.push(node.clone());
                 self.lattice.insert(node, self.matrix);
@@ -307,6 +489,16 @@ impl<'a> LatticeBuilder<'a> {
                 created = self.provide_oovs(ch_off, created, provider.as_ref())?;
             }
 
+            if created.is_empty() && self.constraints.is_active() {
+                // not all providers create nodes at every position, e.g. regex-based ones
+                for provider in self.oov_providers.iter().rev() {
+                    created = self.provide_constrained_oov(ch_off, provider.as_ref())?;
+                    if !created.is_empty() {
+                        break;
+                    }
+                }
+            }
+
             if created.is_empty() {
                 return Err(SudachiError::EosBosDisconnect);
             }
@@ -316,6 +508,67 @@ impl<'a> LatticeBuilder<'a> {
         Ok(())
     }
 
+    /// Checks if the node is compatible with the analysis constraints
+    #[inline]
+    fn is_allowed(&self, node: &Node) -> SudachiResult<bool> {
+        if !self.constraints.is_active() {
+            return Ok(true);
+        }
+        if !self.constraints.allows_range(node.begin(), node.end()) {
+            return Ok(false);
+        }
+        match self.constraints.required_pos(node.begin(), node.end()) {
+            None => Ok(true),
+            Some(pos_id) => {
+                let node_pos = if node.word_id().is_oov() {
+                    node.word_id().word() as u16
+                } else {
+                    self.lexicon
+                        .get_word_info_subset(node.word_id(), InfoSubset::POS_ID)?
+                        .pos_id()
+                };
+                Ok(node_pos == pos_id)
+            }
+        }
+    }
+
+    /// Creates an OOV node when no node satisfies the constraints at the offset.
+    /// The node takes connection ids and cost from the OOV provider
+    /// and spans to the nearest end allowed by the constraints.
+    fn provide_constrained_oov<P>(
+        &mut self,
+        char_offset: usize,
+        plugin: &P,
+    ) -> SudachiResult<CreatedWords>
+    where
+        P: OovProviderPlugin + 'a + ?Sized,
+    {
+        let start_size = self.node_buffer.len();
+        let num_provided = plugin.provide_oov(
+            self.input,
+            char_offset,
+            CreatedWords::empty(),
+            self.node_buffer,
+        )?;
+        if num_provided == 0 {
+            return Ok(CreatedWords::empty());
+        }
+        let mut node = self.node_buffer[start_size].clone();
+        self.node_buffer.truncate(start_size);
+
+        let num_chars = self.input.current_chars().len();
+        let end = (char_offset + 1..=num_chars)
+            .find(|&e| self.constraints.allows_range(char_offset, e))
+            .unwrap_or(num_chars);
+        node.set_range(char_offset as u32, end as u32);
+        if let Some(pos_id) = self.constraints.required_pos(char_offset, end) {
+            node.set_word_id(WordId::oov(pos_id as u32));
+        }
+        self.node_buffer.push(node.clone());
+        self.lattice.insert(node, self.matrix);
+        Ok(CreatedWords::single((end - char_offset) as i64))
+    }
+
     #[inline]
     fn provide_oovs<P>(
         &mut self,
@@ -330,6 +583,9 @@ impl<'a> LatticeBuilder<'a> {
         let num_provided = plugin.provide_oov(self.input, char_offset, other, self.node_buffer)?;
         for idx in start_size..(start_size + num_provided) {
             let node = self.node_buffer[idx].clone();
+            if !self.is_allowed(&node)? {
+                continue;
+            }
             other = other.add_word(node.char_range().len() as i64);
             self.lattice.insert(node, self.matrix);
         }
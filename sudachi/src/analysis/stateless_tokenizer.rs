@@ -106,6 +106,18 @@ where
         tok.do_tokenize()?;
         tok.into_morpheme_list()
     }
+
+    fn tokenize_n_best(
+        &self,
+        input: &str,
+        mode: Mode,
+        n: usize,
+        enable_debug: bool,
+    ) -> SudachiResult<Vec<(MorphemeList<Self::Dictionary>, i32)>> {
+        let mut tok = StatefulTokenizer::create(self.dict.clone(), enable_debug, mode);
+        tok.reset().push_str(input);
+        tok.n_best(n)
+    }
 }
 
 pub(super) fn split_path<T: DictionaryAccess + ?Sized>(
@@ -0,0 +1,232 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::io::{BufRead, ErrorKind};
+
+use crate::analysis::stateful_tokenizer::StatefulTokenizer;
+use crate::analysis::stateless_tokenizer::DictionaryAccess;
+use crate::analysis::Mode;
+use crate::error::{SudachiError, SudachiResult};
+use crate::prelude::MorphemeList;
+use crate::sentence_detector::{EosSearchState, NonBreakChecker, SentenceDetector};
+
+/// Source of text for [`StreamingTokenizer`]
+pub trait TextChunks {
+    /// Appends the next chunk of text to `out`.
+    /// Returns false if the text has ended.
+    fn next_chunk(&mut self, out: &mut String) -> SudachiResult<bool>;
+}
+
+/// Reads UTF-8 text from [`BufRead`] in chunks.
+/// Characters which are split between reads are handled correctly.
+pub struct ReaderChunks<R> {
+    reader: R,
+    pending: Vec<u8>,
+}
+
+impl<R: BufRead> ReaderChunks<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<R: BufRead> TextChunks for ReaderChunks<R> {
+    fn next_chunk(&mut self, out: &mut String) -> SudachiResult<bool> {
+        loop {
+            let data = self.reader.fill_buf()?;
+            if data.is_empty() {
+                return if self.pending.is_empty() {
+                    Ok(false)
+                } else {
+                    Err(invalid_utf8())
+                };
+            }
+            let length = data.len();
+            self.pending.extend_from_slice(data);
+            self.reader.consume(length);
+
+            let valid = match std::str::from_utf8(&self.pending) {
+                Ok(s) => s.len(),
+                // the last character is incomplete
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => return Err(invalid_utf8()),
+            };
+            if valid != 0 {
+                // Safety: the prefix was checked to be valid UTF-8
+                out.push_str(unsafe { std::str::from_utf8_unchecked(&self.pending[..valid]) });
+                self.pending.drain(..valid);
+                return Ok(true);
+            }
+        }
+    }
+}
+
+fn invalid_utf8() -> SudachiError {
+    std::io::Error::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8").into()
+}
+
+/// Takes text from an iterator of string chunks
+pub struct StrChunks<I> {
+    iter: I,
+}
+
+impl<I> TextChunks for StrChunks<I>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    fn next_chunk(&mut self, out: &mut String) -> SudachiResult<bool> {
+        match self.iter.next() {
+            None => Ok(false),
+            Some(s) => {
+                out.push_str(s.as_ref());
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Tokenizer which analyzes a stream of text sentence by sentence.
+///
+/// Text is buffered until [`SentenceDetector`] finds a sentence boundary,
+/// so sentences which span several chunks of input are analyzed as a whole.
+/// Produced [`MorphemeList`]s contain one sentence each, and offsets of their
+/// morphemes are relative to the beginning of the stream.
+pub struct StreamingTokenizer<D, C> {
+    tokenizer: StatefulTokenizer<D>,
+    detector: SentenceDetector,
+    source: C,
+    buffer: String,
+    /// Offset of the buffer start in the stream, in bytes and chars
+    offset: (usize, usize),
+    /// Search for the sentence end in the buffer is resumed when more text is read
+    eos_search: EosSearchState,
+    eof: bool,
+}
+
+impl<D: DictionaryAccess, R: BufRead> StreamingTokenizer<D, ReaderChunks<R>> {
+    /// Create a tokenizer reading UTF-8 text from the reader
+    pub fn from_reader(dict: D, mode: Mode, reader: R) -> Self {
+        Self::new(dict, mode, ReaderChunks::new(reader))
+    }
+}
+
+impl<D: DictionaryAccess, I> StreamingTokenizer<D, StrChunks<I>>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    /// Create a tokenizer taking text from an iterator of string chunks
+    pub fn from_chunks<T: IntoIterator<IntoIter = I>>(dict: D, mode: Mode, chunks: T) -> Self {
+        let source = StrChunks {
+            iter: chunks.into_iter(),
+        };
+        Self::new(dict, mode, source)
+    }
+}
+
+impl<D: DictionaryAccess, C: TextChunks> StreamingTokenizer<D, C> {
+    pub fn new(dict: D, mode: Mode, source: C) -> Self {
+        Self {
+            tokenizer: StatefulTokenizer::new(dict, mode),
+            detector: SentenceDetector::new(),
+            source,
+            buffer: String::new(),
+            offset: (0, 0),
+            eos_search: EosSearchState::default(),
+            eof: false,
+        }
+    }
+
+    /// Use the sentence detector with the specified limit (in chars) of the sentence length
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.detector = SentenceDetector::with_limit(limit);
+        self
+    }
+
+    /// Borrow the underlying tokenizer, e.g. to change the analysis settings
+    pub fn tokenizer_mut(&mut self) -> &mut StatefulTokenizer<D> {
+        &mut self.tokenizer
+    }
+
+    /// Finds the end of the next sentence in the buffer, reading more data if needed.
+    /// Returns None if there is no more data.
+    fn next_sentence_end(&mut self) -> SudachiResult<Option<usize>> {
+        loop {
+            if !self.buffer.is_empty() {
+                let checker = NonBreakChecker::new(self.tokenizer.dict().lexicon());
+                let eos = self.detector.get_eos_resumable(
+                    &self.buffer,
+                    &mut self.eos_search,
+                    Some(&checker),
+                )?;
+                if eos > 0 {
+                    // there can be more closing characters in the next chunk
+                    if (eos as usize) < self.buffer.len() || self.eof {
+                        return Ok(Some(eos as usize));
+                    }
+                } else {
+                    // the detector checks only the first `limit` chars
+                    let over_limit = ((-eos) as usize) < self.buffer.len();
+                    if self.eof || over_limit {
+                        return Ok(Some(-eos as usize));
+                    }
+                }
+            }
+
+            if self.eof {
+                return Ok(None);
+            }
+            if !self.source.next_chunk(&mut self.buffer)? {
+                self.eof = true;
+            }
+        }
+    }
+}
+
+impl<D: DictionaryAccess + Clone, C: TextChunks> StreamingTokenizer<D, C> {
+    /// Analyze the next sentence of the stream
+    pub fn next_sentence(&mut self) -> SudachiResult<Option<MorphemeList<D>>> {
+        let end = match self.next_sentence_end()? {
+            None => return Ok(None),
+            Some(e) => e,
+        };
+
+        let sentence = &self.buffer[..end];
+        let num_chars = sentence.chars().count();
+        self.tokenizer.reset().push_str(sentence);
+        self.buffer.drain(..end);
+        self.eos_search = EosSearchState::default();
+        self.tokenizer.do_tokenize()?;
+
+        let mut result = MorphemeList::empty(self.tokenizer.dict_clone());
+        result.collect_results(&mut self.tokenizer)?;
+        result.set_offset(self.offset.0, self.offset.1)?;
+        self.offset = (self.offset.0 + end, self.offset.1 + num_chars);
+        Ok(Some(result))
+    }
+}
+
+impl<D: DictionaryAccess + Clone, C: TextChunks> Iterator for StreamingTokenizer<D, C> {
+    type Item = SudachiResult<MorphemeList<D>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_sentence().transpose()
+    }
+}
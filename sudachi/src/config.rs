@@ -20,6 +20,7 @@ use std::fs::File;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
 
+use crate::dic::oov_reading::OovReadingConfig;
 use crate::dic::subset::InfoSubset;
 use crate::error::SudachiError;
 use lazy_static::lazy_static;
@@ -47,6 +48,9 @@ pub enum ConfigError {
     #[error("Invalid format: {0}")]
     InvalidFormat(String),
 
+    #[error("Invalid value of {0}: {1}")]
+    InvalidValue(String, String),
+
     #[error("Argument {0} is missing")]
     MissingArgument(String),
 
@@ -160,6 +164,8 @@ pub struct Config {
     pub path_rewrite_plugins: Vec<Value>,
     // this option is Python-only and is ignored in Rust APIs
     pub projection: SurfaceProjection,
+    /// Reading estimation for OOV words, disabled if not set
+    pub oov_reading: Option<OovReadingConfig>,
 }
 
 /// Struct corresponds with raw config json file.
@@ -187,6 +193,7 @@ pub struct ConfigBuilder {
     oovProviderPlugin: Option<Vec<Value>>,
     pathRewritePlugin: Option<Vec<Value>>,
     projection: Option<SurfaceProjection>,
+    oovReading: Option<OovReadingConfig>,
 }
 
 pub fn default_resource_dir() -> PathBuf {
@@ -296,6 +303,7 @@ impl ConfigBuilder {
             oov_provider_plugins: self.oovProviderPlugin.unwrap_or_default(),
             path_rewrite_plugins: self.pathRewritePlugin.unwrap_or_default(),
             projection: self.projection.unwrap_or(SurfaceProjection::Surface),
+            oov_reading: self.oovReading,
         }
     }
 
@@ -311,6 +319,7 @@ impl ConfigBuilder {
         merge_cfg_value!(self, other, oovProviderPlugin);
         merge_cfg_value!(self, other, pathRewritePlugin);
         merge_cfg_value!(self, other, projection);
+        merge_cfg_value!(self, other, oovReading);
         self
     }
 }
@@ -0,0 +1,336 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Cache of parsed csv lexicons for incremental dictionary builds.
+//!
+//! Parsed lexicons are stored in files named by the hash of their contents,
+//! so unchanged lexicons are not parsed again. Cache files which can't be read
+//! are ignored and the lexicon is parsed as usual.
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use crate::analysis::Mode;
+use crate::dic::build::format::LexiconFormat;
+use crate::dic::build::lexicon::{LexiconShard, RawLexiconEntry, SplitUnit, StrPosEntry};
+use crate::dic::verify::checksum;
+use crate::dic::word_id::WordId;
+use crate::dic::POS_DEPTH;
+use crate::error::SudachiResult;
+
+/// Incremented when the format of cache files or the parsing of lexicons changes
+const CACHE_VERSION: u32 = 1;
+const MAGIC: &[u8; 8] = b"SDSHARD\0";
+
+pub(crate) struct ShardCache {
+    dir: PathBuf,
+}
+
+/// Identity of the lexicon contents together with the way they are parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ShardKey {
+    settings: u64,
+    data: u64,
+    len: u64,
+}
+
+impl ShardCache {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub(crate) fn key(data: &[u8], format: &LexiconFormat) -> ShardKey {
+        let settings = format!(
+            "{}/{}/{:?}",
+            CACHE_VERSION,
+            env!("CARGO_PKG_VERSION"),
+            format
+        );
+        ShardKey {
+            settings: checksum(settings.as_bytes()),
+            data: checksum(data),
+            len: data.len() as u64,
+        }
+    }
+
+    fn path(&self, key: &ShardKey) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}-{:016x}.shard", key.settings, key.data))
+    }
+
+    /// Returns the cached shard, unreadable cache files are treated as missing
+    pub(crate) fn get(&self, key: &ShardKey) -> Option<LexiconShard> {
+        let data = std::fs::read(self.path(key)).ok()?;
+        decode(&data, key)
+    }
+
+    pub(crate) fn put(&self, key: &ShardKey, shard: &LexiconShard) -> SudachiResult<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        // write to a temporary file first, so concurrent builds never see partial files
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        let result =
+            std::fs::write(&tmp, encode(shard, key)).and_then(|_| std::fs::rename(&tmp, &path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        Ok(result?)
+    }
+}
+
+fn encode(shard: &LexiconShard, key: &ShardKey) -> Vec<u8> {
+    let mut enc = Encoder {
+        buf: Vec::with_capacity(shard.entries.len() * 64),
+    };
+    enc.buf.extend_from_slice(MAGIC);
+    enc.u32(CACHE_VERSION);
+    enc.u64(key.settings);
+    enc.u64(key.data);
+    enc.u64(key.len);
+    enc.u64(shard.unresolved as u64);
+
+    enc.u32(shard.pos.len() as u32);
+    for pos in shard.pos.iter() {
+        for field in pos.fields() {
+            enc.str(field);
+        }
+    }
+
+    enc.u32(shard.entries.len() as u32);
+    for e in shard.entries.iter() {
+        enc.i16(e.left_id);
+        enc.i16(e.right_id);
+        enc.i16(e.cost);
+        enc.str(&e.surface);
+        enc.opt_str(&e.headword);
+        enc.u64(e.dic_form.as_raw());
+        enc.opt_str(&e.norm_form);
+        enc.u16(e.pos);
+        enc.splits(&e.splits_a);
+        enc.splits(&e.splits_b);
+        enc.opt_str(&e.reading);
+        enc.u8(match e.splitting {
+            Mode::A => 0,
+            Mode::B => 1,
+            Mode::C => 2,
+        });
+        enc.u32(e.word_structure.len() as u32);
+        for wid in e.word_structure.iter() {
+            enc.u64(wid.as_raw());
+        }
+        enc.u32(e.synonym_groups.len() as u32);
+        for v in e.synonym_groups.iter() {
+            enc.u32(*v);
+        }
+    }
+    enc.buf
+}
+
+fn decode(data: &[u8], key: &ShardKey) -> Option<LexiconShard> {
+    let mut dec = Decoder { data };
+    if dec.bytes(MAGIC.len())? != MAGIC
+        || dec.u32()? != CACHE_VERSION
+        || dec.u64()? != key.settings
+        || dec.u64()? != key.data
+        || dec.u64()? != key.len
+    {
+        return None;
+    }
+    let unresolved = dec.u64()? as usize;
+
+    let num_pos = dec.u32()? as usize;
+    let mut pos = Vec::with_capacity(num_pos.min(data.len()));
+    for _ in 0..num_pos {
+        let mut fields: [Cow<str>; POS_DEPTH] = Default::default();
+        for f in fields.iter_mut() {
+            *f = Cow::Owned(dec.str()?);
+        }
+        pos.push(StrPosEntry::new(fields));
+    }
+
+    let num_entries = dec.u32()? as usize;
+    let mut entries = Vec::with_capacity(num_entries.min(data.len()));
+    for _ in 0..num_entries {
+        entries.push(RawLexiconEntry {
+            left_id: dec.i16()?,
+            right_id: dec.i16()?,
+            cost: dec.i16()?,
+            surface: dec.str()?,
+            headword: dec.opt_str()?,
+            dic_form: WordId::from_raw(dec.u64()?),
+            norm_form: dec.opt_str()?,
+            pos: dec.u16()?,
+            splits_a: dec.splits()?,
+            splits_b: dec.splits()?,
+            reading: dec.opt_str()?,
+            splitting: match dec.u8()? {
+                0 => Mode::A,
+                1 => Mode::B,
+                2 => Mode::C,
+                _ => return None,
+            },
+            word_structure: dec.list(|d| d.u64().map(WordId::from_raw))?,
+            synonym_groups: dec.list(|d| d.u32())?,
+        });
+    }
+
+    if !dec.data.is_empty() {
+        return None;
+    }
+
+    Some(LexiconShard {
+        pos,
+        entries,
+        unresolved,
+    })
+}
+
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    fn u16(&mut self, v: u16) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn i16(&mut self, v: i16) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn str(&mut self, v: &str) {
+        self.u32(v.len() as u32);
+        self.buf.extend_from_slice(v.as_bytes());
+    }
+
+    fn opt_str(&mut self, v: &Option<String>) {
+        match v {
+            None => self.u8(0),
+            Some(s) => {
+                self.u8(1);
+                self.str(s);
+            }
+        }
+    }
+
+    fn splits(&mut self, v: &[SplitUnit]) {
+        self.u32(v.len() as u32);
+        for s in v {
+            match s {
+                SplitUnit::Ref(wid) => {
+                    self.u8(0);
+                    self.u64(wid.as_raw());
+                }
+                SplitUnit::Inline {
+                    surface,
+                    pos,
+                    reading,
+                } => {
+                    self.u8(1);
+                    self.str(surface);
+                    self.u16(*pos);
+                    self.opt_str(reading);
+                }
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.bytes(N).map(|b| b.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.array::<1>().map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        self.array().map(i16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    fn opt_str(&mut self) -> Option<Option<String>> {
+        match self.u8()? {
+            0 => Some(None),
+            1 => self.str().map(Some),
+            _ => None,
+        }
+    }
+
+    fn list<T, F: FnMut(&mut Self) -> Option<T>>(&mut self, mut f: F) -> Option<Vec<T>> {
+        let len = self.u32()? as usize;
+        let mut result = Vec::with_capacity(len.min(self.data.len()));
+        for _ in 0..len {
+            result.push(f(self)?);
+        }
+        Some(result)
+    }
+
+    fn splits(&mut self) -> Option<Vec<SplitUnit>> {
+        self.list(|d| match d.u8()? {
+            0 => d.u64().map(|raw| SplitUnit::Ref(WordId::from_raw(raw))),
+            1 => Some(SplitUnit::Inline {
+                surface: d.str()?,
+                pos: d.u16()?,
+                reading: d.opt_str()?,
+            }),
+            _ => None,
+        })
+    }
+}
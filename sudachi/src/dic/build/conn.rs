@@ -14,6 +14,7 @@
  *  limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, Write};
 use std::path::Path;
@@ -23,6 +24,7 @@ use regex::Regex;
 
 use crate::dic::build::error::{BuildFailure, DicBuildError, DicCompilationCtx, DicWriteResult};
 use crate::dic::build::parse::{it_next, parse_i16};
+use crate::dic::connect::MatrixFormat;
 use crate::error::SudachiResult;
 
 pub struct ConnBuffer {
@@ -64,7 +66,7 @@ impl ConnBuffer {
         self.num_right
     }
 
-    pub fn write_to<W: Write>(&self, writer: &mut W) -> SudachiResult<usize> {
+    pub fn write_to<W: Write>(&self, writer: &mut W, format: MatrixFormat) -> SudachiResult<usize> {
         if self.num_left < 0 {
             return num_error("left", self.num_left);
         }
@@ -73,10 +75,50 @@ impl ConnBuffer {
             return num_error("right", self.num_right);
         }
 
+        match format {
+            MatrixFormat::Dense => {
+                writer.write_all(&i16::to_le_bytes(self.num_left))?;
+                writer.write_all(&i16::to_le_bytes(self.num_right))?;
+                writer.write_all(&self.matrix)?;
+                Ok(4 + self.matrix.len())
+            }
+            MatrixFormat::Dedup => self.write_dedup(writer),
+        }
+    }
+
+    /// Layout: marker (-1), format tag, num_left, num_right, number of unique rows,
+    /// row index for each right id and unique rows in the order of their first appearance
+    fn write_dedup<W: Write>(&self, writer: &mut W) -> SudachiResult<usize> {
+        let row_size = self.num_left as usize * 2;
+        let mut unique: HashMap<&[u8], u16> = HashMap::new();
+        let mut rows: Vec<&[u8]> = Vec::new();
+        let mut index = Vec::with_capacity(self.num_right as usize);
+        if row_size > 0 {
+            for row in self.matrix.chunks(row_size) {
+                let id = *unique.entry(row).or_insert_with(|| {
+                    rows.push(row);
+                    (rows.len() - 1) as u16
+                });
+                index.push(id);
+            }
+        } else if self.num_right > 0 {
+            // all rows are empty, so they share a single row
+            rows.push(&[]);
+            index.resize(self.num_right as usize, 0);
+        }
+
+        writer.write_all(&MatrixFormat::MARKER.to_le_bytes())?;
+        writer.write_all(&MatrixFormat::DEDUP_TAG.to_le_bytes())?;
         writer.write_all(&i16::to_le_bytes(self.num_left))?;
         writer.write_all(&i16::to_le_bytes(self.num_right))?;
-        writer.write_all(&self.matrix)?;
-        Ok(4 + self.matrix.len())
+        writer.write_all(&u16::to_le_bytes(rows.len() as u16))?;
+        for id in index.iter() {
+            writer.write_all(&id.to_le_bytes())?;
+        }
+        for row in rows.iter() {
+            writer.write_all(row)?;
+        }
+        Ok(10 + index.len() * 2 + rows.len() * row_size)
     }
 
     pub fn read_file(&mut self, path: &Path) -> SudachiResult<()> {
@@ -103,7 +145,7 @@ impl ConnBuffer {
         }
 
         let result = self.parse_header();
-        let (left, right) = self.ctx.transform(result)?;
+        let (left, right, default) = self.ctx.transform(result)?;
         if left < 0 {
             return num_error("left", left);
         }
@@ -112,8 +154,12 @@ impl ConnBuffer {
             return num_error("right", right);
         }
 
-        let size = left as usize * right as usize * 2;
-        self.matrix.resize(size, 0);
+        let size = left as usize * right as usize;
+        self.matrix.clear();
+        self.matrix.reserve(size * 2);
+        for _ in 0..size {
+            self.matrix.extend_from_slice(&default.to_le_bytes());
+        }
         self.num_left = left;
         self.num_right = right;
 
@@ -137,12 +183,18 @@ impl ConnBuffer {
         Ok(())
     }
 
-    fn parse_header(&mut self) -> DicWriteResult<(i16, i16)> {
-        let mut items = SPLIT_REGEX.splitn(self.line.trim(), 2);
+    /// The header is `num_left num_right` for dense matrices (MeCab `matrix.def`),
+    /// sparse matrices have the cost of all the omitted entries as the third value
+    fn parse_header(&mut self) -> DicWriteResult<(i16, i16, i16)> {
+        let mut items = SPLIT_REGEX.splitn(self.line.trim(), 3);
         // TODO: fix get_next error message
         let left = it_next(&self.line, &mut items, "left_num", parse_i16)?;
         let right = it_next(&self.line, &mut items, "right_num", parse_i16)?;
-        Ok((left, right))
+        let default = match items.next() {
+            Some(v) => parse_i16(v)?,
+            None => 0,
+        };
+        Ok((left, right, default))
     }
 
     fn parse_line(&mut self) -> DicWriteResult<()> {
@@ -154,6 +206,20 @@ impl ConnBuffer {
     }
 
     fn write_elem(&mut self, left: i16, right: i16, cost: i16) -> DicWriteResult<()> {
+        if left < 0 || left >= self.num_left {
+            return Err(BuildFailure::InvalidFieldSize {
+                actual: left as _,
+                expected: self.num_left as _,
+                field: "left",
+            });
+        }
+        if right < 0 || right >= self.num_right {
+            return Err(BuildFailure::InvalidFieldSize {
+                actual: right as _,
+                expected: self.num_right as _,
+                field: "right",
+            });
+        }
         let index = right as usize * self.num_left as usize + left as usize;
         let index = index * 2;
         let bytes = cost.to_le_bytes();
@@ -175,7 +241,17 @@ fn num_error<T>(part: &'static str, value: i16) -> SudachiResult<T> {
 #[cfg(test)]
 mod test {
     use crate::dic::build::conn::ConnBuffer;
-    use crate::dic::connect::ConnectionMatrix;
+    use crate::dic::build::error::{BuildFailure, DicBuildError};
+    use crate::dic::connect::{ConnectionMatrix, MatrixFormat};
+    use crate::error::SudachiError;
+    use claim::assert_matches;
+
+    fn written(parser: &ConnBuffer, format: MatrixFormat) -> Vec<u8> {
+        let mut result = Vec::new();
+        let size = parser.write_to(&mut result, format).unwrap();
+        assert_eq!(result.len(), size);
+        result
+    }
 
     #[test]
     fn parse_simple2x2() {
@@ -199,4 +275,105 @@ mod test {
         assert_eq!(cost.cost(1, 0), 2);
         assert_eq!(cost.cost(1, 1), 3);
     }
+
+    #[test]
+    fn parse_sparse() {
+        let data = "
+        3 2 100
+        0 0 0
+        2 1 -5";
+        let mut parser = ConnBuffer::new();
+        parser.read(data.as_bytes()).unwrap();
+        let bytes = written(&parser, MatrixFormat::Dense);
+        let (cost, size) = ConnectionMatrix::parse(&bytes, 0).unwrap();
+        assert_eq!(bytes.len(), size);
+        assert_eq!(cost.cost(0, 0), 0);
+        assert_eq!(cost.cost(1, 0), 100);
+        assert_eq!(cost.cost(0, 1), 100);
+        assert_eq!(cost.cost(2, 1), -5);
+    }
+
+    #[test]
+    fn entry_out_of_range() {
+        let data = "2 2\n0 2 1";
+        let mut parser = ConnBuffer::new();
+        assert_matches!(
+            parser.read(data.as_bytes()),
+            Err(SudachiError::DictionaryCompilationError(DicBuildError {
+                cause: BuildFailure::InvalidFieldSize { field: "right", .. },
+                line: 2,
+                ..
+            }))
+        );
+    }
+
+    #[test]
+    fn dedup_roundtrip() {
+        let data = "
+        2 3 7
+        0 0 1
+        1 0 2
+        0 2 1
+        1 2 2";
+        let mut parser = ConnBuffer::new();
+        parser.read(data.as_bytes()).unwrap();
+        let dense = written(&parser, MatrixFormat::Dense);
+        let dedup = written(&parser, MatrixFormat::Dedup);
+        // 3 rows, 2 of which are the same
+        assert_eq!(10 + 3 * 2 + 2 * 2 * 2, dedup.len());
+
+        let (dense, _) = ConnectionMatrix::parse(&dense, 0).unwrap();
+        let (mut dedup, size) = ConnectionMatrix::parse(&dedup, 0).unwrap();
+        assert_eq!(24, size);
+        assert!(dedup.is_compressed());
+        assert!(!dense.is_compressed());
+        assert_eq!(2, dedup.num_left());
+        assert_eq!(3, dedup.num_right());
+        for left in 0..2 {
+            for right in 0..3 {
+                assert_eq!(dense.cost(left, right), dedup.cost(left, right));
+            }
+        }
+
+        // updates do not affect other rows which had the same contents
+        dedup.update(0, 2, 42);
+        assert!(!dedup.is_compressed());
+        assert_eq!(42, dedup.cost(0, 2));
+        assert_eq!(1, dedup.cost(0, 0));
+        assert_eq!(7, dedup.cost(0, 1));
+    }
+
+    #[test]
+    fn dedup_without_shared_rows_is_dense() {
+        let data = "
+        2 2 7
+        0 0 1
+        1 0 2";
+        let mut parser = ConnBuffer::new();
+        parser.read(data.as_bytes()).unwrap();
+        let dedup = written(&parser, MatrixFormat::Dedup);
+        let (dedup, _) = ConnectionMatrix::parse(&dedup, 0).unwrap();
+        assert!(!dedup.is_compressed());
+        assert_eq!(1, dedup.cost(0, 0));
+        assert_eq!(2, dedup.cost(1, 0));
+        assert_eq!(7, dedup.cost(0, 1));
+    }
+
+    #[test]
+    fn roundtrip_without_left_ids() {
+        for (right, format) in [
+            (0, MatrixFormat::Dense),
+            (0, MatrixFormat::Dedup),
+            (3, MatrixFormat::Dense),
+            (3, MatrixFormat::Dedup),
+        ] {
+            let mut parser = ConnBuffer::new();
+            parser.read(format!("0 {}", right).as_bytes()).unwrap();
+            let bytes = written(&parser, format);
+            let (matrix, size) = ConnectionMatrix::parse(&bytes, 0).unwrap();
+            assert_eq!(bytes.len(), size);
+            assert_eq!(0, matrix.num_left());
+            assert_eq!(right as usize, matrix.num_right());
+        }
+    }
 }
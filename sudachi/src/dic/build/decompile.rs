@@ -0,0 +1,253 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Conversion of binary dictionaries back to the csv lexicon
+//! which is accepted by [`DictBuilder`](crate::dic::build::DictBuilder).
+
+use std::io::Write;
+
+use crate::dic::grammar::Grammar;
+use crate::dic::header::HeaderVersion;
+use crate::dic::lexicon::word_infos::WordInfoData;
+use crate::dic::lexicon::Lexicon;
+use crate::dic::subset::InfoSubset;
+use crate::dic::word_id::WordId;
+use crate::dic::DictionaryLoader;
+use crate::error::{SudachiError, SudachiResult};
+
+/// Writes the contents of a binary dictionary in the csv format.
+///
+/// Entries are written in the word id order, and all references
+/// (dictionary form, splits, word structure) are written as word ids,
+/// so the output can be compiled into the same binary lexicon.
+///
+/// Surfaces of words which are not indexed (with negative left id)
+/// are not stored in the binary dictionary, headwords are used for them instead.
+pub struct Decompiler<'a> {
+    lexicon: &'a Lexicon<'a>,
+    system_pos: &'a [Vec<String>],
+    user_pos: &'a [Vec<String>],
+    matrix: Option<&'a Grammar<'a>>,
+}
+
+impl<'a> Decompiler<'a> {
+    /// Creates decompiler for the dictionary.
+    /// User dictionaries require the grammar of the system dictionary
+    /// they were built with.
+    pub fn new(
+        dict: &'a DictionaryLoader<'a>,
+        system: Option<&'a Grammar<'a>>,
+    ) -> SudachiResult<Decompiler<'a>> {
+        let grammar = dict.grammar.as_ref();
+        match dict.header.version {
+            HeaderVersion::SystemDict(_) => {
+                let grammar = grammar.ok_or(SudachiError::InvalidDictionaryGrammar)?;
+                Ok(Decompiler {
+                    lexicon: &dict.lexicon,
+                    system_pos: &grammar.pos_list,
+                    user_pos: &[],
+                    matrix: Some(grammar),
+                })
+            }
+            HeaderVersion::UserDict(_) => {
+                let system = system.ok_or_else(|| {
+                    SudachiError::InvalidDictionaryGrammar
+                        .with_context("system dictionary is required to decompile user dictionary")
+                })?;
+                Ok(Decompiler {
+                    lexicon: &dict.lexicon,
+                    system_pos: &system.pos_list,
+                    user_pos: grammar.map_or(&[], |g| &g.pos_list),
+                    matrix: None,
+                })
+            }
+        }
+    }
+
+    /// Write the csv lexicon, one line per word.
+    /// Returns the number of written entries.
+    pub fn write_lexicon<W: Write>(&self, w: &mut W) -> SudachiResult<usize> {
+        let size = self.lexicon.size();
+        let mut surfaces = vec![None; size as usize];
+        self.lexicon.for_each_indexed_word(|key, wid| {
+            if let Some(s) = surfaces.get_mut(wid as usize) {
+                *s = Some(String::from_utf8_lossy(key).into_owned());
+            }
+        });
+
+        let mut line = String::new();
+        for (wid, surface) in surfaces.into_iter().enumerate() {
+            let info = self
+                .lexicon
+                .get_word_info_raw(wid as u32, InfoSubset::all())?;
+            let params = self.lexicon.get_word_param(wid as u32);
+            line.clear();
+            self.format_entry(&mut line, surface.as_deref(), params, &info)?;
+            w.write_all(line.as_bytes())?;
+        }
+        Ok(size as usize)
+    }
+
+    /// Write the connection matrix in the matrix.def format.
+    /// Returns false if the dictionary does not have a matrix (user dictionaries).
+    pub fn write_matrix<W: Write>(&self, w: &mut W) -> SudachiResult<bool> {
+        let grammar = match self.matrix {
+            Some(g) => g,
+            None => return Ok(false),
+        };
+        let conn = grammar.conn_matrix();
+        writeln!(w, "{} {}", conn.num_left(), conn.num_right())?;
+        for left in 0..conn.num_left() {
+            for right in 0..conn.num_right() {
+                writeln!(w, "{} {} {}", left, right, conn.cost(left as _, right as _))?;
+            }
+        }
+        Ok(true)
+    }
+
+    fn format_entry(
+        &self,
+        out: &mut String,
+        surface: Option<&str>,
+        (left, right, cost): (i16, i16, i16),
+        info: &WordInfoData,
+    ) -> SudachiResult<()> {
+        use std::fmt::Write;
+
+        let headword = info.surface.as_str();
+        let pos = self.pos(info.pos_id)?;
+
+        escape_into(out, surface.unwrap_or(headword));
+        write!(out, ",{},{},{},", left, right, cost).unwrap();
+        escape_into(out, headword);
+        for p in pos {
+            out.push(',');
+            escape_into(out, p);
+        }
+        out.push(',');
+        escape_into(out, non_empty_or(&info.reading_form, headword));
+        out.push(',');
+        escape_into(out, non_empty_or(&info.normalized_form, headword));
+        out.push(',');
+        if info.dictionary_form_word_id < 0 {
+            out.push('*');
+        } else {
+            format_wid(
+                out,
+                WordId::from_storage(info.dictionary_form_word_id as u32),
+            );
+        }
+        out.push(',');
+        out.push_str(split_mode(info));
+        out.push(',');
+        format_list(out, &info.a_unit_split, |o, w| format_wid(o, *w));
+        out.push(',');
+        format_list(out, &info.b_unit_split, |o, w| format_wid(o, *w));
+        out.push(',');
+        format_list(out, &info.word_structure, |o, w| format_wid(o, *w));
+        out.push(',');
+        format_list(out, &info.synonym_group_ids, |o, g| {
+            write!(o, "{:06}", g).unwrap()
+        });
+        out.push('\n');
+        Ok(())
+    }
+
+    fn pos(&self, pos_id: u16) -> SudachiResult<&'a [String]> {
+        let id = pos_id as usize;
+        let pos = if id < self.system_pos.len() {
+            self.system_pos.get(id)
+        } else {
+            self.user_pos.get(id - self.system_pos.len())
+        };
+        pos.map(|p| p.as_slice()).ok_or_else(|| {
+            SudachiError::InvalidPartOfSpeech(format!("pos id {} is not defined", pos_id))
+        })
+    }
+}
+
+/// Empty string fields of word info mean that the headword is used
+pub(super) fn non_empty_or<'b>(data: &'b str, default: &'b str) -> &'b str {
+    if data.is_empty() {
+        default
+    } else {
+        data
+    }
+}
+
+/// Splitting mode is not stored in the binary dictionary, so it is inferred from splits
+fn split_mode(info: &WordInfoData) -> &'static str {
+    if !info.b_unit_split.is_empty() {
+        "C"
+    } else if !info.a_unit_split.is_empty() {
+        "B"
+    } else {
+        "A"
+    }
+}
+
+fn format_wid(out: &mut String, wid: WordId) {
+    use std::fmt::Write;
+    if wid.is_user() {
+        out.push('U');
+    }
+    write!(out, "{}", wid.word()).unwrap();
+}
+
+fn format_list<T, F: FnMut(&mut String, &T)>(out: &mut String, data: &[T], mut f: F) {
+    if data.is_empty() {
+        out.push('*');
+        return;
+    }
+    for (i, e) in data.iter().enumerate() {
+        if i != 0 {
+            out.push('/');
+        }
+        f(out, e);
+    }
+}
+
+/// Escape characters which have special meaning in the csv lexicon
+fn escape_into(out: &mut String, data: &str) {
+    for c in data.chars() {
+        match c {
+            '"' | ',' | '\\' | '/' | '\n' | '\r' => {
+                use std::fmt::Write;
+                write!(out, "\\u{:04x}", c as u32).unwrap();
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn escape(data: &str) -> String {
+        let mut result = String::new();
+        escape_into(&mut result, data);
+        result
+    }
+
+    #[test]
+    fn escape_special_chars() {
+        assert_eq!("a\\u002cb", escape("a,b"));
+        assert_eq!("\\u0022x\\u0022", escape("\"x\""));
+        assert_eq!("\\u005cu0041", escape("\\u0041"));
+        assert_eq!("東京", escape("東京"));
+    }
+}
@@ -0,0 +1,533 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Comparison of two binary dictionaries.
+//!
+//! Word ids are not stable between dictionary builds, so entries are matched
+//! by their headword, part of speech and reading form,
+//! and references to other words (dictionary form, splits) are compared using the same key.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use itertools::Itertools;
+use serde_json::{json, Value};
+
+use crate::dic::build::decompile::non_empty_or;
+use crate::dic::build::report::ReportFormat;
+use crate::dic::grammar::Grammar;
+use crate::dic::header::HeaderVersion;
+use crate::dic::lexicon::word_infos::WordInfoData;
+use crate::dic::lexicon::Lexicon;
+use crate::dic::subset::InfoSubset;
+use crate::dic::word_id::WordId;
+use crate::dic::DictionaryLoader;
+use crate::error::{SudachiError, SudachiResult};
+
+/// Dictionary entry with all references resolved to word keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffEntry {
+    pub headword: String,
+    pub pos: Vec<String>,
+    pub reading_form: String,
+    /// Indexed surface, None if the word is not indexed
+    pub surface: Option<String>,
+    pub left_id: i16,
+    pub right_id: i16,
+    pub cost: i16,
+    pub normalized_form: String,
+    /// Key of the dictionary form word, None if the word is the dictionary form itself
+    pub dictionary_form: Option<String>,
+    pub a_unit_split: Vec<String>,
+    pub b_unit_split: Vec<String>,
+    pub word_structure: Vec<String>,
+    pub synonym_group_ids: Vec<u32>,
+}
+
+impl DiffEntry {
+    /// Key which is used to match entries of two dictionaries:
+    /// headword, part of speech and reading form separated by commas
+    pub fn key(&self) -> String {
+        word_key(&self.headword, &self.pos, &self.reading_form)
+    }
+
+    /// Compared fields, except the key
+    fn fields(&self) -> [(&'static str, Value); 10] {
+        [
+            ("surface", json!(self.surface)),
+            ("left_id", json!(self.left_id)),
+            ("right_id", json!(self.right_id)),
+            ("cost", json!(self.cost)),
+            ("normalized_form", json!(self.normalized_form)),
+            ("dictionary_form", json!(self.dictionary_form)),
+            ("a_unit_split", json!(self.a_unit_split)),
+            ("b_unit_split", json!(self.b_unit_split)),
+            ("word_structure", json!(self.word_structure)),
+            ("synonym_group_ids", json!(self.synonym_group_ids)),
+        ]
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut result = serde_json::Map::new();
+        result.insert("headword".to_owned(), json!(self.headword));
+        result.insert("pos".to_owned(), json!(self.pos));
+        result.insert("reading_form".to_owned(), json!(self.reading_form));
+        for (name, value) in self.fields() {
+            result.insert(name.to_owned(), value);
+        }
+        Value::Object(result)
+    }
+}
+
+/// Entry which is present in both dictionaries, but has different fields
+#[derive(Clone, Debug)]
+pub struct EntryChange {
+    pub old: DiffEntry,
+    pub new: DiffEntry,
+}
+
+impl EntryChange {
+    /// Names of changed fields with old and new values
+    pub fn changed_fields(&self) -> Vec<(&'static str, Value, Value)> {
+        self.old
+            .fields()
+            .into_iter()
+            .zip(self.new.fields())
+            .filter(|((_, o), (_, n))| o != n)
+            .map(|((name, o), (_, n))| (name, o, n))
+            .collect()
+    }
+}
+
+/// Changed cell of the connection matrix
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CostChange {
+    pub left: u16,
+    pub right: u16,
+    pub old: i16,
+    pub new: i16,
+}
+
+/// Difference of connection matrices.
+/// Only the cells which are present in both matrices are compared.
+#[derive(Clone, Debug, Default)]
+pub struct MatrixDiff {
+    /// (num_left, num_right)
+    pub old_size: (usize, usize),
+    pub new_size: (usize, usize),
+    pub changed: Vec<CostChange>,
+}
+
+impl MatrixDiff {
+    pub fn is_empty(&self) -> bool {
+        self.old_size == self.new_size && self.changed.is_empty()
+    }
+}
+
+/// Difference of two dictionaries
+#[derive(Clone, Debug, Default)]
+pub struct DictDiff {
+    pub added_pos: Vec<Vec<String>>,
+    pub removed_pos: Vec<Vec<String>>,
+    /// None for user dictionaries
+    pub matrix: Option<MatrixDiff>,
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    pub changed: Vec<EntryChange>,
+}
+
+impl DictDiff {
+    /// Compares two dictionaries of the same kind.
+    /// User dictionaries require the system dictionary they were built with
+    /// to resolve references to system words.
+    pub fn compare(
+        old: &DictionaryLoader,
+        new: &DictionaryLoader,
+        system: Option<&DictionaryLoader>,
+    ) -> SudachiResult<DictDiff> {
+        let old = Source::new(old, system)?;
+        let new = Source::new(new, system)?;
+        if old.is_user != new.is_user {
+            return Err(SudachiError::InvalidDictionaryGrammar
+                .with_context("system dictionary can't be compared with user dictionary"));
+        }
+
+        let mut result = DictDiff {
+            added_pos: missing(new.own_pos(), old.own_pos()),
+            removed_pos: missing(old.own_pos(), new.own_pos()),
+            matrix: None,
+            ..Default::default()
+        };
+        if let (Some(o), Some(n)) = (old.matrix, new.matrix) {
+            result.matrix = Some(compare_matrix(o, n));
+        }
+
+        let mut entries: BTreeMap<String, (Vec<DiffEntry>, Vec<DiffEntry>)> = BTreeMap::new();
+        for e in old.entries()? {
+            entries.entry(e.key()).or_default().0.push(e);
+        }
+        for e in new.entries()? {
+            entries.entry(e.key()).or_default().1.push(e);
+        }
+
+        for (_, (mut olds, mut news)) in entries {
+            // identical entries are unchanged, remaining ones are paired in the word id order
+            olds.retain(|o| match news.iter().position(|n| n == o) {
+                Some(idx) => {
+                    news.remove(idx);
+                    false
+                }
+                None => true,
+            });
+            let num_changed = olds.len().min(news.len());
+            let removed = olds.split_off(num_changed);
+            let added = news.split_off(num_changed);
+            result.changed.extend(
+                olds.into_iter()
+                    .zip(news)
+                    .map(|(old, new)| EntryChange { old, new }),
+            );
+            result.removed.extend(removed);
+            result.added.extend(added);
+        }
+
+        Ok(result)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_pos.is_empty()
+            && self.removed_pos.is_empty()
+            && self.matrix.as_ref().map_or(true, |m| m.is_empty())
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+    }
+
+    pub fn write<W: Write>(&self, format: ReportFormat, out: &mut W) -> SudachiResult<()> {
+        match format {
+            ReportFormat::Text => self.write_text(out),
+            ReportFormat::Json => self.write_json(out),
+        }
+    }
+
+    /// Write the difference as a single-line JSON object
+    pub fn write_json<W: Write>(&self, out: &mut W) -> SudachiResult<()> {
+        serde_json::to_writer(&mut *out, &self.to_json())?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Value {
+        let matrix = self.matrix.as_ref().map(|m| {
+            let changed: Vec<Value> = m
+                .changed
+                .iter()
+                .map(|c| json!({"left": c.left, "right": c.right, "old": c.old, "new": c.new}))
+                .collect();
+            json!({
+                "old_size": [m.old_size.0, m.old_size.1],
+                "new_size": [m.new_size.0, m.new_size.1],
+                "changed": changed,
+            })
+        });
+        let changed: Vec<Value> = self
+            .changed
+            .iter()
+            .map(|c| {
+                let fields: serde_json::Map<String, Value> = c
+                    .changed_fields()
+                    .into_iter()
+                    .map(|(name, o, n)| (name.to_owned(), json!({"old": o, "new": n})))
+                    .collect();
+                json!({
+                    "headword": c.old.headword,
+                    "pos": c.old.pos,
+                    "reading_form": c.old.reading_form,
+                    "changes": fields,
+                })
+            })
+            .collect();
+        json!({
+            "pos": {
+                "added": self.added_pos,
+                "removed": self.removed_pos,
+            },
+            "matrix": matrix,
+            "entries": {
+                "added": self.added.iter().map(DiffEntry::to_json).collect::<Vec<_>>(),
+                "removed": self.removed.iter().map(DiffEntry::to_json).collect::<Vec<_>>(),
+                "changed": changed,
+            }
+        })
+    }
+
+    /// Write the difference in the human-readable form:
+    /// added lines start with `+`, removed with `-` and changed with `~`
+    pub fn write_text<W: Write>(&self, out: &mut W) -> SudachiResult<()> {
+        writeln!(
+            out,
+            "# pos: {} added, {} removed",
+            self.added_pos.len(),
+            self.removed_pos.len()
+        )?;
+        for p in self.added_pos.iter() {
+            writeln!(out, "+ {}", p.join(","))?;
+        }
+        for p in self.removed_pos.iter() {
+            writeln!(out, "- {}", p.join(","))?;
+        }
+
+        if let Some(m) = self.matrix.as_ref() {
+            writeln!(
+                out,
+                "# matrix: {}x{} -> {}x{}, {} changed",
+                m.old_size.0,
+                m.old_size.1,
+                m.new_size.0,
+                m.new_size.1,
+                m.changed.len()
+            )?;
+            for c in m.changed.iter() {
+                writeln!(out, "~ {} {}: {} -> {}", c.left, c.right, c.old, c.new)?;
+            }
+        }
+
+        writeln!(
+            out,
+            "# entries: {} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )?;
+        for e in self.added.iter() {
+            writeln!(out, "+ {}", format_entry(e))?;
+        }
+        for e in self.removed.iter() {
+            writeln!(out, "- {}", format_entry(e))?;
+        }
+        for c in self.changed.iter() {
+            writeln!(out, "~ {}", c.old.key())?;
+            for (name, o, n) in c.changed_fields() {
+                writeln!(
+                    out,
+                    "    {}: {} -> {}",
+                    name,
+                    format_value(&o),
+                    format_value(&n)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn word_key(headword: &str, pos: &[String], reading_form: &str) -> String {
+    format!("{},{},{}", headword, pos.join(","), reading_form)
+}
+
+fn format_entry(e: &DiffEntry) -> String {
+    format!("{} ({},{},{})", e.key(), e.left_id, e.right_id, e.cost)
+}
+
+fn format_value(v: &Value) -> String {
+    match v {
+        Value::Null => "*".to_owned(),
+        Value::String(s) => s.clone(),
+        Value::Array(a) if a.is_empty() => "*".to_owned(),
+        Value::Array(a) => a.iter().map(format_value).join("/"),
+        v => v.to_string(),
+    }
+}
+
+fn missing(from: &[Vec<String>], other: &[Vec<String>]) -> Vec<Vec<String>> {
+    from.iter()
+        .filter(|p| !other.contains(p))
+        .cloned()
+        .collect()
+}
+
+fn compare_matrix(old: &Grammar, new: &Grammar) -> MatrixDiff {
+    let (o, n) = (old.conn_matrix(), new.conn_matrix());
+    let mut changed = Vec::new();
+    for left in 0..o.num_left().min(n.num_left()) {
+        for right in 0..o.num_right().min(n.num_right()) {
+            let old = o.cost(left as u16, right as u16);
+            let new = n.cost(left as u16, right as u16);
+            if old != new {
+                changed.push(CostChange {
+                    left: left as u16,
+                    right: right as u16,
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+    MatrixDiff {
+        old_size: (o.num_left(), o.num_right()),
+        new_size: (n.num_left(), n.num_right()),
+        changed,
+    }
+}
+
+/// Dictionary with the system dictionary it refers to
+struct Source<'a> {
+    lexicon: &'a Lexicon<'a>,
+    system: &'a Lexicon<'a>,
+    system_pos: &'a [Vec<String>],
+    user_pos: &'a [Vec<String>],
+    matrix: Option<&'a Grammar<'a>>,
+    is_user: bool,
+}
+
+impl<'a> Source<'a> {
+    fn new(
+        dict: &'a DictionaryLoader<'a>,
+        system: Option<&'a DictionaryLoader<'a>>,
+    ) -> SudachiResult<Source<'a>> {
+        let grammar = dict.grammar.as_ref();
+        match dict.header.version {
+            HeaderVersion::SystemDict(_) => {
+                let grammar = grammar.ok_or(SudachiError::InvalidDictionaryGrammar)?;
+                Ok(Source {
+                    lexicon: &dict.lexicon,
+                    system: &dict.lexicon,
+                    system_pos: &grammar.pos_list,
+                    user_pos: &[],
+                    matrix: Some(grammar),
+                    is_user: false,
+                })
+            }
+            HeaderVersion::UserDict(_) => {
+                let system = system.ok_or_else(|| {
+                    SudachiError::InvalidDictionaryGrammar
+                        .with_context("system dictionary is required to compare user dictionaries")
+                })?;
+                let system_grammar = system
+                    .grammar
+                    .as_ref()
+                    .ok_or(SudachiError::InvalidDictionaryGrammar)?;
+                Ok(Source {
+                    lexicon: &dict.lexicon,
+                    system: &system.lexicon,
+                    system_pos: &system_grammar.pos_list,
+                    user_pos: grammar.map_or(&[], |g| &g.pos_list),
+                    matrix: None,
+                    is_user: true,
+                })
+            }
+        }
+    }
+
+    /// POS which are defined in this dictionary
+    fn own_pos(&self) -> &'a [Vec<String>] {
+        if self.is_user {
+            self.user_pos
+        } else {
+            self.system_pos
+        }
+    }
+
+    fn pos(&self, pos_id: u16) -> SudachiResult<&'a [String]> {
+        let id = pos_id as usize;
+        let pos = if id < self.system_pos.len() {
+            self.system_pos.get(id)
+        } else {
+            self.user_pos.get(id - self.system_pos.len())
+        };
+        pos.map(|p| p.as_slice()).ok_or_else(|| {
+            SudachiError::InvalidPartOfSpeech(format!("pos id {} is not defined", pos_id))
+        })
+    }
+
+    /// Key of the referenced word, references use the binary dictionary representation
+    fn reference(&self, wid: WordId) -> SudachiResult<String> {
+        let lexicon = match wid.dic() {
+            0 => self.system,
+            1 if self.is_user => self.lexicon,
+            _ => return Err(SudachiError::InvalidRange(wid.word() as _, wid.word() as _)),
+        };
+        if wid.word() >= lexicon.size() {
+            return Err(SudachiError::InvalidRange(
+                wid.word() as _,
+                lexicon.size() as _,
+            ));
+        }
+        let subset = InfoSubset::SURFACE | InfoSubset::POS_ID | InfoSubset::READING_FORM;
+        let info = lexicon.get_word_info_raw(wid.word(), subset)?;
+        Ok(word_key(
+            &info.surface,
+            self.pos(info.pos_id)?,
+            non_empty_or(&info.reading_form, &info.surface),
+        ))
+    }
+
+    fn references(&self, wids: &[WordId]) -> SudachiResult<Vec<String>> {
+        wids.iter().map(|w| self.reference(*w)).collect()
+    }
+
+    fn entries(&self) -> SudachiResult<Vec<DiffEntry>> {
+        let size = self.lexicon.size();
+        let mut surfaces = vec![None; size as usize];
+        self.lexicon.for_each_indexed_word(|key, wid| {
+            if let Some(s) = surfaces.get_mut(wid as usize) {
+                *s = Some(String::from_utf8_lossy(key).into_owned());
+            }
+        });
+
+        let mut result = Vec::with_capacity(size as usize);
+        for (wid, surface) in surfaces.into_iter().enumerate() {
+            let info = self
+                .lexicon
+                .get_word_info_raw(wid as u32, InfoSubset::all())?;
+            let (left_id, right_id, cost) = self.lexicon.get_word_param(wid as u32);
+            result.push(self.entry(surface, left_id, right_id, cost, info)?);
+        }
+        Ok(result)
+    }
+
+    fn entry(
+        &self,
+        surface: Option<String>,
+        left_id: i16,
+        right_id: i16,
+        cost: i16,
+        info: WordInfoData,
+    ) -> SudachiResult<DiffEntry> {
+        let headword = info.surface;
+        let dictionary_form = if info.dictionary_form_word_id < 0 {
+            None
+        } else {
+            let wid = WordId::from_storage(info.dictionary_form_word_id as u32);
+            Some(self.reference(wid)?)
+        };
+        Ok(DiffEntry {
+            pos: self.pos(info.pos_id)?.to_vec(),
+            reading_form: non_empty_or(&info.reading_form, &headword).to_owned(),
+            normalized_form: non_empty_or(&info.normalized_form, &headword).to_owned(),
+            surface,
+            left_id,
+            right_id,
+            cost,
+            dictionary_form,
+            a_unit_split: self.references(&info.a_unit_split)?,
+            b_unit_split: self.references(&info.b_unit_split)?,
+            word_structure: self.references(&info.word_structure)?,
+            synonym_group_ids: info.synonym_group_ids,
+            headword,
+        })
+    }
+}
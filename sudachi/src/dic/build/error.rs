@@ -151,6 +151,10 @@ impl DicCompilationCtx {
         }
     }
 
+    pub fn filename(&self) -> &str {
+        &self.name
+    }
+
     pub fn set_filename(&mut self, new_name: String) -> String {
         std::mem::replace(&mut self.name, new_name)
     }
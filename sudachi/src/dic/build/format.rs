@@ -0,0 +1,193 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Formats of csv lexicons which can be read by
+//! [`DictBuilder`](crate::dic::build::DictBuilder).
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::dic::POS_DEPTH;
+
+/// Format of the csv lexicon
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LexiconFormat {
+    /// Sudachi csv lexicon with 18 or 19 columns
+    #[default]
+    Sudachi,
+    /// MeCab csv lexicon (IPADIC, UniDic, mecab-user-dict) with the specified columns.
+    ///
+    /// Entries are read as A-mode words without splits, the dictionary form
+    /// is the word itself.
+    Mecab(MecabColumns),
+}
+
+impl LexiconFormat {
+    /// Name of the format preset, `mecab` is the same as `ipadic`
+    pub fn name(&self) -> &'static str {
+        match self {
+            LexiconFormat::Sudachi => "sudachi",
+            LexiconFormat::Mecab(c) if *c == MecabColumns::unidic() => "unidic",
+            LexiconFormat::Mecab(c) if *c == MecabColumns::ipadic() => "ipadic",
+            LexiconFormat::Mecab(_) => "mecab",
+        }
+    }
+}
+
+impl FromStr for LexiconFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sudachi" => Ok(LexiconFormat::Sudachi),
+            "mecab" | "ipadic" => Ok(LexiconFormat::Mecab(MecabColumns::ipadic())),
+            "unidic" => Ok(LexiconFormat::Mecab(MecabColumns::unidic())),
+            _ => Err(
+                "lexicon format must be one of \"sudachi\", \"mecab\", \"ipadic\" or \"unidic\"",
+            ),
+        }
+    }
+}
+
+impl Display for LexiconFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 0-based positions of the Sudachi fields in MeCab csv lexicon rows.
+///
+/// Optional fields which are absent from the mapping, absent from a row
+/// or have `*` as the value fall back to the surface.
+/// Missing POS levels are filled with `*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MecabColumns {
+    pub surface: usize,
+    pub left_id: usize,
+    pub right_id: usize,
+    pub cost: usize,
+    pub pos: [Option<usize>; POS_DEPTH],
+    pub reading: Option<usize>,
+    pub lemma: Option<usize>,
+}
+
+impl MecabColumns {
+    /// IPADIC: `surface,left,right,cost,pos1,pos2,pos3,pos4,ctype,cform,base,reading,pron`.
+    ///
+    /// The base form is used as the normalized form.
+    pub fn ipadic() -> MecabColumns {
+        MecabColumns {
+            surface: 0,
+            left_id: 1,
+            right_id: 2,
+            cost: 3,
+            pos: [Some(4), Some(5), Some(6), Some(7), Some(8), Some(9)],
+            reading: Some(11),
+            lemma: Some(10),
+        }
+    }
+
+    /// UniDic (2.3 and later): `surface,left,right,cost,pos1,pos2,pos3,pos4,cType,cForm,lForm,lemma,...`
+    /// with the reading in the 25th (`kana`) column.
+    ///
+    /// The lemma is used as the normalized form.
+    pub fn unidic() -> MecabColumns {
+        MecabColumns {
+            lemma: Some(11),
+            reading: Some(24),
+            ..Self::ipadic()
+        }
+    }
+
+    /// Replaces the positions of the fields by a comma-separated list of
+    /// `field=column` pairs, e.g. `reading=13,lemma=*`.
+    ///
+    /// Fields are `surface`, `left_id`, `right_id`, `cost`, `reading`, `lemma`
+    /// and `pos`, which takes up to 6 columns separated by `/`.
+    /// `*` as the column removes optional fields from the mapping.
+    pub fn with_overrides(mut self, spec: &str) -> Result<Self, String> {
+        for item in spec.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (field, value) = item
+                .split_once('=')
+                .ok_or_else(|| format!("column mapping must be field=column, was {}", item))?;
+            let column = |v: &str| {
+                v.trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid column {} for field {}", v, field))
+            };
+            let optional = |v: &str| match v.trim() {
+                "*" => Ok(None),
+                v => column(v).map(Some),
+            };
+            match field.trim() {
+                "surface" => self.surface = column(value)?,
+                "left_id" => self.left_id = column(value)?,
+                "right_id" => self.right_id = column(value)?,
+                "cost" => self.cost = column(value)?,
+                "reading" => self.reading = optional(value)?,
+                "lemma" => self.lemma = optional(value)?,
+                "pos" => {
+                    let columns: Vec<_> = value.split('/').collect();
+                    if columns.len() > POS_DEPTH {
+                        return Err(format!(
+                            "pos can have at most {} columns, was {}",
+                            POS_DEPTH, value
+                        ));
+                    }
+                    let mut pos = [None; POS_DEPTH];
+                    for (p, v) in pos.iter_mut().zip(columns) {
+                        *p = optional(v)?;
+                    }
+                    self.pos = pos;
+                }
+                f => return Err(format!("unknown lexicon field {}", f)),
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_format() {
+        assert_eq!(Ok(LexiconFormat::Sudachi), "sudachi".parse());
+        assert_eq!(
+            Ok(LexiconFormat::Mecab(MecabColumns::ipadic())),
+            "mecab".parse()
+        );
+        assert_eq!("unidic", "unidic".parse::<LexiconFormat>().unwrap().name());
+        assert!("juman".parse::<LexiconFormat>().is_err());
+    }
+
+    #[test]
+    fn column_overrides() {
+        let columns = MecabColumns::ipadic()
+            .with_overrides("reading=12, lemma=*,pos=4/5/*")
+            .unwrap();
+        assert_eq!(Some(12), columns.reading);
+        assert_eq!(None, columns.lemma);
+        assert_eq!([Some(4), Some(5), None, None, None, None], columns.pos);
+        assert_eq!(0, columns.surface);
+        assert!(MecabColumns::ipadic().with_overrides("cost=*").is_err());
+        assert!(MecabColumns::ipadic().with_overrides("kana=3").is_err());
+        assert!(MecabColumns::ipadic()
+            .with_overrides("pos=1/2/3/4/5/6/7")
+            .is_err());
+    }
+}
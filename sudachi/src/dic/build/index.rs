@@ -15,11 +15,14 @@
  */
 
 use crate::dic::build::error::{BuildFailure, DicBuildError};
+use crate::dic::build::lexicon::RawLexiconEntry;
 use crate::dic::build::primitives::write_u32_array;
+use crate::dic::build::report::{ReportBuilder, Reporter};
 use crate::dic::word_id::WordId;
 use crate::error::{SudachiError, SudachiResult};
 use crate::util::fxhash::FxBuildHasher;
 use indexmap::map::IndexMap;
+use std::io::Write;
 
 pub struct IndexEntry {
     ids: Vec<WordId>,
@@ -99,6 +102,44 @@ impl<'a> IndexBuilder<'a> {
     }
 }
 
+/// Builds the trie and the word id table for the entries and writes them
+pub(crate) fn write_index<W: Write>(
+    entries: &[RawLexiconEntry],
+    w: &mut W,
+    reporter: &mut Reporter,
+) -> SudachiResult<usize> {
+    let mut size = 0;
+    let mut index = IndexBuilder::new();
+    for (i, e) in entries.iter().enumerate() {
+        if e.should_index() {
+            let wid = WordId::checked(0, i as u32)?;
+            index.add(e.surface(), wid);
+        }
+    }
+
+    let report = ReportBuilder::new("trie");
+    let word_id_table = index.build_word_id_table()?;
+    let trie = index.build_trie()?;
+
+    let trie_size = trie.len() / 4;
+    w.write_all(&(trie_size as u32).to_le_bytes())?;
+    size += 4;
+    w.write_all(&trie)?;
+    size += trie.len();
+    std::mem::drop(trie); //can be big, so drop explicitly
+    reporter.collect(size, report);
+    let cur_size = size;
+
+    let report = ReportBuilder::new("word_id table");
+    w.write_all(&(word_id_table.len() as u32).to_le_bytes())?;
+    size += 4;
+    w.write_all(&word_id_table)?;
+    size += word_id_table.len();
+    reporter.collect(size - cur_size, report);
+
+    Ok(size)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
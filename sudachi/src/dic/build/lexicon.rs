@@ -16,28 +16,34 @@
 
 use std::borrow::{Borrow, Cow};
 use std::fmt::{Debug, Formatter};
-use std::fs::File;
 use std::io::Write;
-use std::path::Path;
 
 use csv::{StringRecord, Trim};
 use indexmap::map::IndexMap;
 use indexmap::Equivalent;
-use memmap2::Mmap;
 
+use crate::analysis::stateful_tokenizer::StatefulTokenizer;
+use crate::analysis::stateless_tokenizer::DictionaryAccess;
 use crate::analysis::Mode;
 use crate::dic::build::error::{BuildFailure, DicCompilationCtx, DicWriteResult};
+use crate::dic::build::format::{LexiconFormat, MecabColumns};
+use crate::dic::build::parallel::{map_parallel, split_lines};
 use crate::dic::build::parse::{
     it_next, none_if_equal, parse_dic_form, parse_i16, parse_mode, parse_slash_list,
     parse_u32_list, parse_wordid, parse_wordid_list, unescape, unescape_cow, WORD_ID_LITERAL,
 };
-use crate::dic::build::primitives::{write_u32_array, Utf16Writer};
-use crate::dic::build::report::{ReportBuilder, Reporter};
+use crate::dic::build::pool::StringPool;
+use crate::dic::build::primitives::{
+    write_u32_array, write_varint, write_varint_array, ToU32, Utf16Writer,
+};
+use crate::dic::build::report::{EstimatedCost, ReportBuilder, Reporter};
 use crate::dic::build::MAX_POS_IDS;
 use crate::dic::grammar::Grammar;
+use crate::dic::lexicon::Lexicon;
 use crate::dic::word_id::WordId;
 use crate::dic::POS_DEPTH;
-use crate::error::SudachiResult;
+use crate::error::{SudachiError, SudachiResult};
+use crate::prelude::MorphemeList;
 
 #[cfg(test)]
 mod test;
@@ -45,6 +51,9 @@ mod test;
 #[cfg(test)]
 mod wordinfo_test;
 
+/// csv data smaller than this is not split for parsing in parallel
+const MIN_CHUNK_SIZE: usize = 1024 * 1024;
+
 #[derive(Hash, Eq, PartialEq)]
 pub struct StrPosEntry {
     data: [Cow<'static, str>; POS_DEPTH],
@@ -123,7 +132,9 @@ pub(crate) enum SplitUnit {
 impl SplitUnit {
     pub fn format(&self, lexicon: &LexiconReader) -> String {
         match self {
-            SplitUnit::Ref(id) => id.as_raw().to_string(),
+            SplitUnit::Ref(id) => id
+                .to_storage()
+                .map_or_else(|_| id.to_string(), |v| v.to_string()),
             SplitUnit::Inline {
                 surface,
                 pos,
@@ -151,6 +162,9 @@ pub(crate) trait SplitUnitResolver {
     }
 
     fn resolve_inline(&self, surface: &str, pos: u16, reading: Option<&str>) -> Option<WordId>;
+
+    /// Number of words which match the inline split unit, the first one is used by `resolve`
+    fn count_inline(&self, surface: &str, pos: u16, reading: Option<&str>) -> usize;
 }
 
 pub(crate) struct RawLexiconEntry {
@@ -211,7 +225,7 @@ impl RawLexiconEntry {
         w.write_all(&self.pos.to_le_bytes())?;
         size += 2;
         size += u16w.write_empty_if_equal(w, self.norm_form(), self.headword())?;
-        w.write_all(&self.dic_form.as_raw().to_le_bytes())?;
+        w.write_all(&self.dic_form.to_u32()?.to_le_bytes())?;
         size += 4;
         size += u16w.write_empty_if_equal(w, self.reading(), self.headword())?;
         size += write_u32_array(w, &self.splits_a)?;
@@ -221,6 +235,36 @@ impl RawLexiconEntry {
 
         Ok(size)
     }
+
+    /// Writes the word info in the compressed format, see
+    /// [`WordInfoParser::parse_pooled`](crate::dic::read::word_info::WordInfoParser::parse_pooled)
+    pub(crate) fn write_word_info_pooled<W: Write>(
+        &self,
+        pool: &StringPool,
+        w: &mut W,
+    ) -> DicWriteResult<usize> {
+        let [headword, norm_form, reading] = StringPool::strings_of(self);
+        if self.surface.len() > i16::MAX as usize {
+            return Err(BuildFailure::InvalidSize {
+                actual: self.surface.len(),
+                expected: i16::MAX as usize,
+            });
+        }
+
+        let mut size = 0;
+        size += write_varint(w, pool.offset(headword))?;
+        size += write_varint(w, self.surface.len() as u32)?;
+        size += write_varint(w, self.pos as u32)?;
+        size += write_varint(w, pool.offset(norm_form))?;
+        // -1 (no dictionary form) is stored as 0
+        size += write_varint(w, self.dic_form.to_u32()?.wrapping_add(1))?;
+        size += write_varint(w, pool.offset(reading))?;
+        size += write_varint_array(w, &self.splits_a)?;
+        size += write_varint_array(w, &self.splits_b)?;
+        size += write_varint_array(w, &self.word_structure)?;
+        size += write_varint_array(w, &self.synonym_groups)?;
+        Ok(size)
+    }
 }
 
 pub struct LexiconReader {
@@ -232,6 +276,16 @@ pub struct LexiconReader {
     max_left: i16,
     max_right: i16,
     num_system: usize,
+    format: LexiconFormat,
+    line_offset: usize,
+}
+
+/// Entries of a csv lexicon (or its part) which were parsed independently from others.
+/// POS ids are local to the shard, they are remapped when the shard is merged.
+pub(crate) struct LexiconShard {
+    pub(crate) pos: Vec<StrPosEntry>,
+    pub(crate) entries: Vec<RawLexiconEntry>,
+    pub(crate) unresolved: usize,
 }
 
 impl LexiconReader {
@@ -245,9 +299,15 @@ impl LexiconReader {
             max_left: i16::MAX,
             max_right: i16::MAX,
             num_system: usize::MAX,
+            format: LexiconFormat::Sudachi,
+            line_offset: 0,
         }
     }
 
+    pub fn set_format(&mut self, format: LexiconFormat) {
+        self.format = format;
+    }
+
     pub(crate) fn entries(&self) -> &[RawLexiconEntry] {
         &self.entries
     }
@@ -281,16 +341,6 @@ impl LexiconReader {
         })
     }
 
-    pub fn read_file(&mut self, path: &Path) -> SudachiResult<usize> {
-        let file = File::open(path)?;
-        let map = unsafe { Mmap::map(&file) }?;
-        let filename = path.to_str().unwrap_or("<invalid-utf8>").to_owned();
-        let old_name = self.ctx.set_filename(filename);
-        let res = self.read_bytes(&map);
-        self.ctx.set_filename(old_name);
-        res
-    }
-
     pub fn read_bytes(&mut self, data: &[u8]) -> SudachiResult<usize> {
         let mut reader = csv::ReaderBuilder::new()
             .has_headers(false)
@@ -302,13 +352,13 @@ impl LexiconReader {
             match record {
                 Ok(r) => {
                     let line = r.position().map_or(0, |p| p.line()) as usize;
-                    self.ctx.set_line(line);
+                    self.ctx.set_line(line + self.line_offset);
                     self.read_record(&r)?;
                     nread += 1;
                 }
                 Err(e) => {
                     let line = e.position().map_or(0, |p| p.line()) as usize;
-                    self.ctx.set_line(line);
+                    self.ctx.set_line(line + self.line_offset);
                     return Err(self.ctx.to_sudachi_err(BuildFailure::CsvError(e)));
                 }
             }
@@ -316,8 +366,220 @@ impl LexiconReader {
         Ok(nread)
     }
 
+    /// Sets the file name which is used in error messages, returns the previous one
+    pub(crate) fn set_filename(&mut self, name: String) -> String {
+        self.ctx.set_filename(name)
+    }
+
+    pub(crate) fn format(&self) -> &LexiconFormat {
+        &self.format
+    }
+
+    /// Parses the csv lexicon into a shard using up to `threads` threads.
+    ///
+    /// Data is split into chunks on line boundaries which are parsed independently,
+    /// the result is the same as when the data is parsed by a single thread.
+    pub(crate) fn parse_bytes(&self, data: &[u8], threads: usize) -> SudachiResult<LexiconShard> {
+        self.parse_chunks(data, threads, MIN_CHUNK_SIZE)
+    }
+
+    fn parse_chunks(
+        &self,
+        data: &[u8],
+        threads: usize,
+        min_size: usize,
+    ) -> SudachiResult<LexiconShard> {
+        let chunks = split_lines(data, threads, min_size);
+        if chunks.len() == 1 {
+            return self.parse_shard(data, 0);
+        }
+        let shards = map_parallel(chunks, threads, |(chunk, lines)| {
+            self.parse_shard(chunk, lines)
+        });
+        let mut combined = self.empty_like();
+        for shard in shards {
+            combined.merge_shard(shard?)?;
+        }
+        Ok(combined.into_shard())
+    }
+
+    /// Parses the csv lexicon (or its part starting after `line_offset` lines)
+    /// into a shard which can be merged into any reader with the same format
+    fn parse_shard(&self, data: &[u8], line_offset: usize) -> SudachiResult<LexiconShard> {
+        let mut reader = self.empty_like();
+        reader.line_offset = line_offset;
+        reader.read_bytes(data)?;
+        Ok(reader.into_shard())
+    }
+
+    fn empty_like(&self) -> LexiconReader {
+        let mut reader = LexiconReader::new();
+        reader.format = self.format.clone();
+        reader.ctx.set_filename(self.ctx.filename().to_owned());
+        reader
+    }
+
+    /// Converts entries and POS of this reader into a shard
+    pub(crate) fn into_shard(self) -> LexiconShard {
+        LexiconShard {
+            pos: self.pos.into_keys().collect(),
+            entries: self.entries,
+            unresolved: self.unresolved,
+        }
+    }
+
+    /// Appends entries of the shard.
+    ///
+    /// POS ids are assigned in the order of their first appearance, the same way as
+    /// when all shards are read by this reader one after another.
+    pub(crate) fn merge_shard(&mut self, shard: LexiconShard) -> SudachiResult<usize> {
+        let mut remap = Vec::with_capacity(shard.pos.len());
+        for key in shard.pos {
+            let id = match self.pos.get(&key) {
+                Some(id) => *id,
+                None => {
+                    let pos_id = self.pos.len();
+                    if pos_id > MAX_POS_IDS {
+                        return self
+                            .ctx
+                            .err(BuildFailure::PosLimitExceeded(format!("{:?}", key)));
+                    }
+                    self.pos.insert(key, pos_id as u16);
+                    pos_id as u16
+                }
+            };
+            remap.push(id);
+        }
+
+        let num = shard.entries.len();
+        self.entries.reserve(num);
+        for mut e in shard.entries {
+            e.pos = remap[e.pos as usize];
+            for s in e.splits_a.iter_mut().chain(e.splits_b.iter_mut()) {
+                if let SplitUnit::Inline { pos, .. } = s {
+                    *pos = remap[*pos as usize];
+                }
+            }
+            self.entries.push(e);
+        }
+        self.unresolved += shard.unresolved;
+        Ok(num)
+    }
+
+    /// Reads the csv lexicon like [`read_bytes`](Self::read_bytes), but does not stop
+    /// on invalid rows: they are reported to `on_error` with the line number and skipped.
+    ///
+    /// Returns line numbers of the read entries.
+    pub(crate) fn read_bytes_lenient<F: FnMut(usize, String)>(
+        &mut self,
+        data: &[u8],
+        mut on_error: F,
+    ) -> Vec<usize> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .trim(Trim::None)
+            .flexible(true)
+            .from_reader(data);
+        let mut lines = Vec::new();
+        for record in reader.records() {
+            match record {
+                Ok(r) => {
+                    let line = r.position().map_or(0, |p| p.line()) as usize;
+                    self.ctx.set_line(line);
+                    match self.read_record(&r) {
+                        Ok(()) => lines.push(line),
+                        Err(SudachiError::DictionaryCompilationError(e)) => {
+                            on_error(line, e.cause.to_string())
+                        }
+                        Err(e) => on_error(line, e.to_string()),
+                    }
+                }
+                Err(e) => {
+                    let line = e.position().map_or(0, |p| p.line()) as usize;
+                    on_error(line, e.to_string());
+                    if e.is_io_error() {
+                        break;
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    /// Number of POS which were preloaded from the system dictionary
+    pub(crate) fn num_preloaded_pos(&self) -> usize {
+        self.start_pos
+    }
+
+    /// Maximum word ids of references to the system (0) and user (1) dictionaries
+    pub(crate) fn reference_limits(&self) -> (usize, usize) {
+        match self.num_system {
+            // means that we compile system dictionary, there must not be user words
+            usize::MAX => (self.entries.len(), 0),
+            // compiling user dictionary
+            x => (x, self.entries.len()),
+        }
+    }
+
     fn read_record(&mut self, data: &StringRecord) -> SudachiResult<()> {
-        self.parse_record(data).map(|r| self.entries.push(r))
+        let entry = match &self.format {
+            LexiconFormat::Sudachi => self.parse_record(data),
+            LexiconFormat::Mecab(columns) => {
+                let columns = columns.clone();
+                self.parse_mecab_record(data, &columns)
+            }
+        };
+        entry.map(|r| self.entries.push(r))
+    }
+
+    fn parse_mecab_record(
+        &mut self,
+        data: &StringRecord,
+        columns: &MecabColumns,
+    ) -> SudachiResult<RawLexiconEntry> {
+        let ctx = std::mem::take(&mut self.ctx);
+        let rec = RecordWrapper { record: data, ctx };
+        let surface = rec.get(columns.surface, "surface", |s| Ok(s.to_owned()))?;
+        let left_id = rec.get(columns.left_id, "left_id", parse_i16)?;
+        let right_id = rec.get(columns.right_id, "right_id", parse_i16)?;
+        let cost = rec.get(columns.cost, "cost", parse_i16)?;
+
+        // absent or unknown (*) values fall back to the default
+        let optional = |idx: Option<usize>| {
+            idx.and_then(|i| data.get(i))
+                .filter(|v| !v.is_empty() && *v != "*")
+        };
+        let [p1, p2, p3, p4, p5, p6] = columns
+            .pos
+            .map(|idx| Cow::Borrowed(idx.and_then(|i| data.get(i)).unwrap_or("*")));
+        let pos = rec.ctx.transform(self.pos_of([p1, p2, p3, p4, p5, p6]))?;
+
+        if surface.is_empty() {
+            return rec.ctx.err(BuildFailure::EmptySurface);
+        }
+
+        self.ctx = rec.ctx;
+
+        Ok(RawLexiconEntry {
+            left_id,
+            right_id,
+            cost,
+            dic_form: WordId::INVALID,
+            norm_form: optional(columns.lemma)
+                .filter(|v| *v != surface)
+                .map(|v| v.to_owned()),
+            reading: optional(columns.reading)
+                .filter(|v| *v != surface)
+                .map(|v| v.to_owned()),
+            headword: None,
+            surface,
+            pos,
+            splitting: Mode::A,
+            splits_a: Vec::new(),
+            splits_b: Vec::new(),
+            word_structure: Vec::new(),
+            synonym_groups: Vec::new(),
+        })
     }
 
     fn parse_record(&mut self, data: &StringRecord) -> SudachiResult<RawLexiconEntry> {
@@ -403,12 +665,7 @@ impl LexiconReader {
         let mut ctx = DicCompilationCtx::default();
         ctx.set_filename("<entry id>".to_owned());
         ctx.set_line(0);
-        let (max_0, max_1) = match self.num_system {
-            // means that we compile system dictionary, there must not be user words
-            usize::MAX => (self.entries.len(), 0),
-            // compiling user dictionary
-            x => (x, self.entries.len()),
-        };
+        let (max_0, max_1) = self.reference_limits();
         for e in self.entries.iter() {
             if e.left_id >= self.max_left {
                 return ctx.err(BuildFailure::InvalidFieldSize {
@@ -457,7 +714,7 @@ impl LexiconReader {
         Ok(())
     }
 
-    fn validate_wid(
+    pub(crate) fn validate_wid(
         wid: WordId,
         dic0_max: usize,
         dic1_max: usize,
@@ -574,6 +831,42 @@ impl LexiconReader {
         Ok(total)
     }
 
+    /// Replaces placeholder costs (`i16::MIN`) with the ones estimated from the analysis
+    /// of the surface with the given dictionary, the same way as it is done when
+    /// a user dictionary with placeholder costs is loaded.
+    ///
+    /// Analysis requires OOV providers, without them placeholders are kept as is
+    /// and costs are estimated when the dictionary is loaded.
+    pub(crate) fn estimate_costs<D: DictionaryAccess>(
+        &mut self,
+        dict: &D,
+    ) -> SudachiResult<Vec<EstimatedCost>> {
+        let mut result = Vec::new();
+        if dict.oov_provider_plugins().is_empty()
+            || !self.entries.iter().any(|e| e.cost == i16::MIN)
+        {
+            return Ok(result);
+        }
+        let mut tok = StatefulTokenizer::create(dict, false, Mode::C);
+        let mut ms = MorphemeList::empty(dict);
+        for (index, e) in self.entries.iter_mut().enumerate() {
+            if e.cost != i16::MIN {
+                continue;
+            }
+            tok.reset().push_str(e.surface());
+            tok.do_tokenize()?;
+            ms.collect_results(&mut tok)?;
+            e.cost = Lexicon::estimate_cost(&ms);
+            result.push(EstimatedCost {
+                index,
+                surface: e.surface.clone(),
+                cost: e.cost,
+                num_morphemes: ms.len(),
+            });
+        }
+        Ok(result)
+    }
+
     fn resolve_split<R: SplitUnitResolver>(unit: &mut SplitUnit, resolver: &R) -> Option<usize> {
         match unit {
             SplitUnit::Ref(_) => Some(0),
@@ -616,10 +909,68 @@ impl<'a> RecordWrapper<'a> {
     }
 }
 
+/// Serialized word infos of lexicon entries
+pub(crate) struct WordInfos {
+    chunks: Vec<Vec<u8>>,
+    sizes: Vec<usize>,
+    pool: Option<StringPool>,
+}
+
+impl WordInfos {
+    /// Serializes word infos using up to `threads` threads,
+    /// the result does not depend on the number of threads.
+    ///
+    /// Strings are stored in the string pool if `compress` is set.
+    pub(crate) fn serialize(
+        entries: &[RawLexiconEntry],
+        threads: usize,
+        compress: bool,
+    ) -> SudachiResult<Self> {
+        let pool = match compress {
+            true => {
+                let mut ctx = DicCompilationCtx::memory();
+                ctx.set_filename("<string pool>".to_owned());
+                Some(ctx.transform(StringPool::build(entries))?)
+            }
+            false => None,
+        };
+        let chunk_size = std::cmp::max(entries.len() / threads.max(1), 64 * 1024);
+        let tasks: Vec<_> = entries.chunks(chunk_size).enumerate().collect();
+        let parts = map_parallel(tasks, threads, |(i, chunk)| {
+            let mut ctx = DicCompilationCtx::memory();
+            ctx.set_filename("<write entries>".to_owned());
+            ctx.set_line(i * chunk_size);
+            let mut u16w = Utf16Writer::new();
+            let mut buffer = Vec::with_capacity(chunk.len() * 32);
+            let mut sizes = Vec::with_capacity(chunk.len());
+            for e in chunk {
+                let size = match pool.as_ref() {
+                    None => e.write_word_info(&mut u16w, &mut buffer),
+                    Some(p) => e.write_word_info_pooled(p, &mut buffer),
+                };
+                sizes.push(ctx.transform(size)?);
+                ctx.add_line(1);
+            }
+            Ok::<_, SudachiError>((buffer, sizes))
+        });
+
+        let mut result = WordInfos {
+            chunks: Vec::with_capacity(parts.len()),
+            sizes: Vec::with_capacity(entries.len()),
+            pool,
+        };
+        for part in parts {
+            let (buffer, sizes) = part?;
+            result.chunks.push(buffer);
+            result.sizes.extend(sizes);
+        }
+        Ok(result)
+    }
+}
+
 pub struct LexiconWriter<'a> {
     entries: &'a [RawLexiconEntry],
-    u16: Utf16Writer,
-    buffer: Vec<u8>,
+    infos: WordInfos,
     offset: usize,
     reporter: &'a mut Reporter,
 }
@@ -627,13 +978,14 @@ pub struct LexiconWriter<'a> {
 impl<'a> LexiconWriter<'a> {
     pub(crate) fn new(
         entries: &'a [RawLexiconEntry],
+        infos: WordInfos,
         offset: usize,
         reporter: &'a mut Reporter,
     ) -> Self {
+        assert_eq!(entries.len(), infos.sizes.len());
         Self {
-            buffer: Vec::with_capacity(entries.len() * 32),
             entries,
-            u16: Utf16Writer::new(),
+            infos,
             offset,
             reporter,
         }
@@ -654,25 +1006,36 @@ impl<'a> LexiconWriter<'a> {
             ctx.add_line(1);
         }
         self.reporter.collect(total, rep);
+
+        let pool_size = match self.infos.pool.as_ref() {
+            None => 0,
+            Some(pool) => {
+                let rep = ReportBuilder::new("string pool");
+                let size = ctx.transform(pool.write_to(w))?;
+                self.reporter.collect(size, rep);
+                total += size;
+                size
+            }
+        };
         let start = total;
 
         let rep = ReportBuilder::new("wordinfo_offsets");
-        ctx.set_line(0);
-        let offset_base = self.offset + (6 + 4) * self.entries.len() + 4;
+        let offset_base = self.offset + (6 + 4) * self.entries.len() + 4 + pool_size;
         let mut word_offset = 0;
-        for e in self.entries {
+        for size in self.infos.sizes.iter() {
             let u32_offset = (offset_base + word_offset) as u32;
             w.write_all(&u32_offset.to_le_bytes())?;
-            let size = ctx.transform(e.write_word_info(&mut self.u16, &mut self.buffer))?;
             word_offset += size;
             total += 4;
-            ctx.add_line(1);
         }
         self.reporter.collect(total - start, rep);
 
         let rep = ReportBuilder::new("wordinfos (copy only)");
-        let info_size = self.buffer.len();
-        w.write_all(&self.buffer)?;
+        let mut info_size = 0;
+        for chunk in self.infos.chunks.iter() {
+            w.write_all(chunk)?;
+            info_size += chunk.len();
+        }
         self.reporter.collect(info_size, rep);
 
         Ok(total + info_size)
@@ -208,3 +208,54 @@ fn word_info_rw() {
     assert_eq!(wi.synonym_group_ids().len(), 0);
     assert_eq!(wi.dictionary_form_word_id(), -1);
 }
+
+fn compile_system(bldr: &mut DictBuilder<crate::dic::build::NoDic>) -> Vec<u8> {
+    bldr.set_compile_time(std::time::UNIX_EPOCH);
+    bldr.read_conn(include_bytes!("../test/matrix_10x10.def"))
+        .unwrap();
+    bldr.resolve().unwrap();
+    let mut result = Vec::new();
+    bldr.compile(&mut result).unwrap();
+    result
+}
+
+#[test]
+fn parse_chunks_same_as_sequential() {
+    let data = include_bytes!("../test/lex.csv");
+    let mut sequential = DictBuilder::new_system();
+    sequential.set_threads(1);
+    sequential.read_lexicon(data).unwrap();
+
+    let mut parallel = DictBuilder::new_system();
+    parallel.set_threads(4);
+    let shard = parallel.lexicon.parse_chunks(data, 4, 1).unwrap();
+    assert_eq!(46, parallel.lexicon.merge_shard(shard).unwrap());
+
+    assert_eq!(
+        compile_system(&mut sequential),
+        compile_system(&mut parallel)
+    );
+}
+
+#[test]
+fn parse_chunks_reports_global_lines() {
+    let mut data = String::new();
+    for i in 0..10 {
+        writeln!(
+            data,
+            "x{},-1,-1,5293,京都,名詞,固有名詞,地名,一般,*,*,キョウト,京都,*,A,*,*,*,*",
+            i
+        )
+        .unwrap()
+    }
+    data.push_str("京都,1,1,5293,京都,名詞,固有名詞,地名,一般,*,*,キョウト,京都,*,A,*,*\n");
+    let rdr = LexiconReader::new();
+    assert_matches!(
+        rdr.parse_chunks(data.as_bytes(), 4, 1).err(),
+        Some(SudachiError::DictionaryCompilationError(DicBuildError {
+            cause: BuildFailure::NoRawField(_),
+            line: 11,
+            ..
+        }))
+    );
+}
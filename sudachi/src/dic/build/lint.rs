@@ -0,0 +1,507 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Checks of csv lexicons which do not compile the dictionary.
+//!
+//! Unlike [`DictBuilder`](crate::dic::build::DictBuilder), which stops on the first error,
+//! [`Linter`] collects all found problems with their file and line numbers.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::Write;
+
+use memmap2::Mmap;
+use serde_json::{json, Value};
+
+use crate::analysis::stateless_tokenizer::DictionaryAccess;
+use crate::dic::build::conn::ConnBuffer;
+use crate::dic::build::format::LexiconFormat;
+use crate::dic::build::lexicon::{LexiconReader, RawLexiconEntry, SplitUnit, SplitUnitResolver};
+use crate::dic::build::report::ReportFormat;
+use crate::dic::build::resolve::{BinDictResolver, RawDictResolver};
+use crate::dic::build::{AsDataSource, DataSource, NoDic};
+use crate::dic::word_id::WordId;
+use crate::error::SudachiResult;
+
+/// Costs with larger absolute values are reported as suspicious
+const MAX_USUAL_COST: i16 = 20000;
+
+/// Severity of a problem: errors make the dictionary build fail,
+/// warnings are likely mistakes in the lexicon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintLevel {
+    Warning,
+    Error,
+}
+
+impl Display for LintLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let repr = match self {
+            LintLevel::Warning => "warning",
+            LintLevel::Error => "error",
+        };
+        f.write_str(repr)
+    }
+}
+
+/// Type of a problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// Row can't be parsed
+    Parse,
+    /// Same word is defined more than once
+    Duplicate,
+    /// Cost is likely a mistake
+    Cost,
+    /// Reading is not katakana
+    Reading,
+    /// Normalized form is not a word of the dictionary
+    NormalizedForm,
+    /// Split can't be resolved or resolves to several words
+    Split,
+    /// POS is not defined in the system dictionary
+    Pos,
+    /// Left or right id is out of the connection matrix
+    ConnId,
+    /// Word id reference is out of the dictionary
+    Reference,
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let repr = match self {
+            LintKind::Parse => "parse",
+            LintKind::Duplicate => "duplicate",
+            LintKind::Cost => "cost",
+            LintKind::Reading => "reading",
+            LintKind::NormalizedForm => "normalized-form",
+            LintKind::Split => "split",
+            LintKind::Pos => "pos",
+            LintKind::ConnId => "conn-id",
+            LintKind::Reference => "reference",
+        };
+        f.write_str(repr)
+    }
+}
+
+/// A problem in the lexicon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    pub file: String,
+    pub line: usize,
+    pub level: LintLevel,
+    pub kind: LintKind,
+    pub message: String,
+}
+
+impl LintIssue {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "file": self.file,
+            "line": self.line,
+            "level": self.level.to_string(),
+            "kind": self.kind.to_string(),
+            "message": self.message,
+        })
+    }
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.file, self.line, self.level, self.kind, self.message
+        )
+    }
+}
+
+/// All problems found in the lexicons, ordered by file and line
+#[derive(Debug, Clone, Default)]
+pub struct LintReport {
+    /// Number of entries which were read successfully
+    pub entries: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    pub fn num_errors(&self) -> usize {
+        self.count(LintLevel::Error)
+    }
+
+    pub fn num_warnings(&self) -> usize {
+        self.count(LintLevel::Warning)
+    }
+
+    fn count(&self, level: LintLevel) -> usize {
+        self.issues.iter().filter(|i| i.level == level).count()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "entries": self.entries,
+            "errors": self.num_errors(),
+            "warnings": self.num_warnings(),
+            "issues": self.issues.iter().map(|i| i.to_json()).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W, format: ReportFormat) -> SudachiResult<()> {
+        match format {
+            ReportFormat::Text => {
+                for issue in self.issues.iter() {
+                    writeln!(w, "{}", issue)?;
+                }
+                writeln!(
+                    w,
+                    "{} entries, {} errors, {} warnings",
+                    self.entries,
+                    self.num_errors(),
+                    self.num_warnings()
+                )?;
+            }
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut *w, &self.to_json())?;
+                writeln!(w)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks csv lexicons without building the dictionary.
+///
+/// Checks which need the connection matrix are done only if it is provided,
+/// either with [`Linter::read_conn`] or by the system dictionary of a user lexicon.
+pub struct Linter<D> {
+    lexicon: LexiconReader,
+    system: Option<D>,
+    conn: Option<(i16, i16)>,
+    files: Vec<String>,
+    /// (file index, line) of each entry
+    positions: Vec<(usize, usize)>,
+    /// (file index, line, level, kind, message)
+    issues: Vec<(usize, usize, LintLevel, LintKind, String)>,
+}
+
+impl Linter<NoDic> {
+    /// Creates a new linter for system dictionary lexicons
+    pub fn new_system() -> Self {
+        Self::new_empty()
+    }
+}
+
+impl<D: DictionaryAccess> Linter<D> {
+    fn new_empty() -> Self {
+        Self {
+            lexicon: LexiconReader::new(),
+            system: None,
+            conn: None,
+            files: Vec::new(),
+            positions: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    /// Creates a new linter for user dictionary lexicons
+    pub fn new_user(system: D) -> Self {
+        let mut linter = Self::new_empty();
+        linter.lexicon.preload_pos(system.grammar());
+        let cm = system.grammar().conn_matrix();
+        linter.conn = Some((cm.num_left() as _, cm.num_right() as _));
+        linter
+            .lexicon
+            .set_num_system_words(system.lexicon().size() as usize);
+        linter.system = Some(system);
+        linter
+    }
+
+    /// Set the format of csv lexicons which are read after this call
+    pub fn set_lexicon_format(&mut self, format: LexiconFormat) {
+        self.lexicon.set_format(format);
+    }
+
+    /// Read the connection matrix to check left and right ids of system lexicons
+    pub fn read_conn<'a, T: AsDataSource<'a> + 'a>(&mut self, data: T) -> SudachiResult<()> {
+        let mut conn = ConnBuffer::new();
+        match data.convert() {
+            DataSource::File(p) => conn.read_file(p),
+            DataSource::Data(d) => conn.read(d),
+        }?;
+        self.conn = Some((conn.left(), conn.right()));
+        Ok(())
+    }
+
+    /// Read the csv lexicon from either a file or an in-memory buffer.
+    ///
+    /// Invalid rows are reported as issues, only I/O errors are returned.
+    /// Returns the number of read entries.
+    pub fn read_lexicon<'a, T: AsDataSource<'a> + 'a>(&mut self, data: T) -> SudachiResult<usize> {
+        let file = self.files.len();
+        self.files.push(data.name());
+        let issues = &mut self.issues;
+        let mut on_error = |line: usize, message: String| {
+            issues.push((file, line, LintLevel::Error, LintKind::Parse, message))
+        };
+        let lines = match data.convert() {
+            DataSource::File(p) => {
+                let map = unsafe { Mmap::map(&File::open(p)?) }?;
+                self.lexicon.read_bytes_lenient(&map, &mut on_error)
+            }
+            DataSource::Data(d) => self.lexicon.read_bytes_lenient(d, &mut on_error),
+        };
+        let num = lines.len();
+        self.positions.extend(lines.into_iter().map(|l| (file, l)));
+        Ok(num)
+    }
+
+    /// Check the read entries and return all found problems
+    pub fn lint(mut self) -> SudachiResult<LintReport> {
+        let system_resolver = match self.system.as_ref() {
+            Some(d) => Some(BinDictResolver::new(d)?),
+            None => None,
+        };
+        let entries = self.lexicon.entries();
+        let resolver = RawDictResolver::new(entries, self.system.is_some());
+        let words: HashSet<&str> = entries
+            .iter()
+            .flat_map(|e| [e.surface(), e.headword()])
+            .collect();
+        let mut seen = HashMap::new();
+        let mut issues = Vec::new();
+
+        for (i, e) in entries.iter().enumerate() {
+            let (file, line) = self.positions[i];
+            let mut report = |level, kind, message| issues.push((file, line, level, kind, message));
+
+            let key = (
+                e.surface(),
+                e.left_id,
+                e.right_id,
+                e.headword(),
+                e.pos,
+                e.reading(),
+                e.norm_form(),
+                e.dic_form,
+            );
+            if let Some(&(f, l)) = seen.get(&key) {
+                report(
+                    LintLevel::Warning,
+                    LintKind::Duplicate,
+                    format!("duplicate of {}:{}", self.files[f], l),
+                );
+            } else {
+                seen.insert(key, (file, line));
+            }
+
+            self.check_conn(e, &mut report);
+            self.check_cost(e, &mut report);
+            check_reading(e, &mut report);
+
+            if e.norm_form.is_some()
+                && !words.contains(e.norm_form())
+                && !self.is_system_word(e.norm_form())
+            {
+                report(
+                    LintLevel::Warning,
+                    LintKind::NormalizedForm,
+                    format!("normalized form {} is not in the dictionary", e.norm_form()),
+                );
+            }
+
+            // user lexicons can define new POS, but often it is a typo
+            if self.system.is_some() && e.pos as usize >= self.lexicon.num_preloaded_pos() {
+                let pos = self.lexicon.pos_obj(e.pos).unwrap();
+                report(
+                    LintLevel::Warning,
+                    LintKind::Pos,
+                    format!("POS {:?} is not in the system dictionary", pos),
+                );
+            }
+
+            let limits = self.lexicon.reference_limits();
+            let mut refs: Vec<(&'static str, WordId)> = Vec::new();
+            if e.dic_form != WordId::INVALID {
+                refs.push(("dictionary form", e.dic_form));
+            }
+            refs.extend(e.word_structure.iter().map(|w| ("word structure", *w)));
+            for (field, wid) in refs {
+                if LexiconReader::validate_wid(wid, limits.0, limits.1, field).is_err() {
+                    report(
+                        LintLevel::Error,
+                        LintKind::Reference,
+                        format!("{} {} is not in the dictionary", field, ref_str(wid)),
+                    );
+                }
+            }
+            for (name, splits) in [("split A", &e.splits_a), ("split B", &e.splits_b)] {
+                for unit in splits {
+                    match unit {
+                        SplitUnit::Ref(wid) => {
+                            if LexiconReader::validate_wid(*wid, limits.0, limits.1, name).is_err()
+                            {
+                                report(
+                                    LintLevel::Error,
+                                    LintKind::Reference,
+                                    format!("{} {} is not in the dictionary", name, ref_str(*wid)),
+                                );
+                            }
+                        }
+                        SplitUnit::Inline {
+                            surface,
+                            pos,
+                            reading,
+                        } => {
+                            let reading = reading.as_deref();
+                            let count = resolver.count_inline(surface, *pos, reading)
+                                + system_resolver
+                                    .as_ref()
+                                    .map_or(0, |r| r.count_inline(surface, *pos, reading));
+                            let unit = unit.format(&self.lexicon);
+                            if count == 0 {
+                                report(
+                                    LintLevel::Error,
+                                    LintKind::Split,
+                                    format!("{} {} can't be resolved", name, unit),
+                                );
+                            } else if count > 1 {
+                                report(
+                                    LintLevel::Warning,
+                                    LintKind::Split,
+                                    format!(
+                                        "{} {} is ambiguous, it matches {} words",
+                                        name, unit, count
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.issues.extend(issues);
+        // sort is stable, so issues of the same line keep the order of checks
+        self.issues.sort_by_key(|(f, l, ..)| (*f, *l));
+        let files = self.files;
+        Ok(LintReport {
+            entries: entries.len(),
+            issues: self
+                .issues
+                .into_iter()
+                .map(|(f, line, level, kind, message)| LintIssue {
+                    file: files[f].clone(),
+                    line,
+                    level,
+                    kind,
+                    message,
+                })
+                .collect(),
+        })
+    }
+
+    fn is_system_word(&self, word: &str) -> bool {
+        match self.system.as_ref() {
+            Some(d) => d
+                .lexicon()
+                .lookup(word.as_bytes(), 0)
+                .any(|e| e.end == word.len()),
+            None => false,
+        }
+    }
+
+    fn check_conn<F: FnMut(LintLevel, LintKind, String)>(
+        &self,
+        e: &RawLexiconEntry,
+        report: &mut F,
+    ) {
+        let (left, right) = match self.conn {
+            Some(c) => c,
+            None => return,
+        };
+        if e.left_id >= left {
+            report(
+                LintLevel::Error,
+                LintKind::ConnId,
+                format!("left_id {} is out of the matrix size {}", e.left_id, left),
+            );
+        }
+        if e.should_index() && !(0..right).contains(&e.right_id) {
+            report(
+                LintLevel::Error,
+                LintKind::ConnId,
+                format!(
+                    "right_id {} is out of the matrix size {}",
+                    e.right_id, right
+                ),
+            );
+        }
+    }
+
+    fn check_cost<F: FnMut(LintLevel, LintKind, String)>(
+        &self,
+        e: &RawLexiconEntry,
+        report: &mut F,
+    ) {
+        if !e.should_index() {
+            return;
+        }
+        let message = match e.cost {
+            i16::MIN if self.system.is_none() => {
+                "placeholder cost -32768 is estimated only in user dictionaries".to_owned()
+            }
+            i16::MIN => return,
+            -1 => "cost -1 is used as is, use -32768 to estimate the cost".to_owned(),
+            c if c.abs() > MAX_USUAL_COST => {
+                format!("cost {} is out of the usual range ±{}", c, MAX_USUAL_COST)
+            }
+            _ => return,
+        };
+        report(LintLevel::Warning, LintKind::Cost, message);
+    }
+}
+
+fn check_reading<F: FnMut(LintLevel, LintKind, String)>(e: &RawLexiconEntry, report: &mut F) {
+    let reading = e.reading();
+    if reading.chars().any(|c| is_hiragana(c) || is_kanji(c)) {
+        report(
+            LintLevel::Warning,
+            LintKind::Reading,
+            format!("reading {} is not katakana", reading),
+        );
+    }
+}
+
+fn is_hiragana(c: char) -> bool {
+    ('\u{3041}'..='\u{309f}').contains(&c)
+}
+
+fn is_kanji(c: char) -> bool {
+    ('\u{3400}'..='\u{4dbf}').contains(&c)
+        || ('\u{4e00}'..='\u{9fff}').contains(&c)
+        || ('\u{f900}'..='\u{faff}').contains(&c)
+        || ('\u{20000}'..='\u{3ffff}').contains(&c)
+}
+
+/// Word id in the csv lexicon syntax
+fn ref_str(wid: WordId) -> String {
+    if wid.is_user() {
+        format!("U{}", wid.word())
+    } else {
+        wid.word().to_string()
+    }
+}
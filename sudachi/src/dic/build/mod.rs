@@ -14,29 +14,42 @@
  *  limitations under the License.
  */
 
+use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
 
 use crate::analysis::stateless_tokenizer::DictionaryAccess;
+use crate::dic::build::cache::ShardCache;
 use crate::dic::build::error::{BuildFailure, DicBuildError, DicCompilationCtx};
-use crate::dic::build::index::IndexBuilder;
-use crate::dic::build::lexicon::LexiconWriter;
+use crate::dic::build::format::LexiconFormat;
+use crate::dic::build::index::write_index;
+use crate::dic::build::lexicon::{LexiconWriter, WordInfos};
 use crate::dic::build::report::{DictPartReport, ReportBuilder, Reporter};
 use crate::dic::build::resolve::{BinDictResolver, ChainedResolver, RawDictResolver};
+use crate::dic::connect::MatrixFormat;
 use crate::dic::grammar::Grammar;
 use crate::dic::header::{Header, HeaderVersion, SystemDictVersion, UserDictVersion};
 use crate::dic::lexicon_set::LexiconSet;
-use crate::dic::word_id::WordId;
+use crate::dic::verify::checksum;
 use crate::error::SudachiResult;
 use crate::plugin::input_text::InputTextPlugin;
 use crate::plugin::oov::OovProviderPlugin;
 use crate::plugin::path_rewrite::PathRewritePlugin;
 
+mod cache;
 pub(crate) mod conn;
+pub mod decompile;
+pub mod diff;
 pub mod error;
+pub mod format;
 pub(crate) mod index;
 pub(crate) mod lexicon;
+pub mod lint;
+mod parallel;
 pub(crate) mod parse;
+mod pool;
 pub(crate) mod primitives;
 pub mod report;
 mod resolve;
@@ -129,8 +142,13 @@ pub struct DictBuilder<D> {
     ctx: DicCompilationCtx,
     header: Header,
     resolved: bool,
+    checksum: bool,
     prebuilt: Option<D>,
     reporter: Reporter,
+    threads: usize,
+    cache: Option<ShardCache>,
+    matrix_format: MatrixFormat,
+    compress: bool,
 }
 
 impl DictBuilder<NoDic> {
@@ -149,8 +167,13 @@ impl<D: DictionaryAccess> DictBuilder<D> {
             ctx: DicCompilationCtx::default(),
             header: Header::new(),
             resolved: false,
+            checksum: false,
             prebuilt: None,
             reporter: Reporter::new(),
+            threads: parallel::default_threads(),
+            cache: None,
+            matrix_format: MatrixFormat::Dense,
+            compress: false,
         }
     }
 
@@ -182,17 +205,84 @@ impl<D: DictionaryAccess> DictBuilder<D> {
         self.header.description = description.into()
     }
 
+    /// Write the checksum of the dictionary contents into the header.
+    /// It is checked by [`verify`](crate::dic::verify), description can't be longer than
+    /// [`Header::MAX_DESCRIPTION_WITH_CHECKSUM`] bytes in this case.
+    pub fn set_checksum(&mut self, checksum: bool) {
+        self.checksum = checksum;
+    }
+
+    /// Set the storage format of the connection matrix.
+    ///
+    /// [`MatrixFormat::Dedup`] makes dictionaries with many identical matrix rows
+    /// smaller at the cost of slower lookups.
+    /// Such dictionaries have a new header version, which also has compressed word infos
+    /// (see [`set_compressed_word_infos`](Self::set_compressed_word_infos)),
+    /// and can't be read by versions which do not support it.
+    pub fn set_matrix_format(&mut self, format: MatrixFormat) {
+        self.matrix_format = format;
+        self.update_version();
+    }
+
+    /// Store word infos in the compressed format: strings are deduplicated in the string pool
+    /// and numbers are stored as varints.
+    ///
+    /// Such dictionaries have a new header version and can't be read by versions which
+    /// do not support it.
+    pub fn set_compressed_word_infos(&mut self, compress: bool) {
+        self.compress = compress;
+        self.update_version();
+    }
+
+    /// Set the format of csv lexicons which are read after this call
+    pub fn set_lexicon_format(&mut self, format: LexiconFormat) {
+        self.lexicon.set_format(format);
+    }
+
+    /// Set the number of threads which are used to parse csv lexicons and
+    /// to serialize word infos, 0 means the number of available CPUs.
+    ///
+    /// Built dictionaries do not depend on the number of threads.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = match threads {
+            0 => parallel::default_threads(),
+            n => n,
+        };
+    }
+
+    /// Use the directory to cache parsed csv lexicons.
+    ///
+    /// Lexicons with the same contents (and format) as during one of the previous builds
+    /// are taken from the cache instead of being parsed again.
+    pub fn set_cache_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.cache = Some(ShardCache::new(dir.into()));
+    }
+
     /// Read the csv lexicon from either a file or an in-memory buffer
     pub fn read_lexicon<'a, T: AsDataSource<'a> + 'a>(&mut self, data: T) -> SudachiResult<usize> {
         let report = ReportBuilder::new(data.name()).read();
         let result = match data.convert() {
-            DataSource::File(p) => self.lexicon.read_file(p),
-            DataSource::Data(d) => self.lexicon.read_bytes(d),
+            DataSource::File(p) => {
+                let file = File::open(p)?;
+                let map = unsafe { Mmap::map(&file) }?;
+                let filename = p.to_str().unwrap_or("<invalid-utf8>").to_owned();
+                let old_name = self.lexicon.set_filename(filename);
+                let res = self.read_lexicon_data(&map, report);
+                self.lexicon.set_filename(old_name);
+                res
+            }
+            DataSource::Data(d) => self.read_lexicon_data(d, report),
         };
-        self.reporter.collect_r(result, report)
+        let (count, report) = result?;
+        self.reporter.collect(count, report);
+        Ok(count)
     }
 
-    /// Read the connection matrix from either a file or an in-memory buffer
+    /// Read the connection matrix from either a file or an in-memory buffer.
+    ///
+    /// Besides the MeCab `matrix.def` format, sparse matrices are accepted:
+    /// their header line is `num_left num_right default_cost` and only entries
+    /// with other costs are listed.
     pub fn read_conn<'a, T: AsDataSource<'a> + 'a>(&mut self, data: T) -> SudachiResult<()> {
         let report = ReportBuilder::new(data.name()).read();
         match data.convert() {
@@ -214,16 +304,32 @@ impl<D: DictionaryAccess> DictBuilder<D> {
         let report = ReportBuilder::new("validate").read();
         self.lexicon.validate_entries()?;
         self.reporter.collect(self.lexicon.entries().len(), report);
-        let mut written = self.header.write_to(w)?;
-        written += self.write_grammar(w)?;
-        self.write_lexicon(w, written)?;
+        if !self.checksum {
+            self.header.checksum = None;
+            let mut written = self.header.write_to(w)?;
+            written += self.write_grammar(w)?;
+            self.write_lexicon(w, written)?;
+            return Ok(());
+        }
+        // checksum covers everything after the header, so the contents need to be buffered
+        let mut contents = Vec::new();
+        let written = Header::STORAGE_SIZE + self.write_grammar(&mut contents)?;
+        self.write_lexicon(&mut contents, written)?;
+        self.header.checksum = Some(checksum(&contents));
+        self.header.write_to(w)?;
+        w.write_all(&contents)?;
         Ok(())
     }
 
     /// Resolve the dictionary references.
     ///
+    /// User dictionary entries with the placeholder cost (`-32768`) get the cost
+    /// estimated from the analysis of their surfaces with the system dictionary,
+    /// chosen values are available in the [`report`](Self::report).
+    ///
     /// Returns the number of resolved entries
     pub fn resolve(&mut self) -> SudachiResult<usize> {
+        self.estimate_costs()?;
         self.resolve_impl()
     }
 
@@ -236,12 +342,21 @@ impl<D: DictionaryAccess> DictBuilder<D> {
 // private functions
 impl<D: DictionaryAccess> DictBuilder<D> {
     fn set_user(&mut self, user: bool) {
-        if user {
-            self.header.version = HeaderVersion::UserDict(UserDictVersion::Version3)
-        } else {
-            self.header.version = HeaderVersion::SystemDict(SystemDictVersion::Version2)
-        }
         self.user = user;
+        self.update_version();
+    }
+
+    /// Sets the oldest header version which supports the selected formats
+    fn update_version(&mut self) {
+        let dense = self.matrix_format == MatrixFormat::Dense;
+        self.header.version = match (self.user, self.compress, dense) {
+            (false, false, true) => HeaderVersion::SystemDict(SystemDictVersion::Version2),
+            (false, true, true) => HeaderVersion::SystemDict(SystemDictVersion::Version3),
+            (false, _, false) => HeaderVersion::SystemDict(SystemDictVersion::Version4),
+            (true, false, true) => HeaderVersion::UserDict(UserDictVersion::Version3),
+            (true, true, true) => HeaderVersion::UserDict(UserDictVersion::Version4),
+            (true, _, false) => HeaderVersion::UserDict(UserDictVersion::Version5),
+        };
     }
 
     fn write_grammar<W: Write>(&mut self, w: &mut W) -> SudachiResult<usize> {
@@ -250,48 +365,68 @@ impl<D: DictionaryAccess> DictBuilder<D> {
         size += self.lexicon.write_pos_table(w)?;
         self.reporter.collect(size, r1);
         let r2 = ReportBuilder::new("conn_matrix");
-        size += self.conn.write_to(w)?;
+        size += self.conn.write_to(w, self.matrix_format)?;
         self.reporter.collect(size, r2);
         Ok(size)
     }
 
-    fn write_index<W: Write>(&mut self, w: &mut W) -> SudachiResult<usize> {
-        let mut size = 0;
-        let mut index = IndexBuilder::new();
-        for (i, e) in self.lexicon.entries().iter().enumerate() {
-            if e.should_index() {
-                let wid = WordId::checked(0, i as u32)?;
-                index.add(e.surface(), wid);
+    /// Returns the number of read entries and the report, which records
+    /// whether the entries were taken from the cache
+    fn read_lexicon_data(
+        &mut self,
+        data: &[u8],
+        report: ReportBuilder,
+    ) -> SudachiResult<(usize, ReportBuilder)> {
+        let cache = match self.cache.as_ref() {
+            None if self.threads <= 1 => return self.lexicon.read_bytes(data).map(|n| (n, report)),
+            None => {
+                let shard = self.lexicon.parse_bytes(data, self.threads)?;
+                return self.lexicon.merge_shard(shard).map(|n| (n, report));
             }
+            Some(c) => c,
+        };
+        let key = ShardCache::key(data, self.lexicon.format());
+        if let Some(shard) = cache.get(&key) {
+            return self
+                .lexicon
+                .merge_shard(shard)
+                .map(|n| (n, report.cached()));
         }
-
-        let report = ReportBuilder::new("trie");
-        let word_id_table = index.build_word_id_table()?;
-        let trie = index.build_trie()?;
-
-        let trie_size = trie.len() / 4;
-        w.write_all(&(trie_size as u32).to_le_bytes())?;
-        size += 4;
-        w.write_all(&trie)?;
-        size += trie.len();
-        std::mem::drop(trie); //can be big, so drop explicitly
-        self.reporter.collect(size, report);
-        let cur_size = size;
-
-        let report = ReportBuilder::new("word_id table");
-        w.write_all(&(word_id_table.len() as u32).to_le_bytes())?;
-        size += 4;
-        w.write_all(&word_id_table)?;
-        size += word_id_table.len();
-        self.reporter.collect(size - cur_size, report);
-
-        Ok(size)
+        let shard = self.lexicon.parse_bytes(data, self.threads)?;
+        // the cache is best-effort, the build does not need it
+        let report = match cache.put(&key, &shard) {
+            Ok(_) => report,
+            Err(e) => report.warning(format!("failed to write the build cache: {}", e)),
+        };
+        self.lexicon.merge_shard(shard).map(|n| (n, report))
     }
 
     fn write_lexicon<W: Write>(&mut self, w: &mut W, offset: usize) -> SudachiResult<usize> {
-        let mut size = self.write_index(w)?;
-        let mut writer =
-            LexiconWriter::new(self.lexicon.entries(), offset + size, &mut self.reporter);
+        let entries = self.lexicon.entries();
+        let threads = self.threads;
+        let compress = self.header.has_string_pool();
+        let build_index = || {
+            let mut buffer = Vec::new();
+            let mut reporter = Reporter::new();
+            write_index(entries, &mut buffer, &mut reporter).map(|_| (buffer, reporter))
+        };
+        // the index does not depend on word infos, so it is built while they are serialized
+        let (index, infos) = if threads > 1 {
+            std::thread::scope(|s| {
+                let index = s.spawn(build_index);
+                let infos = WordInfos::serialize(entries, threads - 1, compress);
+                (index.join().expect("index build panicked"), infos)
+            })
+        } else {
+            (build_index(), WordInfos::serialize(entries, 1, compress))
+        };
+        let (index, index_report) = index?;
+        let infos = infos?;
+
+        w.write_all(&index)?;
+        self.reporter.append(index_report);
+        let mut size = index.len();
+        let mut writer = LexiconWriter::new(entries, infos, offset + size, &mut self.reporter);
         size += writer.write(w)?;
         Ok(size)
     }
@@ -312,6 +447,19 @@ impl<D: DictionaryAccess> DictBuilder<D> {
         unsafe { std::mem::transmute(resolver) }
     }
 
+    fn estimate_costs(&mut self) -> SudachiResult<()> {
+        let dict = match self.prebuilt.as_ref() {
+            Some(d) if self.user => d,
+            _ => return Ok(()),
+        };
+        let report = ReportBuilder::new("estimate costs").read();
+        let costs = self.lexicon.estimate_costs(dict)?;
+        if !costs.is_empty() {
+            self.reporter.collect(costs.len(), report.costs(costs));
+        }
+        Ok(())
+    }
+
     fn resolve_impl(&mut self) -> SudachiResult<usize> {
         if !self.lexicon.needs_split_resolution() {
             self.resolved = true;
@@ -0,0 +1,116 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Helpers to run parts of the dictionary build on several threads.
+//! Results never depend on the number of threads.
+
+use std::sync::Mutex;
+
+/// Number of threads which is used when it is not specified
+pub(crate) fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Applies `f` to all tasks using up to `threads` threads.
+/// Results are returned in the order of tasks.
+pub(crate) fn map_parallel<T, R, F>(tasks: Vec<T>, threads: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let num_tasks = tasks.len();
+    if threads <= 1 || num_tasks <= 1 {
+        return tasks.into_iter().map(f).collect();
+    }
+
+    let queue = Mutex::new(tasks.into_iter().enumerate());
+    let results = Mutex::new((0..num_tasks).map(|_| None).collect::<Vec<_>>());
+    std::thread::scope(|s| {
+        for _ in 0..threads.min(num_tasks) {
+            s.spawn(|| loop {
+                let task = queue.lock().unwrap().next();
+                match task {
+                    Some((i, t)) => {
+                        let r = f(t);
+                        results.lock().unwrap()[i] = Some(r);
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("all tasks are processed"))
+        .collect()
+}
+
+/// Splits csv data into at most `parts` chunks of at least `min_size` bytes on line boundaries.
+/// Returns chunks together with the number of lines before them.
+///
+/// Data which contains quotes is not split, because quoted fields can contain line breaks.
+pub(crate) fn split_lines(data: &[u8], parts: usize, min_size: usize) -> Vec<(&[u8], usize)> {
+    let size = std::cmp::max(data.len() / parts.max(1), min_size.max(1));
+    if data.len() <= size || data.contains(&b'"') {
+        return vec![(data, 0)];
+    }
+
+    let mut result = Vec::with_capacity(parts);
+    let mut start = 0;
+    let mut lines = 0;
+    while start < data.len() {
+        // the chunk ends at the first line break after `size` bytes
+        let search = std::cmp::min(start + size - 1, data.len());
+        let end = match data[search..].iter().position(|b| *b == b'\n') {
+            Some(pos) => search + pos + 1,
+            None => data.len(),
+        };
+        let chunk = &data[start..end];
+        result.push((chunk, lines));
+        lines += chunk.iter().filter(|b| **b == b'\n').count();
+        start = end;
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn map_keeps_order() {
+        let tasks: Vec<usize> = (0..100).collect();
+        let result = map_parallel(tasks, 4, |x| x * 2);
+        assert_eq!((0..100).map(|x| x * 2).collect::<Vec<_>>(), result);
+    }
+
+    #[test]
+    fn split_on_lines() {
+        let data = b"aaa\nbbb\nccc\nddd\n";
+        let chunks = split_lines(data, 2, 1);
+        assert_eq!(
+            vec![(&b"aaa\nbbb\n"[..], 0), (&b"ccc\nddd\n"[..], 2)],
+            chunks
+        );
+        let chunks = split_lines(b"aaa\nbbbbbb\ncc", 2, 1);
+        assert_eq!(vec![(&b"aaa\nbbbbbb\n"[..], 0), (&b"cc"[..], 2)], chunks);
+        assert_eq!(1, split_lines(data, 8, 100).len());
+        assert_eq!(1, split_lines(b"a\n\"b\nc\"\nd\n", 4, 1).len());
+    }
+}
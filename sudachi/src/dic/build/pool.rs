@@ -0,0 +1,101 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::cmp::Reverse;
+use std::io::Write;
+
+use indexmap::map::IndexMap;
+
+use crate::dic::build::error::{BuildFailure, DicWriteResult};
+use crate::dic::build::lexicon::RawLexiconEntry;
+use crate::dic::build::primitives::Utf16Writer;
+use crate::util::fxhash::FxBuildHasher;
+
+/// Deduplicated strings of word infos.
+///
+/// Each string is stored once as a length-prefixed UTF-16 string and word infos refer
+/// to it by its offset in the pool. Frequent strings are placed first, so their offsets
+/// take fewer bytes as varints. The empty string is always at the offset 0.
+pub(crate) struct StringPool {
+    offsets: IndexMap<String, u32, FxBuildHasher>,
+    data: Vec<u8>,
+}
+
+impl StringPool {
+    /// Collects headwords, normalized forms and readings of the entries
+    pub(crate) fn build(entries: &[RawLexiconEntry]) -> DicWriteResult<StringPool> {
+        let mut counts: IndexMap<&str, usize, FxBuildHasher> = IndexMap::default();
+        counts.insert("", 0);
+        for e in entries {
+            for s in Self::strings_of(e) {
+                *counts.entry(s).or_default() += 1;
+            }
+        }
+
+        let mut order: Vec<(&str, usize)> = counts.into_iter().collect();
+        // the empty string stays first, sort is stable, so ties keep the order of appearance
+        order[1..].sort_by_key(|(_, count)| Reverse(*count));
+
+        let mut u16w = Utf16Writer::new();
+        let mut pool = StringPool {
+            offsets: IndexMap::with_capacity_and_hasher(order.len(), Default::default()),
+            data: Vec::new(),
+        };
+        for (s, _) in order {
+            let offset = pool.data.len();
+            if offset > u32::MAX as usize {
+                return Err(BuildFailure::InvalidSize {
+                    actual: offset,
+                    expected: u32::MAX as usize,
+                });
+            }
+            u16w.write(&mut pool.data, s)?;
+            pool.offsets.insert(s.to_owned(), offset as u32);
+        }
+        Ok(pool)
+    }
+
+    /// Strings of the entry in the order they are stored in the word info,
+    /// the normalized form and the reading are empty when they are equal to the headword
+    pub(crate) fn strings_of(e: &RawLexiconEntry) -> [&str; 3] {
+        fn if_not_equal<'a>(s: &'a str, headword: &str) -> &'a str {
+            if s == headword {
+                ""
+            } else {
+                s
+            }
+        }
+        let headword = e.headword();
+        [
+            headword,
+            if_not_equal(e.norm_form(), headword),
+            if_not_equal(e.reading(), headword),
+        ]
+    }
+
+    pub(crate) fn offset(&self, data: &str) -> u32 {
+        *self
+            .offsets
+            .get(data)
+            .expect("all strings must be in the pool")
+    }
+
+    pub(crate) fn write_to<W: Write>(&self, w: &mut W) -> DicWriteResult<usize> {
+        w.write_all(&(self.data.len() as u32).to_le_bytes())?;
+        w.write_all(&self.data)?;
+        Ok(4 + self.data.len())
+    }
+}
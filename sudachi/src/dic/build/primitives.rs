@@ -14,7 +14,7 @@
  *  limitations under the License.
  */
 
-use crate::dic::build::error::BuildFailure::InvalidSize;
+use crate::dic::build::error::BuildFailure::{self, InvalidSize};
 use crate::dic::build::error::DicWriteResult;
 use crate::dic::build::lexicon::SplitUnit;
 use crate::dic::word_id::WordId;
@@ -98,29 +98,30 @@ impl Utf16Writer {
 }
 
 pub(crate) trait ToU32 {
-    fn to_u32(&self) -> u32;
+    fn to_u32(&self) -> DicWriteResult<u32>;
 }
 
 impl ToU32 for u32 {
-    fn to_u32(&self) -> u32 {
-        *self
+    fn to_u32(&self) -> DicWriteResult<u32> {
+        Ok(*self)
     }
 }
 
 impl ToU32 for i32 {
-    fn to_u32(&self) -> u32 {
-        *self as u32
+    fn to_u32(&self) -> DicWriteResult<u32> {
+        Ok(*self as u32)
     }
 }
 
 impl ToU32 for WordId {
-    fn to_u32(&self) -> u32 {
-        self.as_raw()
+    fn to_u32(&self) -> DicWriteResult<u32> {
+        self.to_storage()
+            .map_err(|_| BuildFailure::InvalidWordId(self.to_string()))
     }
 }
 
 impl ToU32 for SplitUnit {
-    fn to_u32(&self) -> u32 {
+    fn to_u32(&self) -> DicWriteResult<u32> {
         match self {
             SplitUnit::Ref(w) => w.to_u32(),
             SplitUnit::Inline { .. } => panic!("splits must be resolved before writing"),
@@ -140,7 +141,7 @@ pub(crate) fn write_u32_array<W: Write, T: ToU32>(w: &mut W, data: &[T]) -> DicW
     let mut written = 1;
 
     for o in data {
-        let i = o.to_u32();
+        let i = o.to_u32()?;
         w.write_all(&i.to_le_bytes())?;
         written += 4;
     }
@@ -148,12 +149,55 @@ pub(crate) fn write_u32_array<W: Write, T: ToU32>(w: &mut W, data: &[T]) -> DicW
     Ok(written)
 }
 
+/// Writes the number as unsigned LEB128, the inverse of
+/// [`varint_parser`](crate::dic::read::varint_parser)
+pub(crate) fn write_varint<W: Write>(w: &mut W, mut value: u32) -> DicWriteResult<usize> {
+    let mut buf = [0u8; 5];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    w.write_all(&buf[..len])?;
+    Ok(len)
+}
+
+/// Same as [`write_u32_array`], but the length and all elements are varints
+pub(crate) fn write_varint_array<W: Write, T: ToU32>(
+    w: &mut W,
+    data: &[T],
+) -> DicWriteResult<usize> {
+    let len = data.len();
+    if len > 127 {
+        return Err(InvalidSize {
+            expected: 127,
+            actual: len,
+        });
+    }
+    let mut written = write_varint(w, len as u32)?;
+    for o in data {
+        written += write_varint(w, o.to_u32()?)?;
+    }
+    Ok(written)
+}
+
 #[cfg(test)]
 mod test {
     use crate::dic::build::error::DicWriteResult;
-    use crate::dic::build::primitives::{write_u32_array, Utf16Writer};
+    use crate::dic::build::primitives::{
+        write_u32_array, write_varint, write_varint_array, Utf16Writer,
+    };
     use crate::dic::read::u16str::utf16_string_parser;
-    use crate::dic::read::u32_array_parser;
+    use crate::dic::read::{
+        u32_array_parser, varint_array_parser, varint_parser, varint_u16_parser,
+    };
     use claim::assert_matches;
 
     #[test]
@@ -219,4 +263,44 @@ mod test {
         let status = write_u32_array(&mut data, &array);
         assert_matches!(status, Err(_));
     }
+
+    #[test]
+    fn write_varints() {
+        let mut data: Vec<u8> = Vec::new();
+        for (value, size) in [(0, 1), (127, 1), (128, 2), (300, 2), (u32::MAX, 5)] {
+            data.clear();
+            assert_eq!(size, write_varint(&mut data, value).unwrap());
+            assert_eq!(size, data.len());
+            let (rem, parsed) = varint_parser(&data).expect("ok");
+            assert_eq!(rem, b"");
+            assert_eq!(parsed, value);
+        }
+        assert!(varint_parser(&[0x80, 0x80]).is_err());
+        // bits above u32 range
+        assert!(varint_parser(&[0xff, 0xff, 0xff, 0xff, 0x1f]).is_err());
+        assert!(varint_parser(&[0x80, 0x80, 0x80, 0x80, 0x70]).is_err());
+        assert!(varint_parser(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]).is_err());
+    }
+
+    #[test]
+    fn varints_of_u16() {
+        let mut data: Vec<u8> = Vec::new();
+        write_varint(&mut data, u16::MAX as u32).unwrap();
+        assert_eq!(Ok((&b""[..], u16::MAX)), varint_u16_parser(&data));
+        data.clear();
+        write_varint(&mut data, u16::MAX as u32 + 1).unwrap();
+        assert!(varint_u16_parser(&data).is_err());
+    }
+
+    #[test]
+    fn write_varint_arrays() {
+        let mut data: Vec<u8> = Vec::new();
+        let array = [0, 5, u32::MAX, 1000];
+        let written = write_varint_array(&mut data, &array).expect("ok");
+        assert_eq!(written, 1 + 1 + 1 + 5 + 2);
+        let (rem, parsed) = varint_array_parser(&data).expect("ok");
+        assert_eq!(rem, b"");
+        assert_eq!(parsed, array);
+        assert!(write_varint_array(&mut data, &[0u32; 130]).is_err());
+    }
 }
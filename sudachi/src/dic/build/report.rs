@@ -14,13 +14,60 @@
  *  limitations under the License.
  */
 
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
+/// Output format of the reports of dictionary tools, e.g. diff and lint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Human-readable text
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err("report format must be one of \"text\" or \"json\""),
+        }
+    }
+}
+
+impl Display for ReportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let repr = match self {
+            ReportFormat::Text => "text",
+            ReportFormat::Json => "json",
+        };
+        f.write_str(repr)
+    }
+}
+
 pub struct DictPartReport {
     pub(self) part: String,
     pub(self) time: Duration,
     pub(self) size: usize,
     pub(self) write: bool,
+    pub(self) costs: Vec<EstimatedCost>,
+    pub(self) warnings: Vec<String>,
+}
+
+/// Cost which was estimated for a user dictionary entry with the placeholder cost
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EstimatedCost {
+    /// Index of the entry in the lexicon
+    pub index: usize,
+    /// Surface of the entry
+    pub surface: String,
+    /// Chosen cost
+    pub cost: i16,
+    /// Number of morphemes in the analysis of the surface with the system dictionary
+    pub num_morphemes: usize,
 }
 
 impl DictPartReport {
@@ -39,6 +86,16 @@ impl DictPartReport {
     pub fn is_write(&self) -> bool {
         self.write
     }
+
+    /// Costs which were estimated during this part of the build
+    pub fn estimated_costs(&self) -> &[EstimatedCost] {
+        &self.costs
+    }
+
+    /// Problems which did not fail the build, e.g. the build cache could not be written
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
 }
 
 pub(crate) struct Reporter {
@@ -73,6 +130,11 @@ impl Reporter {
         }
     }
 
+    /// Appends reports which were collected by another reporter, e.g. on another thread
+    pub fn append(&mut self, other: Reporter) {
+        self.reports.extend(other.reports);
+    }
+
     pub fn reports(&self) -> &[DictPartReport] {
         &self.reports
     }
@@ -92,6 +154,8 @@ impl ReportBuilder {
                 size: 0,
                 time: Duration::default(),
                 write: true,
+                costs: Vec::new(),
+                warnings: Vec::new(),
             },
         }
     }
@@ -100,4 +164,20 @@ impl ReportBuilder {
         self.report.write = false;
         self
     }
+
+    /// Marks the part as one which was taken from the build cache
+    pub fn cached(mut self) -> Self {
+        self.report.part.push_str(" (cached)");
+        self
+    }
+
+    pub fn costs(mut self, costs: Vec<EstimatedCost>) -> Self {
+        self.report.costs = costs;
+        self
+    }
+
+    pub fn warning<S: Into<String>>(mut self, warning: S) -> Self {
+        self.report.warnings.push(warning.into());
+        self
+    }
 }
@@ -76,6 +76,14 @@ impl SplitUnitResolver for BinDictResolver {
             None
         })
     }
+
+    fn count_inline(&self, surface: &str, pos: u16, reading: Option<&str>) -> usize {
+        self.index.get(surface).map_or(0, |v| {
+            v.iter()
+                .filter(|(p, rd, _)| *p == pos && reading.eq(&rd.as_deref()))
+                .count()
+        })
+    }
 }
 
 pub struct RawDictResolver<'a> {
@@ -119,6 +127,14 @@ impl SplitUnitResolver for RawDictResolver<'_> {
             None
         })
     }
+
+    fn count_inline(&self, surface: &str, pos: u16, reading: Option<&str>) -> usize {
+        self.data.get(surface).map_or(0, |data| {
+            data.iter()
+                .filter(|(p, rd, _)| *p == pos && *rd == reading)
+                .count()
+        })
+    }
 }
 
 pub(crate) struct ChainedResolver<A, B> {
@@ -138,4 +154,8 @@ impl<A: SplitUnitResolver, B: SplitUnitResolver> SplitUnitResolver for ChainedRe
             .resolve_inline(surface, pos, reading)
             .or_else(|| self.b.resolve_inline(surface, pos, reading))
     }
+
+    fn count_inline(&self, surface: &str, pos: u16, reading: Option<&str>) -> usize {
+        self.a.count_inline(surface, pos, reading) + self.b.count_inline(surface, pos, reading)
+    }
 }
@@ -17,16 +17,25 @@
 mod with_analysis;
 
 use crate::analysis::stateless_tokenizer::DictionaryAccess;
+use crate::dic::build::decompile::Decompiler;
+use crate::dic::build::diff::{CostChange, DictDiff};
 use crate::dic::build::error::{BuildFailure, DicBuildError};
+use crate::dic::build::format::{LexiconFormat, MecabColumns};
+use crate::dic::build::lint::{LintKind, LintLevel, Linter};
+use crate::dic::build::report::ReportFormat;
 use crate::dic::build::DictBuilder;
+use crate::dic::connect::MatrixFormat;
 use crate::dic::grammar::Grammar;
-use crate::dic::header::{HeaderVersion, SystemDictVersion};
+use crate::dic::header::{Header, HeaderVersion, SystemDictVersion, UserDictVersion};
+use crate::dic::lexicon::word_infos::WordInfoData;
 use crate::dic::lexicon::{Lexicon, LexiconEntry};
 use crate::dic::subset::InfoSubset;
 use crate::dic::word_id::WordId;
 use crate::dic::DictionaryLoader;
 use crate::error::SudachiError;
+use serde_json::json;
 use std::io::sink;
+use std::time::{Duration, UNIX_EPOCH};
 
 static MATRIX_10_10: &[u8] = include_bytes!("matrix_10x10.def");
 
@@ -387,3 +396,493 @@ fn resolve_user_entry_without_system_in_trie() {
     assert_eq!(winfo.a_unit_split()[0], WordId::new(1, 0));
     assert_eq!(winfo.a_unit_split()[1], WordId::new(0, 1));
 }
+
+fn rebuild_system(original: &[u8], csv: &[u8], matrix: &[u8]) -> Vec<u8> {
+    let header = DictionaryLoader::read_system_dictionary(original)
+        .unwrap()
+        .header;
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_compile_time(UNIX_EPOCH + Duration::from_secs(header.create_time));
+    bldr.set_description(header.description);
+    bldr.read_conn(matrix).unwrap();
+    bldr.read_lexicon(csv).unwrap();
+    bldr.resolve().unwrap();
+    let mut rebuilt = Vec::new();
+    bldr.compile(&mut rebuilt).unwrap();
+    rebuilt
+}
+
+#[test]
+fn decompile_system_roundtrip() {
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_description("decompile test");
+    bldr.read_conn(MATRIX_10_10).unwrap();
+    bldr.read_lexicon(include_bytes!("lex.csv")).unwrap();
+    bldr.read_lexicon(
+        "い\\u002cく,-1,-1,0,い\\u002cく,動詞,一般,*,*,五段-カ行,終止形-一般,イ\\u005cク,行く,4,A,*,*,*,*
+\"東京,\"\"都\",1,1,100,東京都,名詞,固有名詞,地名,一般,*,*,トウキョウト,東京都,*,C,5/9,5/9,5/9,000001/2"
+            .as_bytes(),
+    )
+    .unwrap();
+    bldr.resolve().unwrap();
+    let mut original = Vec::new();
+    bldr.compile(&mut original).unwrap();
+
+    let dict = DictionaryLoader::read_system_dictionary(&original).unwrap();
+    let decompiler = Decompiler::new(&dict, None).unwrap();
+    let mut csv = Vec::new();
+    assert_eq!(48, decompiler.write_lexicon(&mut csv).unwrap());
+    let mut matrix = Vec::new();
+    assert!(decompiler.write_matrix(&mut matrix).unwrap());
+
+    let csv_str = String::from_utf8(csv.clone()).unwrap();
+    assert!(csv_str.contains("\n東京\\u002c\\u0022都,1,1,100,東京都,"));
+
+    assert_eq!(original, rebuild_system(&original, &csv, &matrix));
+}
+
+#[test]
+fn decompile_user_roundtrip() {
+    let mut bldr = DictBuilder::new_system();
+    bldr.read_conn(MATRIX_10_10).unwrap();
+    bldr.read_lexicon(include_bytes!("lex.csv")).unwrap();
+    bldr.resolve().unwrap();
+    let mut system_bin = Vec::new();
+    bldr.compile(&mut system_bin).unwrap();
+    let system = DictionaryLoader::read_system_dictionary(&system_bin)
+        .unwrap()
+        .to_loaded()
+        .unwrap();
+
+    let mut bldr = DictBuilder::new_user(&system);
+    bldr.read_lexicon(include_bytes!("user1.csv")).unwrap();
+    bldr.resolve().unwrap();
+    let mut original = Vec::new();
+    bldr.compile(&mut original).unwrap();
+
+    let udic = DictionaryLoader::read_user_dictionary(&original).unwrap();
+    assert!(Decompiler::new(&udic, None).is_err());
+    let decompiler = Decompiler::new(&udic, Some(&system.grammar)).unwrap();
+    let mut csv = Vec::new();
+    assert_eq!(4, decompiler.write_lexicon(&mut csv).unwrap());
+    assert!(!decompiler.write_matrix(&mut sink()).unwrap());
+    let csv_str = String::from_utf8(csv.clone()).unwrap();
+    // cost is not recomputed, split references are written as word ids
+    assert!(csv_str.starts_with("ぴらる,8,8,-32768,ぴらる,"));
+    assert!(csv_str.contains(",B,5/U1,*,5/U1,000001/000003\n"));
+
+    let mut bldr = DictBuilder::new_user(&system);
+    bldr.set_compile_time(UNIX_EPOCH + Duration::from_secs(udic.header.create_time));
+    bldr.read_lexicon(csv.as_slice()).unwrap();
+    bldr.resolve().unwrap();
+    let mut rebuilt = Vec::new();
+    bldr.compile(&mut rebuilt).unwrap();
+    assert_eq!(original, rebuilt);
+}
+
+fn build_system(csv: &[u8], matrix: &[u8]) -> Vec<u8> {
+    let mut bldr = DictBuilder::new_system();
+    bldr.read_conn(matrix).unwrap();
+    bldr.read_lexicon(csv).unwrap();
+    bldr.resolve().unwrap();
+    let mut result = Vec::new();
+    bldr.compile(&mut result).unwrap();
+    result
+}
+
+#[test]
+fn diff_system_matches_entries_by_key() {
+    let old_csv = include_str!("lex.csv");
+    // new word shifts all word ids, references are updated accordingly
+    let new_csv = format!(
+        "京都府,6,6,2000,京都府,名詞,固有名詞,地名,一般,*,*,キョウトフ,京都府,*,A,*,*,*,*\n{}",
+        old_csv
+            .replace("5320,東京都", "5000,東京都")
+            .replace("5/9", "6/10")
+            .replace("イッ,行く,7,", "イッ,行く,8,")
+            .replace("イッ,行く,34,", "イッ,行く,35,")
+    );
+    let new_matrix = String::from_utf8_lossy(MATRIX_10_10).replace("0 1 863", "0 1 777");
+    let old = build_system(old_csv.as_bytes(), MATRIX_10_10);
+    let new = build_system(new_csv.as_bytes(), new_matrix.as_bytes());
+    let old = DictionaryLoader::read_system_dictionary(&old).unwrap();
+    let new = DictionaryLoader::read_system_dictionary(&new).unwrap();
+
+    assert!(DictDiff::compare(&old, &old, None).unwrap().is_empty());
+
+    let diff = DictDiff::compare(&old, &new, None).unwrap();
+    assert!(diff.added_pos.is_empty());
+    assert!(diff.removed.is_empty());
+    assert_eq!(1, diff.added.len());
+    assert_eq!("京都府", diff.added[0].headword);
+    assert_eq!(1, diff.changed.len());
+    assert_eq!(
+        vec![("cost", json!(5320), json!(5000))],
+        diff.changed[0].changed_fields()
+    );
+    let matrix = diff.matrix.as_ref().unwrap();
+    assert_eq!(
+        vec![CostChange {
+            left: 0,
+            right: 1,
+            old: 863,
+            new: 777
+        }],
+        matrix.changed
+    );
+
+    let mut text = Vec::new();
+    diff.write(ReportFormat::Text, &mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert!(text.contains("\n~ 0 1: 863 -> 777\n"));
+    assert!(text.contains("\n    cost: 5320 -> 5000\n"));
+
+    let json = diff.to_json();
+    assert_eq!(1, json["entries"]["added"].as_array().unwrap().len());
+    assert_eq!(
+        json!({"old": 5320, "new": 5000}),
+        json["entries"]["changed"][0]["changes"]["cost"]
+    );
+}
+
+#[test]
+fn diff_user() {
+    let system_bin = build_system(include_bytes!("lex.csv"), MATRIX_10_10);
+    let system = DictionaryLoader::read_system_dictionary(&system_bin).unwrap();
+    let loaded = DictionaryLoader::read_system_dictionary(&system_bin)
+        .unwrap()
+        .to_loaded()
+        .unwrap();
+    let build_user = |csv: &[u8]| {
+        let mut bldr = DictBuilder::new_user(&loaded);
+        bldr.read_lexicon(csv).unwrap();
+        bldr.resolve().unwrap();
+        let mut result = Vec::new();
+        bldr.compile(&mut result).unwrap();
+        result
+    };
+    let old_csv = include_str!("user1.csv");
+    let new_csv: String = old_csv
+        .lines()
+        .filter(|l| !l.starts_with("すだち"))
+        .map(|l| format!("{}\n", l))
+        .collect();
+    let old = build_user(old_csv.as_bytes());
+    let new = build_user(new_csv.as_bytes());
+    let old = DictionaryLoader::read_user_dictionary(&old).unwrap();
+    let new = DictionaryLoader::read_user_dictionary(&new).unwrap();
+
+    assert!(DictDiff::compare(&old, &new, None).is_err());
+    let diff = DictDiff::compare(&old, &new, Some(&system)).unwrap();
+    assert!(diff.matrix.is_none());
+    assert!(diff.added.is_empty());
+    assert!(diff.changed.is_empty());
+    assert_eq!(1, diff.removed.len());
+    assert_eq!("すだち", diff.removed[0].headword);
+    assert_eq!(1, diff.removed_pos.len());
+    assert_eq!("被子植物門", diff.removed_pos[0][0]);
+}
+
+#[test]
+fn build_system_from_mecab_lexicon() {
+    let ipadic = "\
+東京,6,6,3003,名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー
+行っ,7,7,4000,動詞,自立,*,*,五段・カ行促音便,連用タ接続,行く,イッ,イッ
+\"a,b\",8,8,5000,記号,一般,*,*,*,*,*
+";
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_lexicon_format("mecab".parse().unwrap());
+    bldr.read_conn(MATRIX_10_10).unwrap();
+    assert_eq!(3, bldr.read_lexicon(ipadic.as_bytes()).unwrap());
+    bldr.resolve().unwrap();
+    let mut built = Vec::new();
+    bldr.compile(&mut built).unwrap();
+    let dic = DictionaryLoader::read_system_dictionary(&built)
+        .unwrap()
+        .to_loaded()
+        .unwrap();
+    assert_eq!(
+        dic.grammar.pos_list[1],
+        &["動詞", "自立", "*", "*", "五段・カ行促音便", "連用タ接続"]
+    );
+
+    let entry = dic.lexicon().lookup("行っ".as_bytes(), 0).next().unwrap();
+    assert_eq!((7, 7, 4000), dic.lexicon().get_word_param(entry.word_id));
+    let info = dic.lexicon().get_word_info(entry.word_id).unwrap();
+    assert_eq!(info.surface(), "行っ");
+    assert_eq!(info.reading_form(), "イッ");
+    assert_eq!(info.normalized_form(), "行く");
+    assert_eq!(info.dictionary_form_word_id(), -1);
+
+    // csv quoting is supported, missing optional columns fall back to the surface
+    let entry = dic.lexicon().lookup("a,b".as_bytes(), 0).next().unwrap();
+    let info = dic.lexicon().get_word_info(entry.word_id).unwrap();
+    assert_eq!(info.reading_form(), "a,b");
+    assert_eq!(info.normalized_form(), "a,b");
+}
+
+#[test]
+fn build_with_mecab_column_mapping() {
+    let custom = "東京,3003,6,6,名詞,トウキョウ\n";
+    let columns = MecabColumns::ipadic()
+        .with_overrides("left_id=2,right_id=3,cost=1,pos=4,reading=5,lemma=*")
+        .unwrap();
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_lexicon_format(LexiconFormat::Mecab(columns));
+    bldr.read_conn(MATRIX_10_10).unwrap();
+    bldr.read_lexicon(custom.as_bytes()).unwrap();
+    let mut built = Vec::new();
+    bldr.compile(&mut built).unwrap();
+    let dic = DictionaryLoader::read_system_dictionary(&built)
+        .unwrap()
+        .to_loaded()
+        .unwrap();
+    assert_eq!(dic.grammar.pos_list[0], &["名詞", "*", "*", "*", "*", "*"]);
+    assert_eq!(
+        (6, 6, 3003),
+        dic.lexicon().get_word_param(WordId::new(0, 0))
+    );
+    let info = dic.lexicon().get_word_info(WordId::new(0, 0)).unwrap();
+    assert_eq!(info.reading_form(), "トウキョウ");
+
+    // required columns must be present
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_lexicon_format("ipadic".parse().unwrap());
+    assert!(bldr.read_lexicon("東京,6,6\n".as_bytes()).is_err());
+}
+
+#[test]
+fn lint_system_lexicon() {
+    let mut linter = Linter::new_system();
+    linter.read_conn(MATRIX_10_10).unwrap();
+    assert_eq!(
+        1,
+        linter
+            .read_lexicon(include_bytes!("data_1word.csv"))
+            .unwrap()
+    );
+    let report = linter.lint().unwrap();
+    assert_eq!(1, report.entries);
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+
+    let csv = "\
+京都,6,6,5293,京都,名詞,固有名詞,地名,一般,*,*,キョウト,京都,*,A,*,*,*,*
+京都,6,6,5000,京都,名詞,固有名詞,地名,一般,*,*,キョウト,京都,*,A,*,*,*,*
+東京,6,x,5293,東京,名詞,固有名詞,地名,一般,*,*,トウキョウ,東京,*,A,*,*,*,*
+大阪,11,6,-1,大阪,名詞,固有名詞,地名,一般,*,*,おおさか,大坂,*,A,*,*,*,*
+京都府,6,6,25000,京都府,名詞,固有名詞,地名,一般,*,*,キョウトフ,京都府,*,B,\"京都,名詞,固有名詞,地名,一般,*,*,キョウト/大阪,名詞,固有名詞,地名,一般,*,*,オオサカ\",*,7,*
+";
+    let mut linter = Linter::new_system();
+    linter.read_conn(MATRIX_10_10).unwrap();
+    assert_eq!(4, linter.read_lexicon(csv.as_bytes()).unwrap());
+    let report = linter.lint().unwrap();
+    assert_eq!(4, report.entries);
+    let issues: Vec<_> = report
+        .issues
+        .iter()
+        .map(|i| (i.line, i.level, i.kind))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            (2, LintLevel::Warning, LintKind::Duplicate),
+            (3, LintLevel::Error, LintKind::Parse),
+            (4, LintLevel::Error, LintKind::ConnId),
+            (4, LintLevel::Warning, LintKind::Cost),
+            (4, LintLevel::Warning, LintKind::Reading),
+            (4, LintLevel::Warning, LintKind::NormalizedForm),
+            (5, LintLevel::Warning, LintKind::Cost),
+            (5, LintLevel::Error, LintKind::Reference),
+            (5, LintLevel::Warning, LintKind::Split),
+            (5, LintLevel::Error, LintKind::Split),
+        ]
+    );
+    assert_eq!(4, report.num_errors());
+    assert_eq!(6, report.num_warnings());
+    assert!(report.issues[0].message.contains(":1"));
+
+    let mut out = Vec::new();
+    report.write(&mut out, ReportFormat::Json).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(4, value["errors"]);
+    assert_eq!("parse", value["issues"][1]["kind"]);
+    assert_eq!(3, value["issues"][1]["line"]);
+}
+
+#[test]
+fn lint_user_lexicon() {
+    let system = build_system(include_bytes!("lex.csv"), MATRIX_10_10);
+    let system = DictionaryLoader::read_system_dictionary(&system)
+        .unwrap()
+        .to_loaded()
+        .unwrap();
+    let mut linter = Linter::new_user(&system);
+    linter.read_lexicon(include_bytes!("user1.csv")).unwrap();
+    let report = linter.lint().unwrap();
+    let issues: Vec<_> = report.issues.iter().map(|i| (i.line, i.kind)).collect();
+    // すだち has a new POS
+    assert_eq!(issues, [(4, LintKind::Pos)]);
+
+    let mut linter = Linter::new_user(&system);
+    let csv = "東京,10,6,2816,東京,名詞,固有名詞,地名,一般,*,*,トウキョウ,東京,U3,A,*,*,*,*\n";
+    linter.read_lexicon(csv.as_bytes()).unwrap();
+    let report = linter.lint().unwrap();
+    let issues: Vec<_> = report.issues.iter().map(|i| (i.line, i.kind)).collect();
+    assert_eq!(issues, [(1, LintKind::ConnId), (1, LintKind::Reference)]);
+}
+
+#[test]
+fn build_with_cache_is_same_as_without() {
+    let cache = tempfile::tempdir().unwrap();
+    let expected = build_system(include_bytes!("lex.csv"), MATRIX_10_10);
+    for cached in [false, true] {
+        let mut bldr = DictBuilder::new_system();
+        bldr.set_cache_dir(cache.path());
+        bldr.read_conn(MATRIX_10_10).unwrap();
+        assert_eq!(46, bldr.read_lexicon(include_bytes!("lex.csv")).unwrap());
+        let is_cached = bldr.report()[1].part().ends_with(" (cached)");
+        assert_eq!(cached, is_cached);
+        bldr.resolve().unwrap();
+        let mut result = Vec::new();
+        bldr.compile(&mut result).unwrap();
+        // compile time is different, so the header is skipped
+        assert_eq!(
+            &expected[Header::STORAGE_SIZE..],
+            &result[Header::STORAGE_SIZE..]
+        );
+    }
+}
+
+#[test]
+fn build_ignores_broken_cache() {
+    let cache = tempfile::tempdir().unwrap();
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_cache_dir(cache.path());
+    bldr.read_lexicon(include_bytes!("lex.csv")).unwrap();
+    for file in std::fs::read_dir(cache.path()).unwrap() {
+        std::fs::write(file.unwrap().path(), b"broken").unwrap();
+    }
+
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_cache_dir(cache.path());
+    assert_eq!(46, bldr.read_lexicon(include_bytes!("lex.csv")).unwrap());
+    assert!(!bldr.report()[0].part().ends_with(" (cached)"));
+}
+
+#[test]
+fn build_ignores_unwritable_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    // cache directory can't be created
+    let cache = dir.path().join("file");
+    std::fs::write(&cache, b"").unwrap();
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_cache_dir(&cache);
+    bldr.read_conn(MATRIX_10_10).unwrap();
+    assert_eq!(46, bldr.read_lexicon(include_bytes!("lex.csv")).unwrap());
+    let warnings = bldr.report()[1].warnings();
+    assert_eq!(1, warnings.len());
+    assert!(warnings[0].starts_with("failed to write the build cache"));
+    bldr.resolve().unwrap();
+    let mut result = Vec::new();
+    bldr.compile(&mut result).unwrap();
+}
+
+#[test]
+fn build_system_with_dedup_matrix() {
+    // sparse matrix, most of the rows are the same
+    let matrix = b"10 10 100\n0 0 0\n3 4 -20\n5 5 200\n";
+    let dense = build_system(include_bytes!("lex.csv"), matrix);
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_matrix_format(MatrixFormat::Dedup);
+    bldr.read_conn(matrix).unwrap();
+    bldr.read_lexicon(include_bytes!("lex.csv")).unwrap();
+    bldr.resolve().unwrap();
+    let mut dedup = Vec::new();
+    bldr.compile(&mut dedup).unwrap();
+
+    let dense_len = dense.len();
+    let dense = DictionaryLoader::read_system_dictionary(&dense).unwrap();
+    let dedup_dic = DictionaryLoader::read_system_dictionary(&dedup).unwrap();
+    let (g1, g2) = (dense.grammar.unwrap(), dedup_dic.grammar.unwrap());
+    assert!(g2.conn_matrix().is_compressed());
+    for left in 0..10 {
+        for right in 0..10 {
+            assert_eq!(g1.connect_cost(left, right), g2.connect_cost(left, right));
+        }
+    }
+    assert_eq!(dense.lexicon.size(), dedup_dic.lexicon.size());
+    assert!(dedup.len() < dense_len);
+    assert_eq!(
+        dedup_dic.header.version,
+        HeaderVersion::SystemDict(SystemDictVersion::Version4)
+    );
+    assert!(dedup_dic.header.has_string_pool());
+    crate::dic::verify::verify_system(&dedup).unwrap();
+}
+
+#[test]
+fn build_system_with_compressed_word_infos() {
+    let plain = build_system(include_bytes!("lex.csv"), MATRIX_10_10);
+    let mut bldr = DictBuilder::new_system();
+    bldr.set_compressed_word_infos(true);
+    bldr.read_conn(MATRIX_10_10).unwrap();
+    bldr.read_lexicon(include_bytes!("lex.csv")).unwrap();
+    bldr.resolve().unwrap();
+    let mut compressed = Vec::new();
+    bldr.compile(&mut compressed).unwrap();
+    assert!(compressed.len() < plain.len());
+    crate::dic::verify::verify_system(&compressed).unwrap();
+
+    let d1 = DictionaryLoader::read_system_dictionary(&plain).unwrap();
+    let d2 = DictionaryLoader::read_system_dictionary(&compressed).unwrap();
+    assert_eq!(
+        d2.header.version,
+        HeaderVersion::SystemDict(SystemDictVersion::Version3)
+    );
+    assert_eq!(d1.lexicon.size(), d2.lexicon.size());
+    for i in 0..d1.lexicon.size() {
+        let w1: WordInfoData = d1
+            .lexicon
+            .get_word_info(i, InfoSubset::all())
+            .unwrap()
+            .into();
+        let w2: WordInfoData = d2
+            .lexicon
+            .get_word_info(i, InfoSubset::all())
+            .unwrap()
+            .into();
+        assert_eq!(format!("{:?}", w1), format!("{:?}", w2));
+    }
+}
+
+#[test]
+fn build_user_with_compressed_word_infos() {
+    let system = build_system(include_bytes!("data_3words.csv"), MATRIX_10_10);
+    let system = DictionaryLoader::read_system_dictionary(&system)
+        .unwrap()
+        .to_loaded()
+        .unwrap();
+    let mut bldr = DictBuilder::new_user(&system);
+    bldr.set_compressed_word_infos(true);
+    bldr.read_lexicon(include_bytes!("data_2words_3w_refs.csv"))
+        .unwrap();
+    bldr.resolve().unwrap();
+    let mut user = Vec::new();
+    bldr.compile(&mut user).unwrap();
+
+    let udic = DictionaryLoader::read_user_dictionary(&user).unwrap();
+    assert_eq!(
+        udic.header.version,
+        HeaderVersion::UserDict(UserDictVersion::Version4)
+    );
+    let dic = system.merge_dictionary(udic).unwrap();
+    let winfo = dic.lexicon_set.get_word_info(WordId::new(1, 1)).unwrap();
+    assert_eq!(winfo.surface(), "関東");
+    assert_eq!(winfo.a_unit_split(), [WordId::new(1, 0), WordId::new(0, 1)]);
+    let winfo = dic.lexicon_set.get_word_info(WordId::new(1, 0)).unwrap();
+    assert_eq!(
+        winfo.word_structure(),
+        [WordId::new(1, 1), WordId::new(0, 2)]
+    );
+    assert_eq!(winfo.synonym_group_ids(), [0, 1]);
+}
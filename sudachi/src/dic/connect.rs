@@ -1,5 +1,5 @@
 /*
- *  Copyright (c) 2021 Works Applications Co., Ltd.
+ *  Copyright (c) 2021-2024 Works Applications Co., Ltd.
  *
  *  Licensed under the Apache License, Version 2.0 (the "License");
  *  you may not use this file except in compliance with the License.
@@ -14,11 +14,68 @@
  *  limitations under the License.
  */
 
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use crate::error::{SudachiError, SudachiResult};
 use crate::util::cow_array::CowArray;
 
+/// Storage format of the connection matrix in the binary dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatrixFormat {
+    /// Full `num_left * num_right` matrix
+    #[default]
+    Dense,
+    /// Identical rows (costs for the same right id) are stored only once.
+    ///
+    /// Saves memory when the matrix has many identical rows, but each cost lookup
+    /// needs an additional read, unlike the dense format.
+    /// Dictionaries with this format have a new header version,
+    /// so versions which do not support it reject them.
+    Dedup,
+}
+
+impl MatrixFormat {
+    /// Value of the left size which marks non-dense matrices.
+    ///
+    /// Older versions would misread it as a size, so non-dense matrices are written only
+    /// in dictionaries with a header version which they reject,
+    /// see [`Header::has_matrix_formats`](crate::dic::header::Header::has_matrix_formats).
+    pub(crate) const MARKER: i16 = -1;
+    pub(crate) const DEDUP_TAG: u16 = 1;
+}
+
+impl FromStr for MatrixFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dense" => Ok(MatrixFormat::Dense),
+            "dedup" => Ok(MatrixFormat::Dedup),
+            _ => Err("matrix format must be one of \"dense\" or \"dedup\""),
+        }
+    }
+}
+
+impl Display for MatrixFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MatrixFormat::Dense => "dense",
+            MatrixFormat::Dedup => "dedup",
+        })
+    }
+}
+
+/// Connection cost matrix.
+///
+/// Costs for each right id are stored as a contiguous row of `num_left` values.
+/// In deduplicated matrices rows are located by the offsets table,
+/// so rows can be shared between right ids.
+#[derive(Clone)]
 pub struct ConnectionMatrix<'a> {
     data: CowArray<'a, i16>,
+    /// Offset of the row for each right id, empty for dense matrices
+    rows: Vec<u32>,
     num_left: usize,
     num_right: usize,
 }
@@ -32,25 +89,91 @@ impl<'a> ConnectionMatrix<'a> {
     ) -> SudachiResult<ConnectionMatrix<'a>> {
         let size = num_left * num_right;
 
-        let end = offset + size;
+        let end = offset + size * std::mem::size_of::<i16>();
         if end > data.len() {
             return Err(SudachiError::InvalidDictionaryGrammar.with_context("connection matrix"));
         }
 
         Ok(ConnectionMatrix {
             data: CowArray::from_bytes(data, offset, size),
+            rows: Vec::new(),
             num_left,
             num_right,
         })
     }
 
+    /// Reads the matrix (including its sizes) in any of the supported formats.
+    ///
+    /// Returns the matrix and the number of bytes it occupies.
+    pub fn parse(data: &'a [u8], offset: usize) -> SudachiResult<(ConnectionMatrix<'a>, usize)> {
+        let mut reader = Reader { data, offset };
+        let num_left = reader.i16()?;
+        if num_left != MatrixFormat::MARKER {
+            let num_right = reader.i16()?;
+            check_sizes(num_left, num_right)?;
+            let (num_left, num_right) = (num_left as usize, num_right as usize);
+            let matrix = Self::from_offset_size(data, reader.offset, num_left, num_right)?;
+            return Ok((matrix, 4 + num_left * num_right * 2));
+        }
+
+        let tag = reader.u16()?;
+        if tag != MatrixFormat::DEDUP_TAG {
+            return Err(SudachiError::InvalidDictionaryGrammar
+                .with_context(format!("unknown connection matrix format {}", tag)));
+        }
+        let num_left = reader.i16()?;
+        let num_right = reader.i16()?;
+        check_sizes(num_left, num_right)?;
+        let (num_left, num_right) = (num_left as usize, num_right as usize);
+        let num_rows = reader.u16()? as usize;
+        let mut rows = Vec::with_capacity(num_right);
+        for _ in 0..num_right {
+            let row = reader.u16()? as usize;
+            if row >= num_rows {
+                return Err(SudachiError::InvalidDictionaryGrammar
+                    .with_context(format!("connection matrix row {} >= {}", row, num_rows)));
+            }
+            rows.push((row * num_left) as u32);
+        }
+        if num_rows == num_right
+            && rows
+                .iter()
+                .enumerate()
+                .all(|(i, r)| *r as usize == i * num_left)
+        {
+            // no rows are shared, the matrix is dense
+            rows.clear();
+        }
+        let size = num_rows * num_left;
+        if reader.offset + size * 2 > data.len() {
+            return Err(SudachiError::InvalidDictionaryGrammar.with_context("connection matrix"));
+        }
+        let matrix = ConnectionMatrix {
+            data: CowArray::from_bytes(data, reader.offset, size),
+            rows,
+            num_left,
+            num_right,
+        };
+        Ok((matrix, reader.offset + size * 2 - offset))
+    }
+
+    /// Whether rows of the matrix are shared between right ids
+    pub fn is_compressed(&self) -> bool {
+        !self.rows.is_empty()
+    }
+
     #[inline(always)]
     fn index(&self, left: u16, right: u16) -> usize {
         let uleft = left as usize;
         let uright = right as usize;
         debug_assert!(uleft < self.num_left);
         debug_assert!(uright < self.num_right);
-        let index = uright * self.num_left + uleft;
+        let index = if self.rows.is_empty() {
+            uright * self.num_left + uleft
+        } else {
+            debug_assert!(uright < self.rows.len());
+            *unsafe { self.rows.get_unchecked(uright) } as usize + uleft
+        };
         debug_assert!(index < self.data.len());
         index
     }
@@ -58,7 +181,8 @@ impl<'a> ConnectionMatrix<'a> {
     /// Gets the value of the connection matrix
     ///
     /// It is performance critical that this function
-    /// 1. Has no branches
+    /// 1. Has no branches, except the choice between dense and deduplicated
+    ///    matrices, which is always the same for a dictionary
     /// 2. Is inlined to the caller
     ///
     /// This is UB if index is out of bounds, but that can't happen
@@ -71,6 +195,10 @@ impl<'a> ConnectionMatrix<'a> {
     }
 
     pub fn update(&mut self, left: u16, right: u16, value: i16) {
+        if self.is_compressed() {
+            // shared rows must not be modified, so the matrix is expanded to the dense form
+            self.expand();
+        }
         let index = self.index(left, right);
         self.data.set(index, value);
     }
@@ -84,4 +212,47 @@ impl<'a> ConnectionMatrix<'a> {
     pub fn num_right(&self) -> usize {
         self.num_right
     }
+
+    fn expand(&mut self) {
+        let mut dense = Vec::with_capacity(self.num_left * self.num_right);
+        for row in self.rows.iter() {
+            let start = *row as usize;
+            dense.extend_from_slice(&self.data[start..start + self.num_left]);
+        }
+        self.data = CowArray::from_owned(dense);
+        self.rows = Vec::new();
+    }
+}
+
+fn check_sizes(num_left: i16, num_right: i16) -> SudachiResult<()> {
+    if num_left < 0 || num_right < 0 {
+        return Err(SudachiError::InvalidDictionaryGrammar
+            .with_context(format!("connection matrix size {}x{}", num_left, num_right)));
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self) -> SudachiResult<[u8; 2]> {
+        match self.data.get(self.offset..self.offset + 2) {
+            Some(b) => {
+                self.offset += 2;
+                Ok([b[0], b[1]])
+            }
+            None => Err(SudachiError::InvalidDictionaryGrammar.with_context("connection matrix")),
+        }
+    }
+
+    fn i16(&mut self) -> SudachiResult<i16> {
+        self.bytes().map(i16::from_le_bytes)
+    }
+
+    fn u16(&mut self) -> SudachiResult<u16> {
+        self.bytes().map(u16::from_le_bytes)
+    }
 }
@@ -16,14 +16,23 @@
 
 use std::fs::File;
 use std::path::Path;
+use std::sync::Arc;
 
 use memmap2::Mmap;
 
+use crate::analysis::batch_tokenizer::BatchTokenizer;
+use crate::analysis::mlist::MorphemeList;
 use crate::analysis::stateless_tokenizer::DictionaryAccess;
+use crate::analysis::Mode;
 use crate::config::Config;
 use crate::dic::grammar::Grammar;
+use crate::dic::header::Header;
+use crate::dic::lexicon::Lexicon;
 use crate::dic::lexicon_set::LexiconSet;
+use crate::dic::mutable_lexicon::MutableUserLexicon;
+use crate::dic::oov_reading::OovReading;
 use crate::dic::storage::{Storage, SudachiDicData};
+use crate::dic::verify::{verify_system, verify_user, VerifyReport};
 use crate::dic::{DictionaryLoader, LoadedDictionary};
 use crate::error::{SudachiError, SudachiResult};
 use crate::plugin::input_text::InputTextPlugin;
@@ -40,9 +49,14 @@ use crate::plugin::Plugins;
 // will not change if the structure will be moved around.
 // This structure is always read only after creation and is safe to share
 // between threads.
+// Storages, plugins and the base grammar are shared with dictionaries
+// created by `with_user_dicts`.
 pub struct JapaneseDictionary {
     storage: SudachiDicData,
-    plugins: Plugins,
+    plugins: Arc<Plugins>,
+    // grammar after plugin initialization, without user dictionaries
+    //'static is a a lie, lifetime is the same with StorageBackend
+    base_grammar: Grammar<'static>,
     //'static is a a lie, lifetime is the same with StorageBackend
     _grammar: Grammar<'static>,
     //'static is a a lie, lifetime is the same with StorageBackend
@@ -85,6 +99,11 @@ impl JapaneseDictionary {
             cfg.complete_path(&cfg.character_definition_file)?.as_path(),
         )?;
 
+        if let Some(settings) = cfg.oov_reading.as_ref() {
+            let oov_reading = OovReading::from_config(settings, cfg)?;
+            basic_dict.grammar.set_oov_reading(oov_reading);
+        }
+
         let plugins = {
             let grammar = &mut basic_dict.grammar;
             Plugins::load(cfg, grammar)?
@@ -98,20 +117,7 @@ impl JapaneseDictionary {
             p.edit(&mut basic_dict.grammar);
         }
 
-        let mut dic = JapaneseDictionary {
-            storage,
-            plugins,
-            _grammar: basic_dict.grammar,
-            _lexicon: basic_dict.lexicon_set,
-        };
-
-        // this Vec is needed to prevent double borrowing of dic
-        let user_dicts: Vec<_> = dic.storage.user_static_slice();
-        for udic in user_dicts {
-            dic = dic.merge_user_dictionary(udic)?;
-        }
-
-        Ok(dic)
+        Self::from_loaded(storage, basic_dict, Arc::new(plugins))
     }
 
     /// Creates a dictionary from the specified configuration and storage, with embedded character definition
@@ -123,6 +129,11 @@ impl JapaneseDictionary {
             storage.system_static_slice()
         })?;
 
+        if let Some(settings) = cfg.oov_reading.as_ref() {
+            let oov_reading = OovReading::from_config(settings, cfg)?;
+            basic_dict.grammar.set_oov_reading(oov_reading);
+        }
+
         let plugins = {
             let grammar = &mut basic_dict.grammar;
             Plugins::load(cfg, grammar)?
@@ -136,9 +147,121 @@ impl JapaneseDictionary {
             p.edit(&mut basic_dict.grammar);
         }
 
+        Self::from_loaded(storage, basic_dict, Arc::new(plugins))
+    }
+
+    /// Returns grammar with the correct lifetime
+    pub fn grammar(&self) -> &Grammar<'_> {
+        &self._grammar
+    }
+
+    /// Returns lexicon with the correct lifetime
+    pub fn lexicon(&self) -> &LexiconSet<'_> {
+        &self._lexicon
+    }
+
+    /// Attach a user lexicon which can be modified at runtime.
+    ///
+    /// The lexicon is shared, so words can be added or removed
+    /// while the dictionary is used by tokenizers.
+    /// Only a single mutable lexicon can be attached.
+    ///
+    /// The lexicon is updated for the user dictionaries of this dictionary. It fails if
+    /// words of the lexicon refer to user dictionaries which were added or removed
+    /// since the lexicon was created or attached last.
+    pub fn attach_mutable_lexicon(
+        &mut self,
+        lexicon: Arc<MutableUserLexicon>,
+    ) -> SudachiResult<()> {
+        self._lexicon.attach_mutable(lexicon.clone())?;
+        if let Err(e) = lexicon.bind(self) {
+            self._lexicon.detach_mutable();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Detach the mutable user lexicon, returning it
+    pub fn detach_mutable_lexicon(&mut self) -> Option<Arc<MutableUserLexicon>> {
+        self._lexicon.detach_mutable()
+    }
+
+    /// Analyzes all inputs in parallel, using all available cores.
+    /// Results are returned in the same order as inputs.
+    ///
+    /// Use [`BatchTokenizer`] directly to control the number of threads
+    /// or to reuse tokenizers between batches.
+    pub fn tokenize_batch<S: AsRef<str> + Sync>(
+        &self,
+        inputs: &[S],
+        mode: Mode,
+    ) -> SudachiResult<Vec<MorphemeList<&Self>>> {
+        BatchTokenizer::new(self, mode).tokenize(inputs)
+    }
+
+    /// Number of loaded binary user dictionaries
+    pub fn num_user_dicts(&self) -> usize {
+        self.storage.user_storages().len()
+    }
+
+    /// Creates a new dictionary with the same system dictionary and plugins,
+    /// but with a different list of user dictionaries.
+    ///
+    /// This dictionary is not modified, and the attached mutable user lexicon is shared.
+    /// See [`LiveDictionary`](crate::dic::live_dictionary::LiveDictionary)
+    /// for swapping dictionaries in a running service.
+    pub fn with_user_dicts(&self, user: Vec<Arc<Storage>>) -> SudachiResult<JapaneseDictionary> {
+        let storage = SudachiDicData::from_shared(self.storage.system_storage().clone(), user);
+        let system = unsafe { storage.system_static_slice() };
+        let header = Header::parse(&system[..Header::STORAGE_SIZE])?;
+        let lexicon = Lexicon::parse_with_header(
+            system,
+            Header::STORAGE_SIZE + self.base_grammar.storage_size,
+            &header,
+        )?;
+        let basic_dict = LoadedDictionary {
+            grammar: self.base_grammar.clone(),
+            lexicon_set: LexiconSet::new(lexicon, self._lexicon.num_system_pos()),
+        };
+        let mut dic = Self::from_loaded(storage, basic_dict, self.plugins.clone())?;
+        if let Some(m) = self._lexicon.mutable_lexicon() {
+            dic.attach_mutable_lexicon(m.clone())?;
+        }
+        Ok(dic)
+    }
+
+    /// Checks the system dictionary and all binary user dictionaries for consistency,
+    /// see [`verify`](crate::dic::verify) for the list of performed checks.
+    /// Reads the whole dictionary, so it is not done when the dictionary is loaded.
+    ///
+    /// Returns reports for the system dictionary and then for the user dictionaries.
+    pub fn verify(&self) -> SudachiResult<Vec<VerifyReport>> {
+        let system = verify_system(self.storage.system())
+            .map_err(|e| e.with_context("system dictionary"))?;
+        let mut reports = Vec::with_capacity(self.num_user_dicts() + 1);
+        for (i, user) in self.storage.user_storages().iter().enumerate() {
+            let report = verify_user(user.as_ref().as_ref(), &system)
+                .map_err(|e| e.with_context(format!("user dictionary #{}", i)))?;
+            reports.push(report);
+        }
+        reports.insert(0, system);
+        Ok(reports)
+    }
+
+    /// Shared storages of the user dictionaries
+    pub(crate) fn user_storages(&self) -> &[Arc<Storage>] {
+        self.storage.user_storages()
+    }
+
+    fn from_loaded(
+        storage: SudachiDicData,
+        basic_dict: LoadedDictionary<'static>,
+        plugins: Arc<Plugins>,
+    ) -> SudachiResult<JapaneseDictionary> {
         let mut dic = JapaneseDictionary {
             storage,
             plugins,
+            base_grammar: basic_dict.grammar.clone(),
             _grammar: basic_dict.grammar,
             _lexicon: basic_dict.lexicon_set,
         };
@@ -152,16 +275,6 @@ impl JapaneseDictionary {
         Ok(dic)
     }
 
-    /// Returns grammar with the correct lifetime
-    pub fn grammar(&self) -> &Grammar<'_> {
-        &self._grammar
-    }
-
-    /// Returns lexicon with the correct lifetime
-    pub fn lexicon(&self) -> &LexiconSet<'_> {
-        &self._lexicon
-    }
-
     fn merge_user_dictionary(mut self, dictionary_bytes: &'static [u8]) -> SudachiResult<Self> {
         let user_dict = DictionaryLoader::read_user_dictionary(dictionary_bytes)?;
 
@@ -16,16 +16,15 @@
 
 use crate::dic::character_category::CharacterCategory;
 use crate::dic::connect::ConnectionMatrix;
+use crate::dic::oov_reading::OovReading;
 use crate::dic::read::u16str::utf16_string_parser;
 use crate::dic::POS_DEPTH;
 use crate::error::SudachiNomResult;
 use crate::prelude::*;
 use itertools::Itertools;
-use nom::{
-    bytes::complete::take,
-    number::complete::{le_i16, le_u16},
-};
+use nom::{bytes::complete::take, number::complete::le_u16};
 use std::ops::Index;
+use std::sync::Arc;
 
 type PosList = Vec<Vec<String>>;
 
@@ -33,6 +32,7 @@ type PosList = Vec<Vec<String>>;
 ///
 /// Contains part_of_speech list and connection cost map.
 /// It also holds character category.
+#[derive(Clone)]
 pub struct Grammar<'a> {
     _bytes: &'a [u8],
     pub pos_list: PosList,
@@ -43,6 +43,9 @@ pub struct Grammar<'a> {
 
     /// The mapping from character to character_category_type
     pub character_category: CharacterCategory,
+
+    /// Reading estimation for OOV words, OOV words have no reading if it is not set
+    pub oov_reading: Option<Arc<OovReading>>,
 }
 
 impl<'a> Grammar<'a> {
@@ -56,19 +59,12 @@ impl<'a> Grammar<'a> {
     /// buf: reference to the dictionary bytes
     /// offset: offset to the grammar section in the buf
     pub fn parse(buf: &[u8], offset: usize) -> SudachiResult<Grammar> {
-        let (rest, (pos_list, left_id_size, right_id_size)) = grammar_parser(buf, offset)
+        let (rest, pos_list) = grammar_parser(buf, offset)
             .map_err(|e| SudachiError::InvalidDictionaryGrammar.with_context(e.to_string()))?;
 
         let connect_table_offset = buf.len() - rest.len();
-        let storage_size =
-            (connect_table_offset - offset) + 2 * left_id_size as usize * right_id_size as usize;
-
-        let conn = ConnectionMatrix::from_offset_size(
-            buf,
-            connect_table_offset,
-            left_id_size as usize,
-            right_id_size as usize,
-        )?;
+        let (conn, conn_size) = ConnectionMatrix::parse(buf, connect_table_offset)?;
+        let storage_size = (connect_table_offset - offset) + conn_size;
 
         Ok(Grammar {
             _bytes: buf,
@@ -76,6 +72,7 @@ impl<'a> Grammar<'a> {
             connection: conn,
             storage_size,
             character_category: CharacterCategory::default(),
+            oov_reading: None,
         })
     }
 
@@ -101,6 +98,11 @@ impl<'a> Grammar<'a> {
         self.character_category = character_category;
     }
 
+    /// Sets reading estimation for OOV words
+    pub fn set_oov_reading(&mut self, oov_reading: OovReading) {
+        self.oov_reading = Some(Arc::new(oov_reading));
+    }
+
     /// Sets connect cost for a specific pair of ids
     ///
     /// left_id: right_id of left node
@@ -173,11 +175,8 @@ fn pos_list_parser(input: &[u8]) -> SudachiNomResult<&[u8], PosList> {
     )(rest)
 }
 
-fn grammar_parser(input: &[u8], offset: usize) -> SudachiNomResult<&[u8], (PosList, i16, i16)> {
-    nom::sequence::preceded(
-        take(offset),
-        nom::sequence::tuple((pos_list_parser, le_i16, le_i16)),
-    )(input)
+fn grammar_parser(input: &[u8], offset: usize) -> SudachiNomResult<&[u8], PosList> {
+    nom::sequence::preceded(take(offset), pos_list_parser)(input)
 }
 
 #[cfg(test)]
@@ -49,6 +49,10 @@ pub enum SystemDictVersion {
     // we cannot set value since value can be larger than isize
     Version1,
     Version2,
+    /// Version 2 with strings of word infos stored in the string pool
+    Version3,
+    /// Version 3 with the connection matrix which can be stored in a non-dense format
+    Version4,
 }
 
 impl HeaderVersion {
@@ -61,6 +65,12 @@ impl HeaderVersion {
             HeaderVersion::SystemDict(SystemDictVersion::Version2) => {
                 HeaderVersion::SYSTEM_DICT_VERSION_2
             }
+            HeaderVersion::SystemDict(SystemDictVersion::Version3) => {
+                HeaderVersion::SYSTEM_DICT_VERSION_3
+            }
+            HeaderVersion::SystemDict(SystemDictVersion::Version4) => {
+                HeaderVersion::SYSTEM_DICT_VERSION_4
+            }
             HeaderVersion::UserDict(UserDictVersion::Version1) => {
                 HeaderVersion::USER_DICT_VERSION_1
             }
@@ -70,6 +80,12 @@ impl HeaderVersion {
             HeaderVersion::UserDict(UserDictVersion::Version3) => {
                 HeaderVersion::USER_DICT_VERSION_3
             }
+            HeaderVersion::UserDict(UserDictVersion::Version4) => {
+                HeaderVersion::USER_DICT_VERSION_4
+            }
+            HeaderVersion::UserDict(UserDictVersion::Version5) => {
+                HeaderVersion::USER_DICT_VERSION_5
+            }
             _ => panic!("unknown version {:?}", self),
         }
     }
@@ -80,18 +96,30 @@ pub enum UserDictVersion {
     Version1,
     Version2,
     Version3,
+    /// Version 3 with strings of word infos stored in the string pool
+    Version4,
+    /// Version 4 with the connection matrix which can be stored in a non-dense format
+    Version5,
 }
 impl HeaderVersion {
     /// the first version of system dictionaries
     const SYSTEM_DICT_VERSION_1: u64 = 0x7366d3f18bd111e7;
     /// the second version of system dictionaries
     const SYSTEM_DICT_VERSION_2: u64 = 0xce9f011a92394434;
+    /// the third version of system dictionaries (compressed word infos)
+    const SYSTEM_DICT_VERSION_3: u64 = 0x51c0f7a2d8e3b694;
+    /// the fourth version of system dictionaries (non-dense connection matrix)
+    const SYSTEM_DICT_VERSION_4: u64 = 0x4b80ab06f0fa5117;
     /// the first version of user dictionaries
     const USER_DICT_VERSION_1: u64 = 0xa50f31188bd211e7;
     /// the second version of user dictionaries
     const USER_DICT_VERSION_2: u64 = 0x9fdeb5a90168d868;
     /// the third version of user dictionaries
     const USER_DICT_VERSION_3: u64 = 0xca9811756ff64fb0;
+    /// the fourth version of user dictionaries (compressed word infos)
+    const USER_DICT_VERSION_4: u64 = 0x2f86d94be01c7a35;
+    /// the fifth version of user dictionaries (non-dense connection matrix)
+    const USER_DICT_VERSION_5: u64 = 0x0fdad2f43886c87d;
 
     pub fn from_u64(v: u64) -> Option<Self> {
         match v {
@@ -101,9 +129,17 @@ impl HeaderVersion {
             HeaderVersion::SYSTEM_DICT_VERSION_2 => {
                 Some(Self::SystemDict(SystemDictVersion::Version2))
             }
+            HeaderVersion::SYSTEM_DICT_VERSION_3 => {
+                Some(Self::SystemDict(SystemDictVersion::Version3))
+            }
+            HeaderVersion::SYSTEM_DICT_VERSION_4 => {
+                Some(Self::SystemDict(SystemDictVersion::Version4))
+            }
             HeaderVersion::USER_DICT_VERSION_1 => Some(Self::UserDict(UserDictVersion::Version1)),
             HeaderVersion::USER_DICT_VERSION_2 => Some(Self::UserDict(UserDictVersion::Version2)),
             HeaderVersion::USER_DICT_VERSION_3 => Some(Self::UserDict(UserDictVersion::Version3)),
+            HeaderVersion::USER_DICT_VERSION_4 => Some(Self::UserDict(UserDictVersion::Version4)),
+            HeaderVersion::USER_DICT_VERSION_5 => Some(Self::UserDict(UserDictVersion::Version5)),
             _ => None,
         }
     }
@@ -111,12 +147,18 @@ impl HeaderVersion {
 
 /// Dictionary header
 ///
-/// Contains version, create_time, and description
+/// Contains version, create_time, description and optional content checksum.
+///
+/// The checksum is stored in the tail of the description field after the NUL terminator,
+/// so it is ignored by readers which do not know about it.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Header {
     pub version: HeaderVersion,
     pub create_time: u64,
     pub description: String,
+    /// Checksum of the dictionary contents after the header,
+    /// see [`crate::dic::verify::checksum`]
+    pub checksum: Option<u64>,
 }
 
 impl Default for Header {
@@ -128,6 +170,11 @@ impl Default for Header {
 impl Header {
     const DESCRIPTION_SIZE: usize = 256;
     pub const STORAGE_SIZE: usize = 8 + 8 + Header::DESCRIPTION_SIZE;
+    const CHECKSUM_MAGIC: &'static [u8; 4] = b"SDCK";
+    const CHECKSUM_SIZE: usize = Header::CHECKSUM_MAGIC.len() + 8;
+    const CHECKSUM_OFFSET: usize = Header::DESCRIPTION_SIZE - Header::CHECKSUM_SIZE;
+    /// Maximum length of the description (in bytes) if the header has a checksum
+    pub const MAX_DESCRIPTION_WITH_CHECKSUM: usize = Header::CHECKSUM_OFFSET - 1;
 
     /// Creates new system dictionary header
     /// Its version field should be modified to create user dictionary header
@@ -140,6 +187,7 @@ impl Header {
             version: HeaderVersion::SystemDict(SystemDictVersion::Version2),
             create_time: unix_time.as_secs(),
             description: String::new(),
+            checksum: None,
         }
     }
 
@@ -156,7 +204,7 @@ impl Header {
 
     /// Creates a new header from a dictionary bytes
     pub fn parse(bytes: &[u8]) -> Result<Header, HeaderError> {
-        let (_rest, (version, create_time, description)) =
+        let (_rest, (version, create_time, (description, checksum))) =
             header_parser(bytes).map_err(|_| HeaderError::CannotParse)?;
 
         let version = HeaderVersion::from_u64(version).ok_or(HeaderError::InvalidVersion)?;
@@ -165,6 +213,7 @@ impl Header {
             version,
             create_time,
             description,
+            checksum,
         })
     }
 
@@ -175,6 +224,8 @@ impl Header {
             HeaderVersion::SystemDict(_)
                 | HeaderVersion::UserDict(UserDictVersion::Version2)
                 | HeaderVersion::UserDict(UserDictVersion::Version3)
+                | HeaderVersion::UserDict(UserDictVersion::Version4)
+                | HeaderVersion::UserDict(UserDictVersion::Version5)
         )
     }
 
@@ -183,24 +234,58 @@ impl Header {
         matches!(
             self.version,
             HeaderVersion::SystemDict(SystemDictVersion::Version2)
+                | HeaderVersion::SystemDict(SystemDictVersion::Version3)
+                | HeaderVersion::SystemDict(SystemDictVersion::Version4)
                 | HeaderVersion::UserDict(UserDictVersion::Version3)
+                | HeaderVersion::UserDict(UserDictVersion::Version4)
+                | HeaderVersion::UserDict(UserDictVersion::Version5)
+        )
+    }
+
+    /// Returns if strings of word infos are stored in the string pool
+    pub fn has_string_pool(&self) -> bool {
+        matches!(
+            self.version,
+            HeaderVersion::SystemDict(SystemDictVersion::Version3)
+                | HeaderVersion::SystemDict(SystemDictVersion::Version4)
+                | HeaderVersion::UserDict(UserDictVersion::Version4)
+                | HeaderVersion::UserDict(UserDictVersion::Version5)
+        )
+    }
+
+    /// Returns if the connection matrix can be stored in a non-dense format,
+    /// see [`MatrixFormat`](crate::dic::connect::MatrixFormat)
+    pub fn has_matrix_formats(&self) -> bool {
+        matches!(
+            self.version,
+            HeaderVersion::SystemDict(SystemDictVersion::Version4)
+                | HeaderVersion::UserDict(UserDictVersion::Version5)
         )
     }
 
     pub fn write_to<W: Write>(&self, w: &mut W) -> SudachiResult<usize> {
-        if self.description.len() > Header::DESCRIPTION_SIZE {
+        let max_size = match self.checksum {
+            None => Header::DESCRIPTION_SIZE,
+            Some(_) => Header::MAX_DESCRIPTION_WITH_CHECKSUM,
+        };
+        if self.description.len() > max_size {
             return Err(SudachiError::InvalidDataFormat(
-                Header::DESCRIPTION_SIZE,
+                max_size,
                 self.description.clone(),
             ));
         }
 
+        let mut description = [0u8; Header::DESCRIPTION_SIZE];
+        description[..self.description.len()].copy_from_slice(self.description.as_bytes());
+        if let Some(checksum) = self.checksum {
+            let tail = &mut description[Header::CHECKSUM_OFFSET..];
+            tail[..4].copy_from_slice(Header::CHECKSUM_MAGIC);
+            tail[4..].copy_from_slice(&checksum.to_le_bytes());
+        }
+
         w.write_all(&self.version.to_u64().to_le_bytes())?;
         w.write_all(&self.create_time.to_le_bytes())?;
-        w.write_all(self.description.as_bytes())?;
-        for _ in 0..Header::DESCRIPTION_SIZE - self.description.len() {
-            w.write_all(&[0])?;
-        }
+        w.write_all(&description)?;
         Ok(Header::STORAGE_SIZE)
     }
 }
@@ -215,12 +300,31 @@ fn nul_terminated_str_from_slice(buf: &[u8]) -> String {
     String::from_utf8_lossy(str_bytes).to_string()
 }
 
-fn description_parser(input: &[u8]) -> SudachiNomResult<&[u8], String> {
+/// Checksum is present only if the description is terminated before the checksum area
+fn checksum_from_slice(buf: &[u8]) -> Option<u64> {
+    let (description, tail) = buf.split_at(Header::CHECKSUM_OFFSET);
+    if !description.contains(&0) || &tail[..4] != Header::CHECKSUM_MAGIC {
+        return None;
+    }
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&tail[4..]);
+    Some(u64::from_le_bytes(value))
+}
+
+fn description_parser(input: &[u8]) -> SudachiNomResult<&[u8], (String, Option<u64>)> {
     let (rest, description_bytes) = take(Header::DESCRIPTION_SIZE)(input)?;
-    Ok((rest, nul_terminated_str_from_slice(description_bytes)))
+    Ok((
+        rest,
+        (
+            nul_terminated_str_from_slice(description_bytes),
+            checksum_from_slice(description_bytes),
+        ),
+    ))
 }
 
-fn header_parser(input: &[u8]) -> SudachiNomResult<&[u8], (u64, u64, String)> {
+type RawHeader = (u64, u64, (String, Option<u64>));
+
+fn header_parser(input: &[u8]) -> SudachiNomResult<&[u8], RawHeader> {
     nom::sequence::tuple((le_u64, le_u64, description_parser))(input)
 }
 
@@ -265,6 +369,7 @@ mod tests {
                 version: HeaderVersion::SystemDict(SystemDictVersion::Version1),
                 description: description_str.to_string(),
                 create_time: 1337,
+                checksum: None,
             })
         );
     }
@@ -288,4 +393,29 @@ mod tests {
         let header2 = Header::parse(&data).unwrap();
         assert_eq!(header, header2);
     }
+
+    #[test]
+    fn write_checksum() {
+        let mut header = Header::new();
+        header.description = String::from("with checksum");
+        header.checksum = Some(0x0123_4567_89ab_cdef);
+        let mut data: Vec<u8> = Vec::new();
+        assert_eq!(header.write_to(&mut data).unwrap(), Header::STORAGE_SIZE);
+        let header2 = Header::parse(&data).unwrap();
+        assert_eq!(header, header2);
+    }
+
+    #[test]
+    fn long_description_has_no_checksum() {
+        let mut header = Header::new();
+        header.description = "a".repeat(Header::DESCRIPTION_SIZE);
+        let mut data: Vec<u8> = Vec::new();
+        header.write_to(&mut data).unwrap();
+        assert_eq!(None, Header::parse(&data).unwrap().checksum);
+
+        header.checksum = Some(5);
+        assert!(header.write_to(&mut Vec::new()).is_err());
+        header.description = "a".repeat(Header::MAX_DESCRIPTION_WITH_CHECKSUM);
+        assert!(header.write_to(&mut Vec::new()).is_ok());
+    }
 }
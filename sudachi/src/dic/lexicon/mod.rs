@@ -19,6 +19,7 @@ use std::mem::size_of;
 
 use crate::analysis::stateful_tokenizer::StatefulTokenizer;
 use crate::analysis::stateless_tokenizer::DictionaryAccess;
+use crate::dic::header::Header;
 use crate::dic::subset::InfoSubset;
 use crate::dic::word_id::WordId;
 use nom::{bytes::complete::take, number::complete::le_u32};
@@ -28,7 +29,7 @@ use crate::prelude::*;
 
 use self::trie::Trie;
 use self::word_id_table::WordIdTable;
-use self::word_infos::{WordInfo, WordInfos};
+use self::word_infos::{WordInfo, WordInfoData, WordInfos};
 use self::word_params::WordParams;
 
 pub mod trie;
@@ -36,10 +37,10 @@ pub mod word_id_table;
 pub mod word_infos;
 pub mod word_params;
 
-/// The first 4 bits of word_id are used to indicate that from which lexicon
-/// the word comes, thus we can only hold 15 lexicons in the same time.
-/// 16th is reserved for marking OOVs.
-pub const MAX_DICTIONARIES: usize = 15;
+/// 8 bits of word_id are used to indicate that from which lexicon
+/// the word comes, thus we can only hold 255 lexicons in the same time.
+/// 256th is reserved for marking OOVs.
+pub const MAX_DICTIONARIES: usize = 255;
 
 /// Dictionary lexicon
 ///
@@ -75,6 +76,29 @@ impl<'a> Lexicon<'a> {
         original_offset: usize,
         has_synonym_group_ids: bool,
     ) -> SudachiResult<Lexicon> {
+        Self::parse_impl(buf, original_offset, has_synonym_group_ids, false)
+    }
+
+    /// Parses the lexicon of the dictionary with the given header
+    pub fn parse_with_header<'h>(
+        buf: &'h [u8],
+        original_offset: usize,
+        header: &Header,
+    ) -> SudachiResult<Lexicon<'h>> {
+        Self::parse_impl(
+            buf,
+            original_offset,
+            header.has_synonym_group_ids(),
+            header.has_string_pool(),
+        )
+    }
+
+    fn parse_impl(
+        buf: &[u8],
+        original_offset: usize,
+        has_synonym_group_ids: bool,
+        has_string_pool: bool,
+    ) -> SudachiResult<Lexicon<'_>> {
         let mut offset = original_offset;
 
         let (_rest, trie_size) = u32_parser_offset(buf, offset)?;
@@ -91,7 +115,24 @@ impl<'a> Lexicon<'a> {
         let word_params = WordParams::new(buf, word_params_size, offset + 4);
         offset += word_params.storage_size();
 
-        let word_infos = WordInfos::new(buf, offset, word_params.size(), has_synonym_group_ids);
+        let pool = if has_string_pool {
+            let (_rest, pool_size) = u32_parser_offset(buf, offset)?;
+            let start = offset + 4;
+            let end = start + pool_size as usize;
+            if end > buf.len() {
+                return Err(SudachiError::InvalidDictionary(format!(
+                    "string pool of {} bytes is out of bounds",
+                    pool_size
+                )));
+            }
+            offset = end;
+            Some(&buf[start..end])
+        } else {
+            None
+        };
+
+        let word_infos =
+            WordInfos::new(buf, offset, word_params.size(), has_synonym_group_ids, pool);
 
         Ok(Lexicon {
             trie,
@@ -137,6 +178,26 @@ impl<'a> Lexicon<'a> {
         self.word_infos.get_word_info(word_id, subset)
     }
 
+    /// Returns WordInfo data for given word_id exactly as it is stored in the binary dictionary:
+    /// dictionary form is not resolved and word ids are not adjusted to the lexicon set
+    pub(crate) fn get_word_info_raw(
+        &self,
+        word_id: u32,
+        subset: InfoSubset,
+    ) -> SudachiResult<WordInfoData> {
+        self.word_infos.get_word_info_raw(word_id, subset)
+    }
+
+    /// Calls `f` for every word which is present in the index,
+    /// with the indexed surface (as UTF-8 bytes) and the word id
+    pub fn for_each_indexed_word<F: FnMut(&[u8], u32)>(&self, mut f: F) {
+        self.trie.for_each_key(|key, value| {
+            for wid in self.word_id_table.entries(value as usize) {
+                f(key, wid);
+            }
+        })
+    }
+
     /// Returns word_param for given word_id.
     /// Params are (left_id, right_id, cost).
     #[inline]
@@ -156,16 +217,22 @@ impl<'a> Lexicon<'a> {
             tok.reset().push_str(wi.surface());
             tok.do_tokenize()?;
             ms.collect_results(&mut tok)?;
-            let internal_cost = ms.get_internal_cost();
-            let cost = internal_cost + Lexicon::USER_DICT_COST_PER_MORPH * ms.len() as i32;
-            let cost = cmp::min(cost, i16::MAX as i32);
-            let cost = cmp::max(cost, i16::MIN as i32);
-            self.word_params.set_cost(wid, cost as i16);
+            self.word_params.set_cost(wid, Lexicon::estimate_cost(&ms));
         }
 
         Ok(())
     }
 
+    /// Cost of a user word which is preferred to the given analysis of its surface:
+    /// a bit cheaper than the internal cost of the analysis
+    pub(crate) fn estimate_cost<D: DictionaryAccess>(analysis: &MorphemeList<D>) -> i16 {
+        let internal_cost = analysis.get_internal_cost();
+        let cost = internal_cost + Lexicon::USER_DICT_COST_PER_MORPH * analysis.len() as i32;
+        let cost = cmp::min(cost, i16::MAX as i32);
+        let cost = cmp::max(cost, i16::MIN as i32);
+        cost as i16
+    }
+
     pub fn size(&self) -> u32 {
         self.word_params.size()
     }
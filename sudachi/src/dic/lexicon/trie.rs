@@ -118,6 +118,37 @@ impl<'a> Trie<'a> {
         }
     }
 
+    /// Calls `f` with every key of the trie and its value, in the lexicographic order of keys
+    pub fn for_each_key<F: FnMut(&[u8], u32)>(&self, mut f: F) {
+        if self.array.is_empty() {
+            return;
+        }
+        let mut key = Vec::with_capacity(64);
+        let root = Trie::offset(self.get(0) as usize);
+        self.visit(root, &mut key, &mut f);
+    }
+
+    fn visit<F: FnMut(&[u8], u32)>(&self, node_pos: usize, key: &mut Vec<u8>, f: &mut F) {
+        // label 0 is used for leaves, keys can't contain it
+        for label in 1..=u8::MAX as usize {
+            let child = node_pos ^ label;
+            if child >= self.array.len() {
+                continue;
+            }
+            let unit = self.get(child) as usize;
+            if Trie::label(unit) != label {
+                continue;
+            }
+            let next = child ^ Trie::offset(unit);
+            key.push(label as u8);
+            if Trie::has_leaf(unit) {
+                f(key, Trie::value(self.get(next)));
+            }
+            self.visit(next, key, f);
+            key.pop();
+        }
+    }
+
     #[inline(always)]
     fn get(&self, index: usize) -> u32 {
         debug_assert!(index < self.array.len());
@@ -28,6 +28,7 @@ pub struct WordInfos<'a> {
     offset: usize,
     _word_size: u32,
     has_synonym_group_ids: bool,
+    pool: Option<&'a [u8]>,
 }
 
 impl<'a> WordInfos<'a> {
@@ -36,12 +37,14 @@ impl<'a> WordInfos<'a> {
         offset: usize,
         _word_size: u32,
         has_synonym_group_ids: bool,
-    ) -> WordInfos {
+        pool: Option<&'a [u8]>,
+    ) -> WordInfos<'a> {
         WordInfos {
             bytes,
             offset,
             _word_size,
             has_synonym_group_ids,
+            pool,
         }
     }
 
@@ -52,7 +55,21 @@ impl<'a> WordInfos<'a> {
     fn parse_word_info(&self, word_id: u32, subset: InfoSubset) -> SudachiResult<WordInfoData> {
         let index = self.word_id_to_offset(word_id)?;
         let parser = WordInfoParser::subset(subset);
-        parser.parse(&self.bytes[index..])
+        match self.pool {
+            None => parser.parse(&self.bytes[index..]),
+            Some(pool) => parser.parse_pooled(&self.bytes[index..], pool),
+        }
+    }
+
+    pub(crate) fn get_word_info_raw(
+        &self,
+        word_id: u32,
+        mut subset: InfoSubset,
+    ) -> SudachiResult<WordInfoData> {
+        if !self.has_synonym_group_ids {
+            subset -= InfoSubset::SYNONYM_GROUP_ID;
+        }
+        self.parse_word_info(word_id, subset)
     }
 
     pub fn get_word_info(&self, word_id: u32, mut subset: InfoSubset) -> SudachiResult<WordInfo> {
@@ -14,10 +14,13 @@
  * limitations under the License.
  */
 
+use std::sync::Arc;
+
 use thiserror::Error;
 
 use crate::dic::lexicon::word_infos::{WordInfo, WordInfoData};
 use crate::dic::lexicon::{Lexicon, LexiconEntry, MAX_DICTIONARIES};
+use crate::dic::mutable_lexicon::MutableUserLexicon;
 use crate::dic::subset::InfoSubset;
 use crate::dic::word_id::WordId;
 use crate::prelude::*;
@@ -33,16 +36,21 @@ pub enum LexiconSetError {
 
     #[error("too many user dictionaries")]
     TooManyDictionaries,
+
+    #[error("mutable user lexicon is already attached")]
+    MutableLexiconAttached,
 }
 
 /// Set of Lexicons
 ///
 /// Handles multiple lexicons as one lexicon
-/// The first lexicon in the list must be from system dictionary
+/// The first lexicon in the list must be from system dictionary.
+/// The mutable user lexicon, if attached, is the last one.
 pub struct LexiconSet<'a> {
     lexicons: Vec<Lexicon<'a>>,
     pos_offsets: Vec<usize>,
     num_system_pos: usize,
+    mutable: Option<Arc<MutableUserLexicon>>,
 }
 
 impl<'a> LexiconSet<'a> {
@@ -55,6 +63,7 @@ impl<'a> LexiconSet<'a> {
             lexicons: vec![system_lexicon],
             pos_offsets: vec![0],
             num_system_pos,
+            mutable: None,
         }
     }
 
@@ -66,6 +75,9 @@ impl<'a> LexiconSet<'a> {
         mut lexicon: Lexicon<'a>,
         pos_offset: usize,
     ) -> Result<(), LexiconSetError> {
+        if self.mutable.is_some() {
+            return Err(LexiconSetError::MutableLexiconAttached);
+        }
         if self.is_full() {
             return Err(LexiconSetError::TooManyDictionaries);
         }
@@ -75,9 +87,35 @@ impl<'a> LexiconSet<'a> {
         Ok(())
     }
 
+    /// Attach the mutable user lexicon, it will use the next dictionary id.
+    ///
+    /// No lexicons can be appended after it.
+    pub fn attach_mutable(
+        &mut self,
+        lexicon: Arc<MutableUserLexicon>,
+    ) -> Result<(), LexiconSetError> {
+        if self.mutable.is_some() {
+            return Err(LexiconSetError::MutableLexiconAttached);
+        }
+        if self.is_full() {
+            return Err(LexiconSetError::TooManyDictionaries);
+        }
+        self.mutable = Some(lexicon);
+        Ok(())
+    }
+
+    /// Detach the mutable user lexicon, returning it
+    pub fn detach_mutable(&mut self) -> Option<Arc<MutableUserLexicon>> {
+        self.mutable.take()
+    }
+
+    pub(crate) fn num_system_pos(&self) -> usize {
+        self.num_system_pos
+    }
+
     /// Returns if dictionary capacity is full
     pub fn is_full(&self) -> bool {
-        self.lexicons.len() >= MAX_DICTIONARIES
+        self.lexicons.len() + self.mutable.is_some() as usize >= MAX_DICTIONARIES
     }
 }
 
@@ -91,11 +129,27 @@ impl LexiconSet<'_> {
         input: &'b [u8],
         offset: usize,
     ) -> impl Iterator<Item = LexiconEntry> + 'b {
+        let mutable = match self.mutable.as_ref() {
+            Some(m) => m.lookup(input, offset, self.mutable_dic_id()),
+            None => Vec::new(),
+        };
         // word_id fixup was moved to lexicon itself
-        self.lexicons
-            .iter()
-            .rev()
-            .flat_map(move |l| l.lookup(input, offset))
+        mutable.into_iter().chain(
+            self.lexicons
+                .iter()
+                .rev()
+                .flat_map(move |l| l.lookup(input, offset)),
+        )
+    }
+
+    /// Returns the attached mutable user lexicon
+    pub fn mutable_lexicon(&self) -> Option<&Arc<MutableUserLexicon>> {
+        self.mutable.as_ref()
+    }
+
+    #[inline]
+    fn mutable_dic_id(&self) -> u8 {
+        self.lexicons.len() as u8
     }
 
     /// Returns WordInfo for given WordId
@@ -108,6 +162,12 @@ impl LexiconSet<'_> {
     /// Rest will be of default values (0 or empty).
     pub fn get_word_info_subset(&self, id: WordId, subset: InfoSubset) -> SudachiResult<WordInfo> {
         let dict_id = id.dic();
+        if let Some(m) = self.mutable.as_ref() {
+            if dict_id == self.mutable_dic_id() {
+                // ids in mutable lexicon are global, only its own id is remapped
+                return m.get_word_info_by_id(id);
+            }
+        }
         let mut word_info: WordInfoData = self.lexicons[dict_id as usize]
             .get_word_info(id.word(), subset)?
             .into();
@@ -139,7 +199,10 @@ impl LexiconSet<'_> {
     /// Returns word_param for given word_id
     pub fn get_word_param(&self, id: WordId) -> (i16, i16, i16) {
         let dic_id = id.dic() as usize;
-        self.lexicons[dic_id].get_word_param(id.word())
+        match self.mutable.as_ref() {
+            Some(m) if dic_id == self.mutable_dic_id() as usize => m.get_word_param(id.word()),
+            _ => self.lexicons[dic_id].get_word_param(id.word()),
+        }
     }
 
     fn update_dict_id(split: &mut Vec<WordId>, dict_id: u8) -> SudachiResult<()> {
@@ -156,4 +219,9 @@ impl LexiconSet<'_> {
     pub fn size(&self) -> u32 {
         self.lexicons.iter().fold(0, |acc, lex| acc + lex.size())
     }
+
+    /// Number of words in each lexicon, without the mutable user lexicon
+    pub(crate) fn lexicon_sizes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.lexicons.iter().map(|lex| lex.size())
+    }
 }
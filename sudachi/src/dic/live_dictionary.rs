@@ -0,0 +1,138 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Dictionary which user dictionaries can be replaced at runtime
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::config::Config;
+use crate::dic::dictionary::JapaneseDictionary;
+use crate::dic::storage::Storage;
+use crate::error::{SudachiError, SudachiResult};
+
+struct Generation {
+    id: u64,
+    dict: Arc<JapaneseDictionary>,
+}
+
+/// Holder of the current generation of [`JapaneseDictionary`],
+/// which allows to add, replace and remove user dictionaries at runtime.
+///
+/// Tokenizers should be created from [`LiveDictionary::snapshot`].
+/// They continue to use the generation they were created with,
+/// changes are visible only for snapshots taken after them.
+///
+/// New generations share the system dictionary and plugins with the current one,
+/// only user dictionaries are loaded again.
+/// Plugins are initialized only once, with the grammar of the system dictionary.
+/// The attached mutable user lexicon is shared by all generations, adding or removing
+/// user dictionaries fails if its words refer to user dictionaries
+/// (see [`JapaneseDictionary::attach_mutable_lexicon`]).
+pub struct LiveDictionary {
+    current: RwLock<Generation>,
+    // serializes updates, so no update is lost;
+    // readers are blocked only while the generation is swapped
+    update: Mutex<()>,
+}
+
+impl LiveDictionary {
+    pub fn new(dict: JapaneseDictionary) -> LiveDictionary {
+        LiveDictionary {
+            current: RwLock::new(Generation {
+                id: 0,
+                dict: Arc::new(dict),
+            }),
+            update: Mutex::new(()),
+        }
+    }
+
+    /// Creates the dictionary from the specified configuration
+    pub fn from_cfg(cfg: &Config) -> SudachiResult<LiveDictionary> {
+        JapaneseDictionary::from_cfg(cfg).map(LiveDictionary::new)
+    }
+
+    /// Returns the current generation of the dictionary
+    pub fn snapshot(&self) -> Arc<JapaneseDictionary> {
+        self.read(|g| g.dict.clone())
+    }
+
+    /// Number of the current generation, it is incremented on each change
+    pub fn generation(&self) -> u64 {
+        self.read(|g| g.id)
+    }
+
+    /// Number of user dictionaries in the current generation
+    pub fn num_user_dicts(&self) -> usize {
+        self.read(|g| g.dict.num_user_dicts())
+    }
+
+    /// Replace the user dictionary with the index (in the configuration order)
+    pub fn reload_user_dict(&self, index: usize, storage: Storage) -> SudachiResult<()> {
+        self.update(|user| {
+            let slot = user.get_mut(index).ok_or_else(|| invalid_index(index))?;
+            *slot = Arc::new(storage);
+            Ok(())
+        })
+    }
+
+    /// Add the user dictionary after all others
+    pub fn add_user_dict(&self, storage: Storage) -> SudachiResult<()> {
+        self.update(|user| {
+            user.push(Arc::new(storage));
+            Ok(())
+        })
+    }
+
+    /// Remove the user dictionary with the index (in the configuration order)
+    pub fn remove_user_dict(&self, index: usize) -> SudachiResult<()> {
+        self.update(|user| {
+            if index >= user.len() {
+                return Err(invalid_index(index));
+            }
+            user.remove(index);
+            Ok(())
+        })
+    }
+
+    fn update<F>(&self, f: F) -> SudachiResult<()>
+    where
+        F: FnOnce(&mut Vec<Arc<Storage>>) -> SudachiResult<()>,
+    {
+        let _guard = self.update.lock().unwrap_or_else(|e| e.into_inner());
+        let (id, current) = self.read(|g| (g.id, g.dict.clone()));
+        let mut user = current.user_storages().to_vec();
+        f(&mut user)?;
+        // the new generation is created without holding the lock,
+        // it requires analysis of user dictionary entries with unknown costs
+        let dict = current.with_user_dicts(user)?;
+        let mut gen = self.current.write().unwrap_or_else(|e| e.into_inner());
+        *gen = Generation {
+            id: id + 1,
+            dict: Arc::new(dict),
+        };
+        Ok(())
+    }
+
+    fn read<R, F: FnOnce(&Generation) -> R>(&self, f: F) -> R {
+        let gen = self.current.read().unwrap_or_else(|e| e.into_inner());
+        f(&gen)
+    }
+}
+
+fn invalid_index(index: usize) -> SudachiError {
+    SudachiError::InvalidRange(index, index + 1)
+        .with_context("user dictionary with such index does not exist")
+}
@@ -37,9 +37,13 @@ pub mod grammar;
 pub mod header;
 pub mod lexicon;
 pub mod lexicon_set;
+pub mod live_dictionary;
+pub mod mutable_lexicon;
+pub mod oov_reading;
 pub mod read;
 pub mod storage;
 pub mod subset;
+pub mod verify;
 pub mod word_id;
 
 const DEFAULT_CHAR_DEF_BYTES: &[u8] = include_bytes!("../../../resources/char.def");
@@ -150,7 +154,7 @@ impl<'a> DictionaryLoader<'a> {
             None
         };
 
-        let lexicon = Lexicon::parse(dictionary_bytes, offset, header.has_synonym_group_ids())?;
+        let lexicon = Lexicon::parse_with_header(dictionary_bytes, offset, &header)?;
 
         Ok(DictionaryLoader {
             header,
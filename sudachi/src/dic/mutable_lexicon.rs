@@ -0,0 +1,494 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! User lexicon which can be modified after the dictionary was loaded
+
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::analysis::stateless_tokenizer::DictionaryAccess;
+use crate::dic::lexicon::word_infos::{WordInfo, WordInfoData};
+use crate::dic::lexicon::LexiconEntry;
+use crate::dic::word_id::WordId;
+use crate::error::{SudachiError, SudachiResult};
+
+/// Word which can be added to a [`MutableUserLexicon`]
+#[derive(Clone, Debug)]
+pub struct UserWord {
+    /// Lookup key, must be in the form produced by input text plugins
+    pub surface: String,
+    /// Part of speech id from the grammar of the dictionary
+    pub pos_id: u16,
+    pub left_id: i16,
+    pub right_id: i16,
+    pub cost: i16,
+    /// Headword, surface is used if None
+    pub headword: Option<String>,
+    /// Reading form, headword is used if None
+    pub reading_form: Option<String>,
+    /// Normalized form, headword is used if None
+    pub normalized_form: Option<String>,
+    /// Dictionary form, headword is used if None
+    pub dictionary_form: Option<String>,
+    pub a_unit_split: Vec<WordId>,
+    pub b_unit_split: Vec<WordId>,
+    pub synonym_group_ids: Vec<u32>,
+}
+
+impl UserWord {
+    pub fn new<S: Into<String>>(
+        surface: S,
+        pos_id: u16,
+        left_id: i16,
+        right_id: i16,
+        cost: i16,
+    ) -> UserWord {
+        UserWord {
+            surface: surface.into(),
+            pos_id,
+            left_id,
+            right_id,
+            cost,
+            headword: None,
+            reading_form: None,
+            normalized_form: None,
+            dictionary_form: None,
+            a_unit_split: Vec::new(),
+            b_unit_split: Vec::new(),
+            synonym_group_ids: Vec::new(),
+        }
+    }
+
+    pub fn with_headword<S: Into<String>>(mut self, headword: S) -> Self {
+        self.headword = Some(headword.into());
+        self
+    }
+
+    pub fn with_reading_form<S: Into<String>>(mut self, reading_form: S) -> Self {
+        self.reading_form = Some(reading_form.into());
+        self
+    }
+
+    pub fn with_normalized_form<S: Into<String>>(mut self, normalized_form: S) -> Self {
+        self.normalized_form = Some(normalized_form.into());
+        self
+    }
+
+    pub fn with_dictionary_form<S: Into<String>>(mut self, dictionary_form: S) -> Self {
+        self.dictionary_form = Some(dictionary_form.into());
+        self
+    }
+
+    pub fn with_splits(mut self, a_unit_split: Vec<WordId>, b_unit_split: Vec<WordId>) -> Self {
+        self.a_unit_split = a_unit_split;
+        self.b_unit_split = b_unit_split;
+        self
+    }
+
+    pub fn with_synonym_group_ids(mut self, ids: Vec<u32>) -> Self {
+        self.synonym_group_ids = ids;
+        self
+    }
+}
+
+struct Entry {
+    params: (i16, i16, i16),
+    info: WordInfoData,
+    /// Id of this lexicon when the word was added, splits refer to this lexicon with it
+    dic_id: u8,
+}
+
+/// Part of the dictionary which words of the lexicon can refer to
+#[derive(PartialEq, Eq)]
+struct Layout {
+    num_pos: usize,
+    num_system_pos: usize,
+    /// Number of words in each lexicon of the dictionary, indexed by dictionary id
+    lexicon_sizes: Vec<u32>,
+}
+
+impl Layout {
+    fn new<D: DictionaryAccess>(dict: &D) -> Layout {
+        Layout {
+            num_pos: dict.grammar().pos_list.len(),
+            num_system_pos: dict.lexicon().num_system_pos(),
+            lexicon_sizes: dict.lexicon().lexicon_sizes().collect(),
+        }
+    }
+
+    /// This lexicon uses the next dictionary id after binary dictionaries
+    fn dic_id(&self) -> u8 {
+        self.lexicon_sizes.len() as u8
+    }
+}
+
+#[derive(Default)]
+struct TrieNode {
+    /// (label, node index), sorted by label
+    children: Vec<(u8, u32)>,
+    words: Vec<u32>,
+}
+
+impl TrieNode {
+    fn child(&self, label: u8) -> Option<u32> {
+        self.children
+            .binary_search_by_key(&label, |(l, _)| *l)
+            .ok()
+            .map(|i| self.children[i].1)
+    }
+}
+
+struct Inner {
+    nodes: Vec<TrieNode>,
+    /// Removed words keep their data, so word ids are never reused
+    /// and analysis results which were created before removal stay valid
+    entries: Vec<Entry>,
+    /// Node index for each word, None if the word was removed
+    locations: Vec<Option<u32>>,
+    num_words: usize,
+    /// Dictionary the lexicon was attached to last
+    layout: Layout,
+}
+
+impl Inner {
+    fn new(layout: Layout) -> Inner {
+        Inner {
+            nodes: vec![TrieNode::default()],
+            entries: Vec::new(),
+            locations: Vec::new(),
+            num_words: 0,
+            layout,
+        }
+    }
+
+    fn find(&self, key: &[u8]) -> Option<u32> {
+        let mut node = 0;
+        for &b in key {
+            node = self.nodes[node as usize].child(b)?;
+        }
+        Some(node)
+    }
+
+    fn find_or_insert(&mut self, key: &[u8]) -> u32 {
+        let mut node = 0u32;
+        for &b in key {
+            let next_idx = self.nodes.len() as u32;
+            let children = &mut self.nodes[node as usize].children;
+            node = match children.binary_search_by_key(&b, |(l, _)| *l) {
+                Ok(i) => children[i].1,
+                Err(i) => {
+                    children.insert(i, (b, next_idx));
+                    self.nodes.push(TrieNode::default());
+                    next_idx
+                }
+            };
+        }
+        node
+    }
+
+    fn remove(&mut self, word_id: u32) -> bool {
+        let node = match self
+            .locations
+            .get_mut(word_id as usize)
+            .and_then(Option::take)
+        {
+            Some(n) => n,
+            None => return false,
+        };
+        self.nodes[node as usize].words.retain(|w| *w != word_id);
+        self.num_words -= 1;
+        true
+    }
+
+    fn remove_all(&mut self) {
+        self.nodes.truncate(1);
+        self.nodes[0] = TrieNode::default();
+        self.locations.iter_mut().for_each(|l| *l = None);
+        self.num_words = 0;
+    }
+
+    /// Split word must exist in the dictionary or in this lexicon (with id `own_id`)
+    fn is_valid_split(&self, w: WordId, own_id: u8) -> bool {
+        if w.is_special() || w.is_oov() {
+            return false;
+        }
+        if w.dic() == own_id {
+            return (w.word() as usize) < self.entries.len();
+        }
+        match self.layout.lexicon_sizes.get(w.dic() as usize) {
+            Some(&size) => w.word() < size,
+            None => false,
+        }
+    }
+
+    /// Returns the word info where splits which refer to this lexicon use `dic_id`
+    fn word_info(&self, word_id: u32, dic_id: u8) -> SudachiResult<WordInfo> {
+        let entry = self
+            .entries
+            .get(word_id as usize)
+            .ok_or_else(|| SudachiError::InvalidUserWord(format!("unknown word id {}", word_id)))?;
+        let mut info = entry.info.clone();
+        if entry.dic_id != dic_id {
+            let splits = info
+                .a_unit_split
+                .iter_mut()
+                .chain(info.b_unit_split.iter_mut());
+            for w in splits.filter(|w| w.dic() == entry.dic_id) {
+                *w = WordId::new(dic_id, w.word());
+            }
+        }
+        Ok(info.into())
+    }
+}
+
+/// User lexicon which is stored in memory and can be modified at runtime.
+///
+/// Words can be added and removed while the lexicon is attached to a dictionary
+/// (see [`JapaneseDictionary::attach_mutable_lexicon`](crate::dic::dictionary::JapaneseDictionary::attach_mutable_lexicon))
+/// and used by tokenizers, changes affect all analyses which start after them.
+///
+/// Words are looked up after all binary user dictionaries, the lexicon uses
+/// the next dictionary id after them.
+/// Part of speech and connection ids must be valid for the grammar of the dictionary
+/// the lexicon is attached to.
+/// Word ids in splits are global and must refer to words of that dictionary,
+/// splits which refer to words of this lexicon follow the changes of its dictionary id.
+pub struct MutableUserLexicon {
+    inner: RwLock<Inner>,
+    num_left: i16,
+    num_right: i16,
+}
+
+impl MutableUserLexicon {
+    /// Creates an empty lexicon for the dictionary.
+    /// Added words are validated against the dictionary until the lexicon
+    /// is attached to another one.
+    pub fn new<D: DictionaryAccess>(dict: &D) -> MutableUserLexicon {
+        let conn = dict.grammar().conn_matrix();
+        MutableUserLexicon {
+            inner: RwLock::new(Inner::new(Layout::new(dict))),
+            num_left: conn.num_left() as i16,
+            num_right: conn.num_right() as i16,
+        }
+    }
+
+    /// Updates the lexicon for the dictionary it is attached to.
+    ///
+    /// Adding or removing binary user dictionaries changes their ids,
+    /// ids of their part of speech and the id of this lexicon.
+    /// Words which refer to binary user dictionaries or their part of speech
+    /// can't be kept after such changes, an error is returned for them
+    /// and the lexicon is not changed.
+    pub(crate) fn bind<D: DictionaryAccess>(&self, dict: &D) -> SudachiResult<()> {
+        let layout = Layout::new(dict);
+        let mut inner = self.write();
+        if inner.layout == layout {
+            return Ok(());
+        }
+        let ids_changed = inner.layout.lexicon_sizes.len() != layout.lexicon_sizes.len()
+            || inner.layout.num_pos != layout.num_pos;
+        let old = std::mem::replace(&mut inner.layout, layout);
+        let invalid = inner
+            .entries
+            .iter()
+            .zip(inner.locations.iter())
+            .filter(|(_, location)| location.is_some())
+            .map(|(e, _)| e)
+            .find(|e| {
+                let info = &e.info;
+                let mut splits = info.a_unit_split.iter().chain(info.b_unit_split.iter());
+                let pos_id = info.pos_id as usize;
+                let refers_user = pos_id >= inner.layout.num_system_pos
+                    || splits.clone().any(|w| w.dic() != 0 && w.dic() != e.dic_id);
+                (ids_changed && refers_user)
+                    || pos_id >= inner.layout.num_pos
+                    || !splits.all(|w| inner.is_valid_split(*w, e.dic_id))
+            })
+            .map(|e| e.info.surface.clone());
+        if let Some(surface) = invalid {
+            inner.layout = old;
+            return Err(SudachiError::InvalidUserWord(format!(
+                "word {} refers to user dictionaries which were changed",
+                surface
+            )));
+        }
+        Ok(())
+    }
+
+    /// Adds the word and returns its id in this lexicon
+    pub fn add_word(&self, word: UserWord) -> SudachiResult<u32> {
+        let mut inner = self.write();
+        self.validate(&inner, &word)?;
+        let headword = word.headword.unwrap_or_else(|| word.surface.clone());
+        let info = WordInfoData {
+            head_word_length: word.surface.len() as u16,
+            pos_id: word.pos_id,
+            normalized_form: word.normalized_form.unwrap_or_default(),
+            dictionary_form_word_id: -1,
+            dictionary_form: word.dictionary_form.unwrap_or_default(),
+            reading_form: word.reading_form.unwrap_or_default(),
+            a_unit_split: word.a_unit_split,
+            b_unit_split: word.b_unit_split,
+            word_structure: Vec::new(),
+            synonym_group_ids: word.synonym_group_ids,
+            surface: headword,
+        };
+
+        let word_id = inner.entries.len() as u32;
+        if word_id > WordId::MAX_WORD {
+            return Err(SudachiError::InvalidUserWord(format!(
+                "lexicon can't have more than {} words",
+                WordId::MAX_WORD
+            )));
+        }
+        let dic_id = inner.layout.dic_id();
+        let node = inner.find_or_insert(word.surface.as_bytes());
+        inner.nodes[node as usize].words.push(word_id);
+        inner.entries.push(Entry {
+            params: (word.left_id, word.right_id, word.cost),
+            info,
+            dic_id,
+        });
+        inner.locations.push(Some(node));
+        inner.num_words += 1;
+        Ok(word_id)
+    }
+
+    /// Removes the word with the id, returned by [`MutableUserLexicon::add_word`].
+    /// Returns false if there was no such word.
+    pub fn remove_word(&self, word_id: u32) -> bool {
+        self.write().remove(word_id)
+    }
+
+    /// Removes all words with the surface, returns the number of removed words
+    pub fn remove_surface(&self, surface: &str) -> usize {
+        let mut inner = self.write();
+        let words = match inner.find(surface.as_bytes()) {
+            Some(node) => inner.nodes[node as usize].words.clone(),
+            None => return 0,
+        };
+        words.into_iter().filter(|w| inner.remove(*w)).count()
+    }
+
+    /// Ids of all words with the surface
+    pub fn find_surface(&self, surface: &str) -> Vec<u32> {
+        let inner = self.read();
+        inner
+            .find(surface.as_bytes())
+            .map(|node| inner.nodes[node as usize].words.clone())
+            .unwrap_or_default()
+    }
+
+    /// Removes all words.
+    /// Like [`MutableUserLexicon::remove_word`], word ids are not reused,
+    /// so analyses which are running concurrently stay valid.
+    pub fn clear(&self) {
+        self.write().remove_all();
+    }
+
+    /// Number of words which were not removed
+    pub fn len(&self) -> usize {
+        self.read().num_words
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns all words which are prefixes of `input[offset..]`
+    /// with the dictionary id `dic_id`
+    pub fn lookup(&self, input: &[u8], offset: usize, dic_id: u8) -> Vec<LexiconEntry> {
+        let inner = self.read();
+        let mut result = Vec::new();
+        let mut node = &inner.nodes[0];
+        for (i, &b) in input.iter().enumerate().skip(offset) {
+            node = match node.child(b) {
+                Some(n) => &inner.nodes[n as usize],
+                None => break,
+            };
+            for &w in node.words.iter() {
+                result.push(LexiconEntry::new(WordId::new(dic_id, w), i + 1));
+            }
+        }
+        result
+    }
+
+    pub fn get_word_info(&self, word_id: u32) -> SudachiResult<WordInfo> {
+        let inner = self.read();
+        inner.word_info(word_id, inner.layout.dic_id())
+    }
+
+    /// Returns the word info where splits which refer to this lexicon use the id
+    /// of the dictionary of `word_id`
+    pub(crate) fn get_word_info_by_id(&self, word_id: WordId) -> SudachiResult<WordInfo> {
+        self.read().word_info(word_id.word(), word_id.dic())
+    }
+
+    /// Returns (left_id, right_id, cost) of the word
+    pub fn get_word_param(&self, word_id: u32) -> (i16, i16, i16) {
+        self.read()
+            .entries
+            .get(word_id as usize)
+            .map_or((-1, -1, i16::MAX), |e| e.params)
+    }
+
+    fn validate(&self, inner: &Inner, word: &UserWord) -> SudachiResult<()> {
+        if word.surface.is_empty() {
+            return Err(SudachiError::InvalidUserWord("surface is empty".to_owned()));
+        }
+        if word.surface.len() > u16::MAX as usize {
+            return Err(SudachiError::InvalidUserWord(format!(
+                "surface is too long: {} bytes",
+                word.surface.len()
+            )));
+        }
+        if word.pos_id as usize >= inner.layout.num_pos {
+            return Err(SudachiError::InvalidPartOfSpeech(format!(
+                "pos id {} is not defined",
+                word.pos_id
+            )));
+        }
+        if word.left_id < 0 || word.left_id >= self.num_left {
+            return Err(SudachiError::InvalidUserWord(format!(
+                "left id {} is out of range 0..{}",
+                word.left_id, self.num_left
+            )));
+        }
+        if word.right_id < 0 || word.right_id >= self.num_right {
+            return Err(SudachiError::InvalidUserWord(format!(
+                "right id {} is out of range 0..{}",
+                word.right_id, self.num_right
+            )));
+        }
+        let own_id = inner.layout.dic_id();
+        let mut splits = word.a_unit_split.iter().chain(word.b_unit_split.iter());
+        if let Some(w) = splits.find(|w| !inner.is_valid_split(**w, own_id)) {
+            return Err(SudachiError::InvalidUserWord(format!(
+                "split contains invalid word id {}",
+                w
+            )));
+        }
+        Ok(())
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Inner> {
+        // lexicon data is always consistent, so poisoning can be ignored
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Inner> {
+        self.inner.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
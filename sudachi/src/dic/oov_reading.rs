@@ -0,0 +1,480 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Reading estimation for out-of-vocabulary words.
+//!
+//! OOV words do not have entries in the dictionary, so their reading is guessed from
+//! the surface: kana are read as they are, alphabetic words letter by letter or from
+//! a loanword table and numbers as they are spoken.
+//! Surfaces with any other characters do not get a reading.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::prelude::*;
+use crate::util::fxhash::FxBuildHasher;
+
+/// How alphabetic words are read
+#[derive(Deserialize, Clone, Copy, Debug, Eq, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AlphabetReading {
+    /// Letter by letter: "ABC" is read as エービーシー
+    #[default]
+    Letters,
+    /// Words from the loanword table, other words letter by letter
+    Loanwords,
+    /// Alphabetic words do not get a reading
+    None,
+}
+
+/// Settings of OOV reading estimation, `oovReading` key of the config file
+#[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OovReadingConfig {
+    #[serde(default)]
+    pub alphabet: AlphabetReading,
+    /// Loanword table, each line contains a word and its reading separated by white spaces
+    #[serde(default)]
+    pub loanwords: Option<PathBuf>,
+    /// Whether numbers get a spoken reading
+    #[serde(default = "default_numbers")]
+    pub numbers: bool,
+}
+
+fn default_numbers() -> bool {
+    true
+}
+
+impl Default for OovReadingConfig {
+    fn default() -> Self {
+        Self {
+            alphabet: AlphabetReading::default(),
+            loanwords: None,
+            numbers: default_numbers(),
+        }
+    }
+}
+
+/// Estimates readings (in katakana) of OOV words
+#[derive(Debug, Clone, Default)]
+pub struct OovReading {
+    alphabet: AlphabetReading,
+    numbers: bool,
+    loanwords: HashMap<String, String, FxBuildHasher>,
+}
+
+const LETTERS: [&str; 26] = [
+    "エー",
+    "ビー",
+    "シー",
+    "ディー",
+    "イー",
+    "エフ",
+    "ジー",
+    "エイチ",
+    "アイ",
+    "ジェー",
+    "ケー",
+    "エル",
+    "エム",
+    "エヌ",
+    "オー",
+    "ピー",
+    "キュー",
+    "アール",
+    "エス",
+    "ティー",
+    "ユー",
+    "ブイ",
+    "ダブリュー",
+    "エックス",
+    "ワイ",
+    "ゼット",
+];
+
+const DIGITS: [&str; 10] = [
+    "ゼロ",
+    "イチ",
+    "ニ",
+    "サン",
+    "ヨン",
+    "ゴ",
+    "ロク",
+    "ナナ",
+    "ハチ",
+    "キュウ",
+];
+
+/// Units of 4-digit groups: 万, 億 and 兆
+const GROUP_UNITS: [&str; 4] = ["", "マン", "オク", "チョウ"];
+
+/// Numbers with more digits are read digit by digit
+const MAX_NUMBER_DIGITS: usize = 16;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CharClass {
+    Kana,
+    Alphabet,
+    Number,
+}
+
+impl OovReading {
+    pub fn new(config: &OovReadingConfig) -> Self {
+        Self {
+            alphabet: config.alphabet,
+            numbers: config.numbers,
+            loanwords: HashMap::default(),
+        }
+    }
+
+    /// Creates the estimator and reads the loanword table, resolving its path with the config
+    pub fn from_config(settings: &OovReadingConfig, config: &Config) -> SudachiResult<Self> {
+        let mut reading = Self::new(settings);
+        if let Some(path) = settings.loanwords.as_ref() {
+            let path = config.complete_path(path)?;
+            let reader = BufReader::new(fs::File::open(&path)?);
+            reading
+                .read_loanwords(reader)
+                .map_err(|e| e.with_context(path.to_string_lossy()))?;
+        }
+        Ok(reading)
+    }
+
+    /// Reads the loanword table
+    ///
+    /// Each line contains a word and its reading separated by white spaces.
+    /// Words are matched ignoring case.
+    /// Empty or line starts with "#" will be ignored
+    pub fn read_loanwords<T: BufRead>(&mut self, reader: T) -> SudachiResult<()> {
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let cols: Vec<_> = line.split_whitespace().collect();
+            if cols.len() != 2 {
+                return Err(SudachiError::InvalidDataFormat(i, line.to_owned()));
+            }
+            self.add_loanword(cols[0], cols[1]);
+        }
+        Ok(())
+    }
+
+    pub fn add_loanword(&mut self, word: &str, reading: &str) {
+        self.loanwords
+            .insert(normalize_alphabet(word), to_katakana(reading));
+    }
+
+    /// Returns the estimated reading of the surface in katakana,
+    /// or None if some part of the surface can't be read
+    pub fn estimate(&self, surface: &str) -> Option<String> {
+        let chars: Vec<char> = surface.chars().collect();
+        if chars.is_empty() {
+            return None;
+        }
+        let mut result = String::with_capacity(surface.len() * 2);
+        let mut start = 0;
+        while start < chars.len() {
+            let class = char_class(&chars, start)?;
+            let mut end = start + 1;
+            while end < chars.len() && char_class(&chars, end) == Some(class) {
+                end += 1;
+            }
+            let part: String = chars[start..end].iter().collect();
+            match class {
+                CharClass::Kana => result.push_str(&to_katakana(&part)),
+                CharClass::Alphabet => self.read_alphabet(&part, &mut result)?,
+                CharClass::Number => self.read_number(&part, &mut result)?,
+            }
+            start = end;
+        }
+        Some(result)
+    }
+
+    fn read_alphabet(&self, word: &str, result: &mut String) -> Option<()> {
+        let word = match self.alphabet {
+            AlphabetReading::None => return None,
+            AlphabetReading::Letters => normalize_alphabet(word),
+            AlphabetReading::Loanwords => {
+                let word = normalize_alphabet(word);
+                if let Some(reading) = self.loanwords.get(&word) {
+                    result.push_str(reading);
+                    return Some(());
+                }
+                word
+            }
+        };
+        for c in word.bytes() {
+            result.push_str(LETTERS[(c - b'a') as usize]);
+        }
+        Some(())
+    }
+
+    fn read_number(&self, number: &str, result: &mut String) -> Option<()> {
+        if !self.numbers {
+            return None;
+        }
+        let number: String = number.chars().filter_map(ascii_digit).collect();
+        let (integer, fraction) = match number.split_once('.') {
+            Some((i, f)) => (i, Some(f)),
+            None => (number.as_str(), None),
+        };
+        let integer = match integer.split(',').collect::<Vec<_>>().as_slice() {
+            [single] => single.to_string(),
+            [first, rest @ ..] if first.len() <= 3 && rest.iter().all(|g| g.len() == 3) => {
+                integer.replace(',', "")
+            }
+            _ => return None,
+        };
+        if integer.len() > MAX_NUMBER_DIGITS || (integer.len() > 1 && integer.starts_with('0')) {
+            read_digits(&integer, result);
+        } else {
+            read_integer(&integer, result);
+        }
+        if let Some(fraction) = fraction {
+            if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            result.push_str("テン");
+            read_digits(fraction, result);
+        }
+        Some(())
+    }
+}
+
+fn char_class(chars: &[char], idx: usize) -> Option<CharClass> {
+    let c = chars[idx];
+    match c {
+        '\u{3041}'..='\u{3096}' | '\u{309D}'..='\u{309E}' => Some(CharClass::Kana),
+        '\u{30A1}'..='\u{30FA}' | '\u{30FC}'..='\u{30FE}' => Some(CharClass::Kana),
+        'a'..='z' | 'A'..='Z' | 'ａ'..='ｚ' | 'Ａ'..='Ｚ' => Some(CharClass::Alphabet),
+        '0'..='9' | '０'..='９' => Some(CharClass::Number),
+        // separators are a part of a number only between digits
+        '.' | ',' | '．' | '，' => {
+            let is_digit = |i: usize| {
+                chars
+                    .get(i)
+                    .is_some_and(|c| c.is_ascii_digit() || ('０'..='９').contains(c))
+            };
+            if idx > 0 && is_digit(idx - 1) && is_digit(idx + 1) {
+                Some(CharClass::Number)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Converts digits and number separators to ASCII
+fn ascii_digit(c: char) -> Option<char> {
+    match c {
+        '0'..='9' | '.' | ',' => Some(c),
+        '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32),
+        '．' => Some('.'),
+        '，' => Some(','),
+        _ => None,
+    }
+}
+
+/// Converts full-width letters to ASCII and lowercases them
+fn normalize_alphabet(word: &str) -> String {
+    word.chars()
+        .map(|c| match c {
+            'ａ'..='ｚ' | 'Ａ'..='Ｚ' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn to_katakana(data: &str) -> String {
+    data.chars()
+        .map(|c| match c {
+            '\u{3041}'..='\u{3096}' | '\u{309D}'..='\u{309E}' => {
+                char::from_u32(c as u32 + 0x60).unwrap_or(c)
+            }
+            _ => c,
+        })
+        .collect()
+}
+
+fn read_digits(digits: &str, result: &mut String) {
+    for d in digits.bytes() {
+        result.push_str(DIGITS[(d - b'0') as usize]);
+    }
+}
+
+/// Reads an integer without leading zeros, up to `MAX_NUMBER_DIGITS` digits
+fn read_integer(integer: &str, result: &mut String) {
+    let value: u64 = integer.parse().unwrap_or(0);
+    if value == 0 {
+        result.push_str(DIGITS[0]);
+        return;
+    }
+    for (unit, unit_reading) in GROUP_UNITS.iter().enumerate().rev() {
+        let group = (value / 10_000u64.pow(unit as u32) % 10_000) as usize;
+        if group == 0 {
+            continue;
+        }
+        let mut reading = read_group(group);
+        if *unit_reading == "チョウ" {
+            // sound changes before 兆: イッチョウ, ハッチョウ, ジュッチョウ
+            for (from, to) in [("イチ", "イッ"), ("ハチ", "ハッ"), ("ジュウ", "ジュッ")]
+            {
+                if let Some(stem) = reading.strip_suffix(from) {
+                    reading = format!("{}{}", stem, to);
+                    break;
+                }
+            }
+        }
+        result.push_str(&reading);
+        result.push_str(unit_reading);
+    }
+}
+
+/// Reads a number from 1 to 9999
+fn read_group(group: usize) -> String {
+    let mut result = String::new();
+    let (thousands, hundreds, tens, ones) =
+        (group / 1000, group / 100 % 10, group / 10 % 10, group % 10);
+    match thousands {
+        0 => {}
+        1 => result.push_str("セン"),
+        3 => result.push_str("サンゼン"),
+        8 => result.push_str("ハッセン"),
+        d => {
+            result.push_str(DIGITS[d]);
+            result.push_str("セン");
+        }
+    }
+    match hundreds {
+        0 => {}
+        1 => result.push_str("ヒャク"),
+        3 => result.push_str("サンビャク"),
+        6 => result.push_str("ロッピャク"),
+        8 => result.push_str("ハッピャク"),
+        d => {
+            result.push_str(DIGITS[d]);
+            result.push_str("ヒャク");
+        }
+    }
+    match tens {
+        0 => {}
+        1 => result.push_str("ジュウ"),
+        d => {
+            result.push_str(DIGITS[d]);
+            result.push_str("ジュウ");
+        }
+    }
+    if ones != 0 {
+        result.push_str(DIGITS[ones]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn estimate(surface: &str) -> Option<String> {
+        OovReading::new(&OovReadingConfig::default()).estimate(surface)
+    }
+
+    #[test]
+    fn kana() {
+        assert_eq!(estimate("すだち").as_deref(), Some("スダチ"));
+        assert_eq!(estimate("スダチー").as_deref(), Some("スダチー"));
+        assert_eq!(estimate("すゞカタ").as_deref(), Some("スヾカタ"));
+        assert_eq!(estimate("酢橘"), None);
+        assert_eq!(estimate(""), None);
+    }
+
+    #[test]
+    fn alphabet() {
+        assert_eq!(estimate("ABC").as_deref(), Some("エービーシー"));
+        assert_eq!(estimate("ｘｙ").as_deref(), Some("エックスワイ"));
+
+        let config = OovReadingConfig {
+            alphabet: AlphabetReading::Loanwords,
+            ..Default::default()
+        };
+        let mut reading = OovReading::new(&config);
+        reading
+            .read_loanwords("# comment\nsudachi すだち\n\nPython パイソン\n".as_bytes())
+            .unwrap();
+        assert_eq!(reading.estimate("Python").as_deref(), Some("パイソン"));
+        assert_eq!(reading.estimate("SUDACHI").as_deref(), Some("スダチ"));
+        assert_eq!(reading.estimate("Go").as_deref(), Some("ジーオー"));
+        assert!(reading.read_loanwords("a b c\n".as_bytes()).is_err());
+
+        let config = OovReadingConfig {
+            alphabet: AlphabetReading::None,
+            ..Default::default()
+        };
+        assert_eq!(OovReading::new(&config).estimate("ABC"), None);
+    }
+
+    #[test]
+    fn numbers() {
+        let cases = [
+            ("0", "ゼロ"),
+            ("7", "ナナ"),
+            ("10", "ジュウ"),
+            ("123", "ヒャクニジュウサン"),
+            ("300", "サンビャク"),
+            ("680", "ロッピャクハチジュウ"),
+            ("1000", "セン"),
+            ("8000", "ハッセン"),
+            ("10000", "イチマン"),
+            ("3,000", "サンゼン"),
+            ("２０２４", "ニセンニジュウヨン"),
+            ("100000000", "イチオク"),
+            ("1000000000000", "イッチョウ"),
+            ("10000000000000", "ジュッチョウ"),
+            ("007", "ゼロゼロナナ"),
+            ("3.14", "サンテンイチヨン"),
+        ];
+        for (number, reading) in cases {
+            assert_eq!(estimate(number).as_deref(), Some(reading), "{}", number);
+        }
+        assert_eq!(estimate("1,00"), None);
+        assert_eq!(estimate("1."), None);
+        assert_eq!(estimate("1.2.3"), None);
+
+        let config = OovReadingConfig {
+            numbers: false,
+            ..Default::default()
+        };
+        assert_eq!(OovReading::new(&config).estimate("123"), None);
+    }
+
+    #[test]
+    fn mixed() {
+        assert_eq!(estimate("A4").as_deref(), Some("エーヨン"));
+        assert_eq!(
+            estimate("iPhoneケース").unwrap(),
+            "アイピーエイチオーエヌイーケース"
+        );
+        assert_eq!(estimate("A-4"), None);
+    }
+}
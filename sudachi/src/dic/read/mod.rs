@@ -21,7 +21,7 @@ use nom::number::complete::{le_u32, le_u8};
 use nom::Parser;
 
 use crate::dic::word_id::WordId;
-use crate::error::SudachiNomResult;
+use crate::error::{SudachiNomError, SudachiNomResult};
 
 pub fn u32_array_parser(input: &[u8]) -> SudachiNomResult<&[u8], Vec<u32>> {
     let (rest, length) = le_u8(input)?;
@@ -30,7 +30,7 @@ pub fn u32_array_parser(input: &[u8]) -> SudachiNomResult<&[u8], Vec<u32>> {
 
 pub fn u32_wid_array_parser(input: &[u8]) -> SudachiNomResult<&[u8], Vec<WordId>> {
     let (rest, length) = le_u8(input)?;
-    nom::multi::count(le_u32.map(WordId::from_raw), length as usize)(rest)
+    nom::multi::count(le_u32.map(WordId::from_storage), length as usize)(rest)
 }
 
 pub fn skip_wid_array(input: &[u8]) -> SudachiNomResult<&[u8], Vec<WordId>> {
@@ -50,3 +50,57 @@ pub fn skip_u32_array(input: &[u8]) -> SudachiNomResult<&[u8], Vec<u32>> {
 pub fn u32_parser(input: &[u8]) -> SudachiNomResult<&[u8], u32> {
     le_u32(input)
 }
+
+/// Parses an unsigned LEB128 number, which is used in compressed word infos.
+/// Numbers which do not fit into u32 are rejected.
+pub fn varint_parser(input: &[u8]) -> SudachiNomResult<&[u8], u32> {
+    let mut result: u32 = 0;
+    for (i, b) in input.iter().take(5).enumerate() {
+        // the fifth byte has only 4 bits of the value
+        if i == 4 && *b > 0x0f {
+            break;
+        }
+        result |= ((b & 0x7f) as u32) << (7 * i);
+        if b & 0x80 == 0 {
+            return Ok((&input[i + 1..], result));
+        }
+    }
+    Err(nom::Err::Failure(SudachiNomError::Nom(
+        input,
+        nom::error::ErrorKind::TooLarge,
+    )))
+}
+
+/// Parses an unsigned LEB128 number which must fit into u16
+pub fn varint_u16_parser(input: &[u8]) -> SudachiNomResult<&[u8], u16> {
+    let (rest, v) = varint_parser(input)?;
+    match u16::try_from(v) {
+        Ok(v) => Ok((rest, v)),
+        Err(_) => Err(nom::Err::Failure(SudachiNomError::Nom(
+            input,
+            nom::error::ErrorKind::TooLarge,
+        ))),
+    }
+}
+
+pub fn varint_array_parser(input: &[u8]) -> SudachiNomResult<&[u8], Vec<u32>> {
+    let (rest, length) = varint_parser(input)?;
+    nom::multi::count(varint_parser, length as usize)(rest)
+}
+
+pub fn varint_wid_array_parser(input: &[u8]) -> SudachiNomResult<&[u8], Vec<WordId>> {
+    let (rest, length) = varint_parser(input)?;
+    nom::multi::count(varint_parser.map(WordId::from_storage), length as usize)(rest)
+}
+
+pub fn skip_varint_wid_array(input: &[u8]) -> SudachiNomResult<&[u8], Vec<WordId>> {
+    skip_varint_array(input).map(|(rest, _)| (rest, Vec::new()))
+}
+
+pub fn skip_varint_array(input: &[u8]) -> SudachiNomResult<&[u8], Vec<u32>> {
+    let (mut rest, length) = varint_parser(input)?;
+    for _ in 0..length {
+        rest = varint_parser(rest)?.0;
+    }
+    Ok((rest, Vec::new()))
+}
@@ -16,9 +16,13 @@
 
 use crate::dic::lexicon::word_infos::WordInfoData;
 use crate::dic::read::u16str::*;
-use crate::dic::read::{skip_u32_array, skip_wid_array, u32_array_parser, u32_wid_array_parser};
+use crate::dic::read::{
+    skip_u32_array, skip_varint_array, skip_varint_wid_array, skip_wid_array, u32_array_parser,
+    u32_wid_array_parser, varint_array_parser, varint_parser, varint_u16_parser,
+    varint_wid_array_parser,
+};
 use crate::dic::subset::InfoSubset;
-use crate::error::SudachiResult;
+use crate::error::{SudachiNomError, SudachiNomResult, SudachiResult};
 use nom::number::complete::{le_i32, le_u16};
 
 pub struct WordInfoParser {
@@ -157,4 +161,104 @@ impl WordInfoParser {
         );
         Ok(self.info)
     }
+
+    /// Parses the word info in the compressed format which is used by dictionaries with
+    /// the string pool (see [`Header::has_string_pool`](crate::dic::header::Header::has_string_pool)).
+    ///
+    /// Numbers are stored as LEB128 varints and strings are offsets of
+    /// length-prefixed UTF-16 strings in the pool.
+    #[inline]
+    pub fn parse_pooled(mut self, data: &[u8], pool: &[u8]) -> SudachiResult<WordInfoData> {
+        let pooled_string = |input| -> SudachiNomResult<&[u8], String> {
+            let (rest, offset) = varint_parser(input)?;
+            match pool.get(offset as usize..) {
+                Some(s) => utf16_string_parser(s).map(|(_, v)| (rest, v)),
+                None => Err(nom::Err::Failure(SudachiNomError::OutOfBounds(
+                    "string pool".to_owned(),
+                    offset as usize,
+                    pool.len(),
+                ))),
+            }
+        };
+        let skip_string = |input| -> SudachiNomResult<&[u8], String> {
+            varint_parser(input).map(|(rest, _)| (rest, String::new()))
+        };
+        // -1 (no dictionary form) is stored as 0
+        let dic_form = |input| -> SudachiNomResult<&[u8], i32> {
+            varint_parser(input).map(|(rest, v)| (rest, v.wrapping_sub(1) as i32))
+        };
+
+        parse_field!(
+            self,
+            data,
+            surface,
+            InfoSubset::SURFACE,
+            pooled_string,
+            skip_string
+        );
+        parse_field!(
+            self,
+            data,
+            head_word_length,
+            InfoSubset::HEAD_WORD_LENGTH,
+            varint_u16_parser
+        );
+        parse_field!(self, data, pos_id, InfoSubset::POS_ID, varint_u16_parser);
+        parse_field!(
+            self,
+            data,
+            normalized_form,
+            InfoSubset::NORMALIZED_FORM,
+            pooled_string,
+            skip_string
+        );
+        parse_field!(
+            self,
+            data,
+            dictionary_form_word_id,
+            InfoSubset::DIC_FORM_WORD_ID,
+            dic_form
+        );
+        parse_field!(
+            self,
+            data,
+            reading_form,
+            InfoSubset::READING_FORM,
+            pooled_string,
+            skip_string
+        );
+        parse_field!(
+            self,
+            data,
+            a_unit_split,
+            InfoSubset::SPLIT_A,
+            varint_wid_array_parser,
+            skip_varint_wid_array
+        );
+        parse_field!(
+            self,
+            data,
+            b_unit_split,
+            InfoSubset::SPLIT_B,
+            varint_wid_array_parser,
+            skip_varint_wid_array
+        );
+        parse_field!(
+            self,
+            data,
+            word_structure,
+            InfoSubset::WORD_STRUCTURE,
+            varint_wid_array_parser,
+            skip_varint_wid_array
+        );
+        parse_field!(
+            self,
+            data,
+            synonym_group_ids,
+            InfoSubset::SYNONYM_GROUP_ID,
+            varint_array_parser,
+            skip_varint_array
+        );
+        Ok(self.info)
+    }
 }
@@ -14,6 +14,8 @@
  *  limitations under the License.
  */
 
+use std::sync::Arc;
+
 use memmap2::Mmap;
 use nom::AsBytes;
 
@@ -33,27 +35,41 @@ impl AsRef<[u8]> for Storage {
     }
 }
 
+/// Storage of system and user dictionaries.
+/// Storages are shared between generations of a reloadable dictionary.
 pub struct SudachiDicData {
     // system dictionary
-    system: Storage,
+    system: Arc<Storage>,
     // user dictionaries
-    user: Vec<Storage>,
+    user: Vec<Arc<Storage>>,
 }
 
 impl SudachiDicData {
     pub fn new(system: Storage) -> Self {
         Self {
-            system,
+            system: Arc::new(system),
             user: Vec::new(),
         }
     }
 
+    pub(crate) fn from_shared(system: Arc<Storage>, user: Vec<Arc<Storage>>) -> Self {
+        Self { system, user }
+    }
+
     pub fn add_user(&mut self, user: Storage) {
-        self.user.push(user)
+        self.user.push(Arc::new(user))
+    }
+
+    pub(crate) fn system_storage(&self) -> &Arc<Storage> {
+        &self.system
+    }
+
+    pub(crate) fn user_storages(&self) -> &[Arc<Storage>] {
+        &self.user
     }
 
     pub fn system(&self) -> &[u8] {
-        self.system.as_ref()
+        self.system.as_ref().as_ref()
     }
 
     /// # Safety
@@ -65,7 +81,7 @@ impl SudachiDicData {
     pub(crate) fn user_static_slice(&self) -> Vec<&'static [u8]> {
         let mut result = Vec::with_capacity(self.user.len());
         for u in self.user.iter() {
-            let slice: &'static [u8] = unsafe { std::mem::transmute(u.as_ref()) };
+            let slice: &'static [u8] = unsafe { std::mem::transmute(u.as_ref().as_ref()) };
             result.push(slice);
         }
         result
@@ -0,0 +1,432 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+//! Full validation of binary dictionaries.
+//!
+//! Loading a dictionary only checks that its sections fit into the data,
+//! the analysis trusts the rest of the contents and a corrupted dictionary
+//! can cause out-of-bounds memory accesses.
+//! Verification reads the whole dictionary and checks everything the analysis relies on:
+//! header version, trie structure, word id table, word parameters against the connection matrix,
+//! word info offsets, part of speech ids and references to other words.
+//! If the header contains a checksum, it is checked as well.
+//!
+//! Verification does not use the regular (unchecked) dictionary readers,
+//! so it is safe to run on untrusted data.
+
+use crate::dic::grammar::Grammar;
+use crate::dic::header::{Header, HeaderVersion};
+use crate::dic::read::word_info::WordInfoParser;
+use crate::dic::subset::InfoSubset;
+use crate::dic::word_id::WordId;
+use crate::error::{SudachiError, SudachiResult};
+
+/// Summary of a successfully verified dictionary
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub header: Header,
+    /// Number of words in the lexicon
+    pub num_words: u32,
+    /// Number of words which are present in the index
+    pub num_indexed_words: usize,
+    /// Number of part of speech entries in the grammar of the dictionary
+    pub num_pos: usize,
+    /// Size of the connection matrix (left, right), user dictionaries do not have it
+    pub matrix_size: (usize, usize),
+}
+
+/// Checksum of the dictionary contents which is stored in the [`Header`].
+///
+/// It is a 64-bit FNV-1a hash of everything after the header.
+pub fn checksum(data: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    data.iter()
+        .fold(OFFSET_BASIS, |h, b| (h ^ *b as u64).wrapping_mul(PRIME))
+}
+
+/// Verify the system dictionary
+pub fn verify_system(data: &[u8]) -> SudachiResult<VerifyReport> {
+    Verifier::new(data, None)?.verify()
+}
+
+/// Verify the user dictionary.
+/// It is checked against the system dictionary which was verified before.
+pub fn verify_user(data: &[u8], system: &VerifyReport) -> SudachiResult<VerifyReport> {
+    if !matches!(system.header.version, HeaderVersion::SystemDict(_)) {
+        return invalid("user dictionary must be verified against a system dictionary".to_owned());
+    }
+    Verifier::new(data, Some(system))?.verify()
+}
+
+fn invalid<T>(message: String) -> SudachiResult<T> {
+    Err(SudachiError::InvalidDictionary(message))
+}
+
+fn section<'a>(
+    data: &'a [u8],
+    start: usize,
+    count: usize,
+    elem_size: usize,
+    name: &str,
+) -> SudachiResult<&'a [u8]> {
+    match count
+        .checked_mul(elem_size)
+        .and_then(|size| start.checked_add(size))
+    {
+        Some(end) if end <= data.len() => Ok(&data[start..end]),
+        _ => invalid(format!(
+            "{} ({} x {} bytes at {}) is out of bounds of {} bytes",
+            name,
+            count,
+            elem_size,
+            start,
+            data.len()
+        )),
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize, name: &str) -> SudachiResult<u32> {
+    let bytes = section(data, offset, 1, 4, name)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn u32_at(data: &[u8], index: usize) -> u32 {
+    let b = &data[index * 4..index * 4 + 4];
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn i16_at(data: &[u8], index: usize) -> i16 {
+    i16::from_le_bytes([data[index * 2], data[index * 2 + 1]])
+}
+
+// Decoding of double array units, same as in lexicon::trie
+fn has_leaf(unit: u32) -> bool {
+    ((unit >> 8) & 1) == 1
+}
+
+fn trie_value(unit: u32) -> u32 {
+    unit & ((1 << 31) - 1)
+}
+
+fn label(unit: u32) -> u32 {
+    unit & ((1 << 31) | 0xFF)
+}
+
+fn trie_offset(unit: u32) -> usize {
+    ((unit >> 10) << ((unit & (1 << 9)) >> 6)) as usize
+}
+
+struct Verifier<'a> {
+    data: &'a [u8],
+    header: Header,
+    system: Option<&'a VerifyReport>,
+}
+
+impl<'a> Verifier<'a> {
+    fn new(data: &'a [u8], system: Option<&'a VerifyReport>) -> SudachiResult<Verifier<'a>> {
+        let header = Header::parse(data)?;
+        match (&header.version, system) {
+            (HeaderVersion::SystemDict(_), None) | (HeaderVersion::UserDict(_), Some(_)) => {}
+            (HeaderVersion::SystemDict(_), Some(_)) => {
+                return invalid("expected user dictionary, got system dictionary".to_owned())
+            }
+            (HeaderVersion::UserDict(_), None) => {
+                return invalid(
+                    "system dictionary is required to verify user dictionary".to_owned(),
+                )
+            }
+        }
+        Ok(Verifier {
+            data,
+            header,
+            system,
+        })
+    }
+
+    fn verify(self) -> SudachiResult<VerifyReport> {
+        let data = self.data;
+        if let Some(expected) = self.header.checksum {
+            let actual = checksum(&data[Header::STORAGE_SIZE..]);
+            if actual != expected {
+                return invalid(format!(
+                    "checksum mismatch: header has {:016x}, contents have {:016x}",
+                    expected, actual
+                ));
+            }
+        }
+
+        let mut offset = Header::STORAGE_SIZE;
+        let (num_pos, matrix_size) = if self.header.has_grammar() {
+            let grammar = Grammar::parse(data, offset).map_err(|e| {
+                SudachiError::InvalidDictionary(format!("failed to parse grammar: {}", e))
+            })?;
+            offset += grammar.storage_size;
+            let conn = grammar.conn_matrix();
+            (grammar.pos_list.len(), (conn.num_left(), conn.num_right()))
+        } else {
+            (0, (0, 0))
+        };
+
+        let (total_pos, (num_left, num_right)) = match self.system {
+            None => {
+                if matrix_size.0 == 0 || matrix_size.1 == 0 {
+                    return invalid(format!(
+                        "connection matrix of system dictionary is empty: {}x{}",
+                        matrix_size.0, matrix_size.1
+                    ));
+                }
+                (num_pos, matrix_size)
+            }
+            Some(s) => (s.num_pos + num_pos, s.matrix_size),
+        };
+
+        let trie_size = read_u32(data, offset, "trie size")? as usize;
+        let trie = section(data, offset + 4, trie_size, 4, "trie")?;
+        offset += 4 + trie.len();
+
+        let table_size = read_u32(data, offset, "word id table size")? as usize;
+        let table = section(data, offset + 4, table_size, 1, "word id table")?;
+        offset += 4 + table.len();
+
+        let num_words = read_u32(data, offset, "word parameters size")?;
+        if num_words > WordId::MAX_WORD {
+            return invalid(format!(
+                "lexicon has {} words, can't have more than {}",
+                num_words,
+                WordId::MAX_WORD
+            ));
+        }
+        let params = section(data, offset + 4, num_words as usize, 6, "word parameters")?;
+        offset += 4 + params.len();
+
+        let pool = if self.header.has_string_pool() {
+            let pool_size = read_u32(data, offset, "string pool size")? as usize;
+            let pool = section(data, offset + 4, pool_size, 1, "string pool")?;
+            offset += 4 + pool.len();
+            Some(pool)
+        } else {
+            None
+        };
+
+        let info_offsets = section(data, offset, num_words as usize, 4, "word info offsets")?;
+
+        let (group_starts, indexed) = verify_word_id_table(table, num_words)?;
+        verify_trie(trie, &group_starts)?;
+        verify_params(params, &indexed, num_left, num_right)?;
+
+        let num_system_words = match self.system {
+            None => num_words,
+            Some(s) => s.num_words,
+        };
+        let mut subset = InfoSubset::all();
+        if !self.header.has_synonym_group_ids() {
+            subset -= InfoSubset::SYNONYM_GROUP_ID;
+        }
+        let refs = References {
+            num_system_words,
+            num_user_words: self.system.map(|_| num_words),
+        };
+        for wid in 0..num_words {
+            let info_offset = u32_at(info_offsets, wid as usize) as usize;
+            if info_offset >= data.len() {
+                return invalid(format!(
+                    "word {}: word info offset {} is out of bounds of {} bytes",
+                    wid,
+                    info_offset,
+                    data.len()
+                ));
+            }
+            let parser = WordInfoParser::subset(subset);
+            let info = match pool {
+                None => parser.parse(&data[info_offset..]),
+                Some(pool) => parser.parse_pooled(&data[info_offset..], pool),
+            };
+            let info = info.map_err(|e| {
+                SudachiError::InvalidDictionary(format!(
+                    "word {}: failed to parse word info: {}",
+                    wid, e
+                ))
+            })?;
+            if info.pos_id as usize >= total_pos {
+                return invalid(format!(
+                    "word {}: pos id {} is out of range 0..{}",
+                    wid, info.pos_id, total_pos
+                ));
+            }
+            match info.dictionary_form_word_id {
+                -1 => {}
+                x if x < 0 => {
+                    return invalid(format!(
+                        "word {}: dictionary form word id {} is invalid",
+                        wid, x
+                    ))
+                }
+                x => refs.check(wid, "dictionary form", WordId::from_storage(x as u32))?,
+            }
+            for &w in info.a_unit_split.iter() {
+                refs.check(wid, "A unit split", w)?;
+            }
+            for &w in info.b_unit_split.iter() {
+                refs.check(wid, "B unit split", w)?;
+            }
+            for &w in info.word_structure.iter() {
+                refs.check(wid, "word structure", w)?;
+            }
+        }
+
+        Ok(VerifyReport {
+            header: self.header,
+            num_words,
+            num_indexed_words: indexed.iter().filter(|x| **x).count(),
+            num_pos,
+            matrix_size,
+        })
+    }
+}
+
+/// Checks that the table consists of (count, ids...) groups and all ids are in range.
+/// Returns the bitmap of group starts and the bitmap of indexed words.
+fn verify_word_id_table(table: &[u8], num_words: u32) -> SudachiResult<(Vec<bool>, Vec<bool>)> {
+    let mut group_starts = vec![false; table.len()];
+    let mut indexed = vec![false; num_words as usize];
+    let mut pos = 0;
+    while pos < table.len() {
+        group_starts[pos] = true;
+        let count = table[pos] as usize;
+        let ids = section(table, pos + 1, count, 4, "word id table entry")?;
+        for i in 0..count {
+            let wid = u32_at(ids, i);
+            if wid >= num_words {
+                return invalid(format!(
+                    "word id table entry at {}: word id {} is out of range 0..{}",
+                    pos, wid, num_words
+                ));
+            }
+            indexed[wid as usize] = true;
+        }
+        pos += 1 + ids.len();
+    }
+    Ok((group_starts, indexed))
+}
+
+/// Walks all nodes of the double array.
+///
+/// Lookup reads the unit at `node ^ byte` for any input byte without bounds checks,
+/// so the whole 256-unit block of every reachable node must be inside the array.
+fn verify_trie(trie: &[u8], group_starts: &[bool]) -> SudachiResult<()> {
+    let len = trie.len() / 4;
+    if len == 0 {
+        return invalid("trie is empty".to_owned());
+    }
+    let check_block = |node: usize| {
+        if (node | 0xff) >= len {
+            invalid(format!(
+                "trie node at {} is out of bounds of {} units",
+                node, len
+            ))
+        } else {
+            Ok(())
+        }
+    };
+
+    let root = trie_offset(u32_at(trie, 0));
+    check_block(root)?;
+    let mut visited = vec![false; len];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        // label 0 is used for leaves, keys can't contain it
+        for l in 1..=u8::MAX as usize {
+            let child = node ^ l;
+            let unit = u32_at(trie, child);
+            if label(unit) != l as u32 {
+                continue;
+            }
+            if std::mem::replace(&mut visited[child], true) {
+                return invalid(format!("trie unit at {} is reachable twice", child));
+            }
+            let next = child ^ trie_offset(unit);
+            check_block(next)?;
+            if has_leaf(unit) {
+                let value = trie_value(u32_at(trie, next)) as usize;
+                if !group_starts.get(value).copied().unwrap_or(false) {
+                    return invalid(format!(
+                        "trie unit at {}: value {} is not a word id table entry",
+                        child, value
+                    ));
+                }
+            }
+            stack.push(next);
+        }
+    }
+    Ok(())
+}
+
+/// Connection ids of indexed words are used for the connection matrix lookups,
+/// other words can have negative ids
+fn verify_params(
+    params: &[u8],
+    indexed: &[bool],
+    num_left: usize,
+    num_right: usize,
+) -> SudachiResult<()> {
+    for (wid, is_indexed) in indexed.iter().enumerate() {
+        let left = i16_at(params, wid * 3);
+        let right = i16_at(params, wid * 3 + 1);
+        let min = if *is_indexed { 0 } else { i16::MIN as i32 };
+        if (left as i32) < min || left as i32 >= num_left as i32 {
+            return invalid(format!(
+                "word {}: left id {} is out of range 0..{}",
+                wid, left, num_left
+            ));
+        }
+        if (right as i32) < min || right as i32 >= num_right as i32 {
+            return invalid(format!(
+                "word {}: right id {} is out of range 0..{}",
+                wid, right, num_right
+            ));
+        }
+    }
+    Ok(())
+}
+
+struct References {
+    num_system_words: u32,
+    /// None when verifying a system dictionary
+    num_user_words: Option<u32>,
+}
+
+impl References {
+    fn check(&self, wid: u32, field: &str, target: WordId) -> SudachiResult<()> {
+        let max = match (target.dic(), self.num_user_words) {
+            (0, _) => self.num_system_words,
+            (1, Some(n)) => n,
+            _ => {
+                return invalid(format!(
+                    "word {}: {} refers to word {} of unknown dictionary",
+                    wid, field, target
+                ))
+            }
+        };
+        if target.word() >= max {
+            return invalid(format!(
+                "word {}: {} refers to word {} which is out of range 0..{}",
+                wid, field, target, max
+            ));
+        }
+        Ok(())
+    }
+}
@@ -105,6 +105,15 @@ pub enum SudachiError {
 
     #[error("MorphemeList is borrowed, make sure that all Ref<> are dropped")]
     MorphemeListBorrowed,
+
+    #[error("Invalid analysis constraint: {0}")]
+    InvalidConstraint(String),
+
+    #[error("Invalid user word: {0}")]
+    InvalidUserWord(String),
+
+    #[error("Invalid dictionary: {0}")]
+    InvalidDictionary(String),
 }
 
 impl From<std::io::Error> for SudachiError {
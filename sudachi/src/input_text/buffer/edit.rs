@@ -113,7 +113,9 @@ pub fn resolve_edits(
                 c.encode_utf8(&mut [0; 4]),
             ),
         };
-        if cur_len > REALLY_MAX_LENGTH as isize {
+        // edits replace disjoint ranges of the source, so the length can't become negative
+        assert!(cur_len >= 0, "negative length after edits: {}", cur_len);
+        if cur_len as usize > REALLY_MAX_LENGTH {
             return cur_len as usize;
         }
     }
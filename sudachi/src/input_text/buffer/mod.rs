@@ -28,11 +28,11 @@ use std::ops::Range;
 use crate::error::{SudachiError, SudachiResult};
 use crate::input_text::InputTextIndex;
 
-/// limit on the maximum length of the input types, in bytes, 3/4 of u16::MAX
-const MAX_LENGTH: usize = u16::MAX as usize / 4 * 3;
+/// limit on the maximum length of the input types, in bytes, 3/4 of u32::MAX
+const MAX_LENGTH: usize = u32::MAX as usize / 4 * 3;
 
 /// if the limit of the rewritten sentence is more than this number, then all bets are off
-const REALLY_MAX_LENGTH: usize = u16::MAX as usize;
+const REALLY_MAX_LENGTH: usize = u32::MAX as usize;
 
 #[derive(Eq, PartialEq, Debug, Clone, Default)]
 enum BufferState {
@@ -189,8 +189,8 @@ impl MeCabOovPlugin {
     /// Creates a new oov node
     fn get_oov_node(&self, oov: &Oov, start: usize, end: usize) -> Node {
         Node::new(
-            start as u16,
-            end as u16,
+            start as u32,
+            end as u32,
             oov.left_id as u16,
             oov.right_id as u16,
             oov.cost,
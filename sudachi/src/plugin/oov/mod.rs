@@ -17,6 +17,7 @@
 use crate::analysis::created::CreatedWords;
 use crate::analysis::Node;
 use serde_json::Value;
+use std::ops::Range;
 
 use crate::config::Config;
 use crate::dic::grammar::Grammar;
@@ -24,12 +25,14 @@ use crate::input_text::InputBuffer;
 use crate::plugin::oov::mecab_oov::MeCabOovPlugin;
 use crate::plugin::oov::regex_oov::RegexOovProvider;
 use crate::plugin::oov::simple_oov::SimpleOovPlugin;
+use crate::plugin::oov::web_token_oov::WebTokenOovPlugin;
 use crate::plugin::PluginCategory;
 use crate::prelude::*;
 
 pub mod mecab_oov;
 pub mod regex_oov;
 pub mod simple_oov;
+pub mod web_token_oov;
 
 /// Trait of plugin to provide oov node during tokenization
 pub trait OovProviderPlugin: Sync + Send {
@@ -50,6 +53,17 @@ pub trait OovProviderPlugin: Sync + Send {
         other_words: CreatedWords,
         result: &mut Vec<Node>,
     ) -> SudachiResult<usize>;
+
+    /// Adds char ranges of the current text which must be analyzed as single morphemes.
+    /// Called once for each input before the lattice is built,
+    /// ranges which conflict with analysis constraints are ignored
+    fn single_word_ranges(
+        &self,
+        _input_text: &InputBuffer,
+        _result: &mut Vec<Range<usize>>,
+    ) -> SudachiResult<()> {
+        Ok(())
+    }
 }
 
 impl PluginCategory for dyn OovProviderPlugin {
@@ -64,6 +78,7 @@ impl PluginCategory for dyn OovProviderPlugin {
             "SimpleOovPlugin" => Some(Box::<SimpleOovPlugin>::default()),
             "MeCabOovPlugin" => Some(Box::<MeCabOovPlugin>::default()),
             "RegexOovProvider" => Some(Box::<RegexOovProvider>::default()),
+            "WebTokenOovPlugin" => Some(Box::<WebTokenOovPlugin>::default()),
             _ => None,
         }
     }
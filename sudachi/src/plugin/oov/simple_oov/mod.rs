@@ -79,8 +79,8 @@ impl OovProviderPlugin for SimpleOovPlugin {
         let length = input_text.get_word_candidate_length(offset);
 
         result.push(Node::new(
-            offset as u16,
-            (offset + length) as u16,
+            offset as u32,
+            (offset + length) as u32,
             self.left_id,
             self.right_id,
             self.cost,
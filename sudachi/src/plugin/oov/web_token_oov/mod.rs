@@ -0,0 +1,280 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::ops::Range;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::analysis::created::CreatedWords;
+use crate::analysis::Node;
+use crate::config::Config;
+use crate::dic::grammar::Grammar;
+use crate::dic::word_id::WordId;
+use crate::input_text::InputBuffer;
+use crate::plugin::oov::OovProviderPlugin;
+use crate::prelude::*;
+use crate::util::check_params::CheckParams;
+use crate::util::user_pos::{UserPosMode, UserPosSupport};
+
+#[cfg(test)]
+mod test;
+
+/// Kinds of web tokens, in the order they are tried
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum WebTokenKind {
+    Url,
+    Email,
+    Mention,
+    Hashtag,
+}
+
+impl WebTokenKind {
+    const ALL: [WebTokenKind; 4] = [
+        WebTokenKind::Url,
+        WebTokenKind::Email,
+        WebTokenKind::Mention,
+        WebTokenKind::Hashtag,
+    ];
+
+    fn pattern(self) -> &'static str {
+        match self {
+            WebTokenKind::Url => {
+                r"^(?:(?i:https?|ftp)://|(?i:www)\.)[0-9A-Za-z\-._~:/?#\[\]@!$&'()*+,;=%]+"
+            }
+            WebTokenKind::Email => {
+                r"^[0-9A-Za-z._%+\-]+@[0-9A-Za-z\-]+(?:\.[0-9A-Za-z\-]+)*\.[A-Za-z]{2,}"
+            }
+            WebTokenKind::Mention => r"^@[0-9A-Za-z_]+",
+            WebTokenKind::Hashtag => r"^#[\p{L}\p{M}\p{N}_]+",
+        }
+    }
+
+    /// Tokens do not start in the middle of words,
+    /// e.g. `@` in `user@example.com` does not start a mention
+    fn can_follow(self, prev: char) -> bool {
+        let is_word = prev.is_ascii_alphanumeric() || prev == '_';
+        match self {
+            WebTokenKind::Url => !is_word,
+            WebTokenKind::Email => !is_word && !"._%+-".contains(prev),
+            WebTokenKind::Mention => !is_word && prev != '@',
+            WebTokenKind::Hashtag => !is_word && prev != '&' && prev != '#',
+        }
+    }
+
+    /// Cheap check of the first character before running the regex
+    fn can_start(self, first: char) -> bool {
+        match self {
+            WebTokenKind::Url => matches!(first.to_ascii_lowercase(), 'h' | 'f' | 'w'),
+            WebTokenKind::Email => first.is_ascii_alphanumeric(),
+            WebTokenKind::Mention => first == '@',
+            WebTokenKind::Hashtag => first == '#',
+        }
+    }
+
+    /// Removes trailing characters which are usually not a part of the token
+    fn trim(self, token: &str) -> &str {
+        match self {
+            WebTokenKind::Url => {
+                let mut token = token.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'']);
+                // keep parentheses of urls like https://en.wikipedia.org/wiki/Rust_(language)
+                while token.ends_with(')')
+                    && token.matches(')').count() > token.matches('(').count()
+                {
+                    token = token[..token.len() - 1]
+                        .trim_end_matches(['.', ',', ':', ';', '!', '?', '\'']);
+                }
+                token
+            }
+            _ => token,
+        }
+    }
+
+    /// Hashtags which consist only of digits are not hashtags: "#1"
+    fn is_valid(self, token: &str) -> bool {
+        match self {
+            WebTokenKind::Hashtag => token[1..].chars().any(|c| !c.is_numeric()),
+            _ => true,
+        }
+    }
+}
+
+struct Recognizer {
+    kind: WebTokenKind,
+    regex: Regex,
+    left_id: u16,
+    right_id: u16,
+    cost: i16,
+    pos: u16,
+}
+
+/// Provides URLs, e-mail addresses, @mentions and #hashtags as OOV words.
+///
+/// Recognized tokens are always analyzed as single morphemes:
+/// other nodes which cross their borders are not created.
+/// Only token kinds which are present in the settings are recognized.
+#[derive(Default)]
+pub struct WebTokenOovPlugin {
+    recognizers: Vec<Recognizer>,
+    max_length: usize,
+}
+
+fn default_max_length() -> usize {
+    256
+}
+
+fn default_cost() -> i64 {
+    i16::MIN as i64
+}
+
+/// Settings of a single token kind, connection ids and cost default to the plugin ones
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+struct TokenSettings {
+    #[serde(alias = "oovPOS")]
+    pos: Vec<String>,
+    leftId: Option<i64>,
+    rightId: Option<i64>,
+    cost: Option<i64>,
+}
+
+/// Struct corresponds with raw config json file.
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+struct PluginSettings {
+    leftId: i64,
+    rightId: i64,
+    #[serde(default = "default_cost")]
+    cost: i64,
+    #[serde(default = "default_max_length")]
+    maxLength: usize,
+    #[serde(default)]
+    userPOS: UserPosMode,
+    url: Option<TokenSettings>,
+    email: Option<TokenSettings>,
+    mention: Option<TokenSettings>,
+    hashtag: Option<TokenSettings>,
+}
+
+impl WebTokenOovPlugin {
+    /// Returns the kind and the end char index of the token which starts at the offset
+    fn find_token(&self, input_text: &InputBuffer, offset: usize) -> Option<(usize, &Recognizer)> {
+        let chars = input_text.current_chars();
+        let first = chars[offset];
+        let prev = offset.checked_sub(1).map(|i| chars[i]);
+        let end = chars.len().min(offset + self.max_length);
+        let text = input_text.curr_slice_c(offset..end);
+        let byte_offset = input_text.to_curr_byte_idx(offset);
+
+        for r in self.recognizers.iter() {
+            if !r.kind.can_start(first) || !prev.map_or(true, |c| r.kind.can_follow(c)) {
+                continue;
+            }
+            if let Some(m) = r.regex.find(text) {
+                let token = r.kind.trim(m.as_str());
+                if token.is_empty() || !r.kind.is_valid(token) {
+                    continue;
+                }
+                let token_end = input_text.ch_idx(byte_offset + token.len());
+                if token_end > offset + 1 {
+                    return Some((token_end, r));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl OovProviderPlugin for WebTokenOovPlugin {
+    fn set_up(
+        &mut self,
+        settings: &Value,
+        _config: &Config,
+        mut grammar: &mut Grammar,
+    ) -> SudachiResult<()> {
+        let settings: PluginSettings = serde_json::from_value(settings.clone())?;
+
+        self.max_length = settings.maxLength;
+        self.recognizers.clear();
+        let kinds = [
+            &settings.url,
+            &settings.email,
+            &settings.mention,
+            &settings.hashtag,
+        ];
+        for (kind, token) in WebTokenKind::ALL.into_iter().zip(kinds) {
+            let token = match token {
+                Some(t) => t,
+                None => continue,
+            };
+            self.recognizers.push(Recognizer {
+                kind,
+                regex: Regex::new(kind.pattern()).expect("built-in patterns are valid"),
+                left_id: grammar.check_left_id(token.leftId.unwrap_or(settings.leftId))?,
+                right_id: grammar.check_right_id(token.rightId.unwrap_or(settings.rightId))?,
+                cost: grammar.check_cost(token.cost.unwrap_or(settings.cost))?,
+                pos: grammar.handle_user_pos(&token.pos, settings.userPOS)?,
+            });
+        }
+        Ok(())
+    }
+
+    fn provide_oov(
+        &self,
+        input_text: &InputBuffer,
+        offset: usize,
+        _other_words: CreatedWords,
+        result: &mut Vec<Node>,
+    ) -> SudachiResult<usize> {
+        match self.find_token(input_text, offset) {
+            None => Ok(0),
+            Some((end, r)) => {
+                result.push(Node::new(
+                    offset as u32,
+                    end as u32,
+                    r.left_id,
+                    r.right_id,
+                    r.cost,
+                    WordId::oov(r.pos as u32),
+                ));
+                Ok(1)
+            }
+        }
+    }
+
+    fn single_word_ranges(
+        &self,
+        input_text: &InputBuffer,
+        result: &mut Vec<Range<usize>>,
+    ) -> SudachiResult<()> {
+        if self.recognizers.is_empty() {
+            return Ok(());
+        }
+        let num_chars = input_text.current_chars().len();
+        let mut offset = 0;
+        while offset < num_chars {
+            match self.find_token(input_text, offset) {
+                Some((end, _)) => {
+                    result.push(offset..end);
+                    offset = end;
+                }
+                None => offset += 1,
+            }
+        }
+        Ok(())
+    }
+}
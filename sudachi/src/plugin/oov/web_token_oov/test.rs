@@ -0,0 +1,152 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use super::*;
+use crate::analysis::node::LatticeNode;
+use crate::test::zero_grammar;
+use crate::util::testing::input_text;
+use serde_json::json;
+
+fn plugin(settings: Value) -> (WebTokenOovPlugin, Grammar<'static>) {
+    let mut plugin = WebTokenOovPlugin::default();
+    let mut grammar = zero_grammar();
+    let cfg = Config::minimal_at("");
+    plugin
+        .set_up(&settings, &cfg, &mut grammar)
+        .expect("failed");
+    (plugin, grammar)
+}
+
+fn all_kinds() -> WebTokenOovPlugin {
+    plugin(json!({
+        "leftId": 0,
+        "rightId": 0,
+        "userPOS": "allow",
+        "url": {"pos": ["名詞", "普通名詞", "URL", "*", "*", "*"]},
+        "email": {"pos": ["名詞", "普通名詞", "EMAIL", "*", "*", "*"], "cost": 100},
+        "mention": {"pos": ["名詞", "固有名詞", "MENTION", "*", "*", "*"]},
+        "hashtag": {"pos": ["名詞", "普通名詞", "HASHTAG", "*", "*", "*"]},
+    }))
+    .0
+}
+
+/// Returns surfaces of the tokens found at the offset
+fn token(p: &WebTokenOovPlugin, data: &str, offset: usize) -> Option<String> {
+    let text = input_text(data);
+    let mut result = Vec::new();
+    let num = p
+        .provide_oov(&text, offset, CreatedWords::empty(), &mut result)
+        .expect("not failed");
+    assert_eq!(num, result.len());
+    result
+        .first()
+        .map(|n| text.curr_slice_c(n.begin()..n.end()).to_owned())
+}
+
+fn ranges(p: &WebTokenOovPlugin, data: &str) -> Vec<String> {
+    let text = input_text(data);
+    let mut result = Vec::new();
+    p.single_word_ranges(&text, &mut result).unwrap();
+    result
+        .into_iter()
+        .map(|r| text.curr_slice_c(r).to_owned())
+        .collect()
+}
+
+#[test]
+fn urls() {
+    let p = all_kinds();
+    assert_eq!(
+        token(&p, "https://example.com/a?b=c#d", 0).as_deref(),
+        Some("https://example.com/a?b=c#d")
+    );
+    assert_eq!(
+        token(&p, "www.example.com.", 0).as_deref(),
+        Some("www.example.com")
+    );
+    assert_eq!(
+        token(&p, "(https://en.wikipedia.org/wiki/Rust_(language))", 1).as_deref(),
+        Some("https://en.wikipedia.org/wiki/Rust_(language)")
+    );
+    assert_eq!(token(&p, "xhttp://example.com", 1), None);
+    assert_eq!(token(&p, "http", 0), None);
+}
+
+#[test]
+fn emails_mentions_hashtags() {
+    let p = all_kinds();
+    assert_eq!(
+        token(&p, "連絡はuser.name+tag@example.co.jpまで", 3).as_deref(),
+        Some("user.name+tag@example.co.jp")
+    );
+    assert_eq!(token(&p, "user@example.com", 4), None);
+    assert_eq!(token(&p, "user@example.com", 1), None);
+    assert_eq!(
+        token(&p, "こんにちは@sudachi_dev", 5).as_deref(),
+        Some("@sudachi_dev")
+    );
+    assert_eq!(
+        token(&p, "#すだち大好き", 0).as_deref(),
+        Some("#すだち大好き")
+    );
+    assert_eq!(token(&p, "#123", 0), None);
+    assert_eq!(token(&p, "&#123;", 1), None);
+}
+
+#[test]
+fn settings_per_kind() {
+    let (p, grammar) = plugin(json!({
+        "leftId": 0,
+        "rightId": 0,
+        "cost": -100,
+        "userPOS": "allow",
+        "email": {"pos": ["名詞", "普通名詞", "EMAIL", "*", "*", "*"]},
+        "mention": {"pos": ["名詞", "固有名詞", "MENTION", "*", "*", "*"], "cost": 50},
+    }));
+    let text = input_text("@user a@b.jp");
+    let mut result = Vec::new();
+    p.provide_oov(&text, 0, CreatedWords::empty(), &mut result)
+        .unwrap();
+    p.provide_oov(&text, 6, CreatedWords::empty(), &mut result)
+        .unwrap();
+    let costs: Vec<_> = result.iter().map(|n| n.cost()).collect();
+    assert_eq!(costs, [50, -100]);
+    let pos: Vec<_> = result
+        .iter()
+        .map(|n| grammar.pos_list[n.word_id().word() as usize][2].as_str())
+        .collect();
+    assert_eq!(pos, ["MENTION", "EMAIL"]);
+    // hashtags are not configured
+    assert_eq!(token(&p, "@user #tag", 6), None);
+}
+
+#[test]
+fn single_words() {
+    let p = all_kinds();
+    assert_eq!(
+        ranges(
+            &p,
+            "詳細はhttps://example.com/やuser@example.comへ#質問 @sudachi"
+        ),
+        [
+            "https://example.com/",
+            "user@example.com",
+            "#質問",
+            "@sudachi"
+        ]
+    );
+    assert!(ranges(&p, "普通の文です").is_empty());
+}
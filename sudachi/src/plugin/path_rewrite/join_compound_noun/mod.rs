@@ -0,0 +1,169 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::analysis::lattice::Lattice;
+use crate::analysis::node::{concat_nodes, ResultNode};
+use crate::config::Config;
+use crate::dic::grammar::Grammar;
+use crate::input_text::InputBuffer;
+use crate::plugin::path_rewrite::PathRewritePlugin;
+use crate::pos::PosMatcher;
+use crate::prelude::*;
+
+#[cfg(test)]
+mod test;
+
+/// Two adjacent nodes can be joined if the left one matches `left` and the right one `right`
+struct JoinRule {
+    left: PosMatcher,
+    right: PosMatcher,
+}
+
+/// Joins sequences of morphemes, usually nouns, into compound morphemes by POS rules.
+///
+/// Joined morphemes keep their constituents, which are returned when the morpheme
+/// is split in A or B mode, so analysis in these modes is not changed by the plugin.
+/// The POS of a compound is the configured one or the POS of its last constituent.
+#[derive(Default)]
+pub struct JoinCompoundNounPlugin {
+    rules: Vec<JoinRule>,
+    pos_id: Option<u16>,
+    max_length: usize,
+}
+
+/// POS pattern: a prefix of POS components, `null` matches any value
+type PosPattern = Vec<Option<String>>;
+
+#[derive(Deserialize)]
+struct RuleSettings {
+    left: Vec<PosPattern>,
+    right: Vec<PosPattern>,
+}
+
+/// Struct corresponds with raw config json file.
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+struct PluginSettings {
+    rules: Vec<RuleSettings>,
+    pos: Option<Vec<String>>,
+    maxLength: Option<usize>,
+}
+
+/// Creates the matcher of all POS in the grammar which match any of the patterns
+fn pos_matcher(grammar: &Grammar, patterns: &[PosPattern]) -> SudachiResult<PosMatcher> {
+    let mut ids = Vec::new();
+    for pattern in patterns {
+        let num_ids = ids.len();
+        for (id, pos) in grammar.pos_list.iter().enumerate() {
+            let matches = pattern.len() <= pos.len()
+                && pattern
+                    .iter()
+                    .zip(pos.iter())
+                    .all(|(p, c)| p.as_ref().map_or(true, |p| p == c));
+            if matches {
+                ids.push(id as u16);
+            }
+        }
+        if ids.len() == num_ids {
+            return Err(SudachiError::InvalidPartOfSpeech(format!("{:?}", pattern)));
+        }
+    }
+    Ok(PosMatcher::new(ids))
+}
+
+impl JoinCompoundNounPlugin {
+    fn can_join(&self, left: &ResultNode, right: &ResultNode) -> bool {
+        let left = left.word_info().pos_id();
+        let right = right.word_info().pos_id();
+        self.rules
+            .iter()
+            .any(|r| r.left.matches_id(left) && r.right.matches_id(right))
+    }
+
+    fn join(
+        &self,
+        mut path: Vec<ResultNode>,
+        begin: usize,
+        end: usize,
+    ) -> SudachiResult<Vec<ResultNode>> {
+        let parts: Arc<[ResultNode]> = path[begin..end].iter().cloned().collect();
+        let pos_id = self
+            .pos_id
+            .unwrap_or_else(|| path[end - 1].word_info().pos_id());
+        path = concat_nodes(path, begin, end, None)?;
+        path[begin].set_pos_id(pos_id);
+        path[begin].set_parts(parts);
+        Ok(path)
+    }
+}
+
+impl PathRewritePlugin for JoinCompoundNounPlugin {
+    fn set_up(
+        &mut self,
+        settings: &Value,
+        _config: &Config,
+        grammar: &Grammar,
+    ) -> SudachiResult<()> {
+        let settings: PluginSettings = serde_json::from_value(settings.clone())?;
+
+        self.rules.clear();
+        for rule in settings.rules.iter() {
+            self.rules.push(JoinRule {
+                left: pos_matcher(grammar, &rule.left)?,
+                right: pos_matcher(grammar, &rule.right)?,
+            });
+        }
+        self.pos_id = match settings.pos {
+            None => None,
+            Some(pos) => Some(
+                grammar
+                    .get_part_of_speech_id(&pos)
+                    .ok_or_else(|| SudachiError::InvalidPartOfSpeech(format!("{:?}", pos)))?,
+            ),
+        };
+        self.max_length = settings.maxLength.unwrap_or(usize::MAX);
+
+        Ok(())
+    }
+
+    fn rewrite(
+        &self,
+        _text: &InputBuffer,
+        mut path: Vec<ResultNode>,
+        _lattice: &Lattice,
+    ) -> SudachiResult<Vec<ResultNode>> {
+        let mut begin = 0;
+        while begin < path.len() {
+            let mut end = begin + 1;
+            while end < path.len()
+                && end - begin < self.max_length
+                && self.can_join(&path[end - 1], &path[end])
+            {
+                end += 1;
+            }
+            if end - begin > 1 {
+                path = self.join(path, begin, end)?;
+            }
+            begin += 1;
+        }
+        Ok(path)
+    }
+}
@@ -0,0 +1,187 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use super::*;
+
+use crate::analysis::node::LatticeNode;
+use crate::analysis::Node;
+use crate::dic::lexicon::word_infos::WordInfoData;
+use crate::dic::word_id::WordId;
+use crate::test::zero_grammar;
+use crate::util::testing::input_text;
+use serde_json::json;
+
+const NOUN: [&str; 6] = ["名詞", "普通名詞", "一般", "*", "*", "*"];
+const PROPER: [&str; 6] = ["名詞", "固有名詞", "地名", "一般", "*", "*"];
+const SUFFIX: [&str; 6] = ["接尾辞", "名詞的", "一般", "*", "*", "*"];
+const PARTICLE: [&str; 6] = ["助詞", "格助詞", "*", "*", "*", "*"];
+
+fn grammar() -> Grammar<'static> {
+    let mut grammar = zero_grammar();
+    for pos in [NOUN, PROPER, SUFFIX, PARTICLE] {
+        grammar.register_pos(&pos).expect("failed");
+    }
+    grammar
+}
+
+fn plugin(settings: Value) -> (JoinCompoundNounPlugin, Grammar<'static>) {
+    let grammar = grammar();
+    let mut plugin = JoinCompoundNounPlugin::default();
+    plugin
+        .set_up(&settings, &Config::minimal_at(""), &grammar)
+        .expect("failed");
+    (plugin, grammar)
+}
+
+fn nouns_and_suffixes() -> Value {
+    json!({
+        "rules": [
+            {"left": [["名詞"]], "right": [["名詞", "普通名詞"], ["接尾辞"]]}
+        ]
+    })
+}
+
+/// Builds the path of nodes with given surfaces and POS
+fn build_path(grammar: &Grammar, nodes: &[(&str, [&str; 6])]) -> Vec<ResultNode> {
+    let mut path = Vec::new();
+    let mut start_cp = 0;
+    let mut start_b = 0;
+    for (surface, pos) in nodes {
+        let pos_id = grammar.get_part_of_speech_id(pos).expect("registered");
+        let len_cp = surface.chars().count();
+        path.push(ResultNode::new(
+            Node::new(
+                start_cp as u32,
+                (start_cp + len_cp) as u32,
+                0,
+                0,
+                100,
+                WordId::new(0, pos_id as u32),
+            ),
+            start_cp as i32 * 100,
+            start_b as u32,
+            (start_b + surface.len()) as u32,
+            WordInfoData {
+                surface: surface.to_string(),
+                head_word_length: surface.len() as u16,
+                pos_id,
+                normalized_form: surface.to_string(),
+                dictionary_form_word_id: -1,
+                dictionary_form: surface.to_string(),
+                reading_form: surface.to_string(),
+                ..Default::default()
+            }
+            .into(),
+        ));
+        start_cp += len_cp;
+        start_b += surface.len();
+    }
+    path
+}
+
+fn rewrite(p: &JoinCompoundNounPlugin, path: Vec<ResultNode>) -> Vec<ResultNode> {
+    let data: String = path.iter().map(|n| n.word_info().surface()).collect();
+    p.rewrite(&input_text(data), path, &Lattice::default())
+        .expect("succeeds")
+}
+
+fn surfaces(path: &[ResultNode]) -> Vec<&str> {
+    path.iter().map(|n| n.word_info().surface()).collect()
+}
+
+#[test]
+fn joins_by_rules() {
+    let (p, grammar) = plugin(nouns_and_suffixes());
+    let path = build_path(
+        &grammar,
+        &[
+            ("東京", PROPER),
+            ("大学", NOUN),
+            ("病院", NOUN),
+            ("に", PARTICLE),
+            ("会議", NOUN),
+            ("室", SUFFIX),
+            ("病院", NOUN),
+        ],
+    );
+    let path = rewrite(&p, path);
+    // a suffix is not a left side of any rule
+    assert_eq!(surfaces(&path), ["東京大学病院", "に", "会議室", "病院"]);
+    assert_eq!(surfaces(path[0].parts()), ["東京", "大学", "病院"]);
+    assert_eq!(surfaces(path[2].parts()), ["会議", "室"]);
+    assert!(path[1].parts().is_empty());
+    assert_eq!(path[0].char_range(), 0..6);
+    assert_eq!(path[0].word_info().reading_form(), "東京大学病院");
+    // the last constituent is the head of a compound
+    assert_eq!(
+        path[0].word_info().pos_id(),
+        grammar.get_part_of_speech_id(&NOUN).unwrap()
+    );
+}
+
+#[test]
+fn rules_are_directed() {
+    let (p, grammar) = plugin(nouns_and_suffixes());
+    // a proper noun is not a right side of any rule
+    let path = build_path(
+        &grammar,
+        &[("室", SUFFIX), ("会議", NOUN), ("東京", PROPER)],
+    );
+    let path = rewrite(&p, path);
+    assert_eq!(surfaces(&path), ["室", "会議", "東京"]);
+}
+
+#[test]
+fn configured_pos_and_max_length() {
+    let mut settings = nouns_and_suffixes();
+    settings["pos"] = json!(PROPER);
+    settings["maxLength"] = json!(2);
+    let (p, grammar) = plugin(settings);
+    let path = build_path(
+        &grammar,
+        &[
+            ("大学", NOUN),
+            ("病院", NOUN),
+            ("会議", NOUN),
+            ("室", SUFFIX),
+        ],
+    );
+    let path = rewrite(&p, path);
+    assert_eq!(surfaces(&path), ["大学病院", "会議室"]);
+    let proper = grammar.get_part_of_speech_id(&PROPER).unwrap();
+    assert!(path.iter().all(|n| n.word_info().pos_id() == proper));
+}
+
+#[test]
+fn patterns_must_match() {
+    let grammar = grammar();
+    let mut p = JoinCompoundNounPlugin::default();
+    let result = p.set_up(
+        &json!({"rules": [{"left": [["動詞"]], "right": [["名詞"]]}]}),
+        &Config::minimal_at(""),
+        &grammar,
+    );
+    assert!(matches!(result, Err(SudachiError::InvalidPartOfSpeech(_))));
+
+    let matcher = pos_matcher(
+        &grammar,
+        &[vec![Some("名詞".to_owned()), None, Some("一般".to_owned())]],
+    )
+    .expect("matches");
+    assert_eq!(1, matcher.num_entries());
+    let matcher = pos_matcher(&grammar, &[vec![Some("名詞".to_owned())]]).expect("matches");
+    assert_eq!(2, matcher.num_entries());
+}
@@ -0,0 +1,438 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::fmt::Write;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::analysis::lattice::Lattice;
+use crate::analysis::node::{concat_nodes, LatticeNode, ResultNode};
+use crate::config::Config;
+use crate::dic::category_type::CategoryType;
+use crate::dic::grammar::Grammar;
+use crate::input_text::InputBuffer;
+use crate::input_text::InputTextIndex;
+use crate::plugin::path_rewrite::join_numeric::numeric_parser::NumericParser;
+use crate::plugin::path_rewrite::PathRewritePlugin;
+use crate::prelude::*;
+
+#[cfg(test)]
+mod test;
+
+/// Kinds of expressions, in the order of preference when matches have the same length
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ExpressionKind {
+    Date,
+    Time,
+    Duration,
+}
+
+/// Units of durations, ordered from the largest
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+enum DurationUnit {
+    Year,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl DurationUnit {
+    fn designator(self) -> char {
+        match self {
+            DurationUnit::Year => 'Y',
+            DurationUnit::Month => 'M',
+            DurationUnit::Week => 'W',
+            DurationUnit::Day => 'D',
+            DurationUnit::Hour => 'H',
+            DurationUnit::Minute => 'M',
+            DurationUnit::Second => 'S',
+        }
+    }
+
+    fn is_time(self) -> bool {
+        self >= DurationUnit::Hour
+    }
+}
+
+const DATE_UNITS: [&str; 3] = ["年", "月", "日"];
+const TIME_PREFIXES: [&str; 2] = ["午前", "午後"];
+
+/// Duration units with a flag whether the word alone means a duration:
+/// "3日間" and "1時間" are durations, "3日" is a date and "30分" is a time
+const DURATION_UNITS: [(&str, DurationUnit, bool); 20] = [
+    ("年", DurationUnit::Year, false),
+    ("年間", DurationUnit::Year, true),
+    ("か月", DurationUnit::Month, true),
+    ("カ月", DurationUnit::Month, true),
+    ("ヵ月", DurationUnit::Month, true),
+    ("ヶ月", DurationUnit::Month, true),
+    ("ケ月", DurationUnit::Month, true),
+    ("箇月", DurationUnit::Month, true),
+    ("か月間", DurationUnit::Month, true),
+    ("カ月間", DurationUnit::Month, true),
+    ("ヵ月間", DurationUnit::Month, true),
+    ("ヶ月間", DurationUnit::Month, true),
+    ("週間", DurationUnit::Week, true),
+    ("日", DurationUnit::Day, false),
+    ("日間", DurationUnit::Day, true),
+    ("時間", DurationUnit::Hour, true),
+    ("分", DurationUnit::Minute, false),
+    ("分間", DurationUnit::Minute, true),
+    ("秒", DurationUnit::Second, false),
+    ("秒間", DurationUnit::Second, true),
+];
+
+/// Unit words are matched over at most this number of nodes
+const MAX_WORD_NODES: usize = 3;
+
+/// Joins date, time and duration expressions as one morpheme.
+///
+/// Normalized forms of joined morphemes are ISO-8601 representations:
+/// "2024年10月17日" → "2024-10-17", "午後3時30分" → "15:30", "1時間30分" → "PT1H30M".
+/// Only expression kinds which are present in the settings are joined.
+#[derive(Default)]
+pub struct JoinDateTimePlugin {
+    date_pos_id: Option<u16>,
+    time_pos_id: Option<u16>,
+    duration_pos_id: Option<u16>,
+    /// Numbers joined by `JoinNumericPlugin` can contain non-numeric characters: "1.5"
+    numeric_pos_id: Option<u16>,
+}
+
+#[derive(Deserialize)]
+struct KindSettings {
+    pos: Vec<String>,
+}
+
+/// Struct corresponds with raw config json file.
+#[derive(Deserialize)]
+struct PluginSettings {
+    date: Option<KindSettings>,
+    time: Option<KindSettings>,
+    duration: Option<KindSettings>,
+}
+
+fn pos_id_of(grammar: &Grammar, settings: Option<&KindSettings>) -> SudachiResult<Option<u16>> {
+    settings
+        .map(|s| {
+            grammar
+                .get_part_of_speech_id(&s.pos)
+                .ok_or_else(|| SudachiError::InvalidPartOfSpeech(format!("{:?}", s.pos)))
+        })
+        .transpose()
+}
+
+fn is_leap_year(year: u32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: Option<u32>, month: u32) -> u32 {
+    match month {
+        2 => match year {
+            Some(y) if !is_leap_year(y) => 28,
+            _ => 29,
+        },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Finds the longest word which is equal to normalized forms of the nodes starting at `idx`.
+/// Returns the end index of the nodes and the found word.
+fn find_word<'w, W>(
+    path: &[ResultNode],
+    idx: usize,
+    words: &'w [W],
+    key: impl Fn(&W) -> &str,
+) -> Option<(usize, &'w W)> {
+    let mut text = String::new();
+    let mut found = None;
+    for (end, node) in path
+        .iter()
+        .enumerate()
+        .skip(idx)
+        .take(MAX_WORD_NODES)
+        .map(|(i, n)| (i + 1, n))
+    {
+        text.push_str(node.word_info().normalized_form());
+        if let Some(w) = words.iter().find(|w| key(w) == text) {
+            found = Some((end, w));
+        }
+        if !words.iter().any(|w| key(w).starts_with(text.as_str())) {
+            break;
+        }
+    }
+    found
+}
+
+fn find_str<'w>(path: &[ResultNode], idx: usize, words: &'w [&str]) -> Option<(usize, &'w str)> {
+    find_word(path, idx, words, |w| w).map(|(end, w)| (end, *w))
+}
+
+impl JoinDateTimePlugin {
+    fn is_numeric<T: InputTextIndex>(&self, text: &T, node: &ResultNode) -> bool {
+        Some(node.word_info().pos_id()) == self.numeric_pos_id
+            || text
+                .cat_of_range(node.char_range())
+                .intersects(CategoryType::NUMERIC | CategoryType::KANJINUMERIC)
+    }
+
+    /// Parses the number which consists of numeric nodes starting at `idx`.
+    /// Returns the end index of the nodes and the normalized number.
+    fn number<T: InputTextIndex>(
+        &self,
+        text: &T,
+        path: &[ResultNode],
+        idx: usize,
+        parser: &mut NumericParser,
+    ) -> Option<(usize, String)> {
+        parser.clear();
+        let mut end = idx;
+        while end < path.len() && self.is_numeric(text, &path[end]) {
+            for c in path[end].word_info().normalized_form().chars() {
+                if !parser.append(&c) {
+                    return None;
+                }
+            }
+            end += 1;
+        }
+        if end == idx || !parser.done() {
+            return None;
+        }
+        Some((end, parser.get_normalized()))
+    }
+
+    /// A number followed by one of the unit words
+    fn component<'w, T: InputTextIndex, W>(
+        &self,
+        text: &T,
+        path: &[ResultNode],
+        idx: usize,
+        parser: &mut NumericParser,
+        units: &'w [W],
+        key: impl Fn(&W) -> &str,
+    ) -> Option<(usize, String, &'w W)> {
+        let (end, number) = self.number(text, path, idx, parser)?;
+        let (end, unit) = find_word(path, end, units, key)?;
+        Some((end, number, unit))
+    }
+
+    /// Year, month and day in this order, at least two of them: "2024年10月", "10月17日"
+    fn date<T: InputTextIndex>(
+        &self,
+        text: &T,
+        path: &[ResultNode],
+        idx: usize,
+        parser: &mut NumericParser,
+    ) -> Option<(usize, String)> {
+        let mut fields: [Option<u32>; 3] = [None; 3];
+        let mut last: Option<usize> = None;
+        let mut end = idx;
+        while let Some((e, number, unit)) =
+            self.component(text, path, end, parser, &DATE_UNITS, |u| u)
+        {
+            let field = DATE_UNITS.iter().position(|u| u == unit)?;
+            if last.is_some_and(|l| field != l + 1) {
+                break;
+            }
+            match number.parse() {
+                Ok(v) => fields[field] = Some(v),
+                Err(_) => break,
+            }
+            last = Some(field);
+            end = e;
+        }
+
+        let valid_month = |m: u32| (1..=12).contains(&m);
+        let valid_day = |y: Option<u32>, m: u32, d: u32| (1..=days_in_month(y, m)).contains(&d);
+        let normalized = match fields {
+            [Some(y), Some(m), Some(d)]
+                if y <= 9999 && valid_month(m) && valid_day(Some(y), m, d) =>
+            {
+                format!("{:04}-{:02}-{:02}", y, m, d)
+            }
+            [Some(y), Some(m), None] if y <= 9999 && valid_month(m) => format!("{:04}-{:02}", y, m),
+            [None, Some(m), Some(d)] if valid_month(m) && valid_day(None, m, d) => {
+                format!("--{:02}-{:02}", m, d)
+            }
+            _ => return None,
+        };
+        Some((end, normalized))
+    }
+
+    /// Hours with optional minutes and seconds: "午前9時30分", "15時半"
+    fn time<T: InputTextIndex>(
+        &self,
+        text: &T,
+        path: &[ResultNode],
+        idx: usize,
+        parser: &mut NumericParser,
+    ) -> Option<(usize, String)> {
+        let (end, prefix) = match find_str(path, idx, &TIME_PREFIXES) {
+            Some((end, prefix)) => (end, Some(prefix)),
+            None => (idx, None),
+        };
+        let (mut end, hour, _) = self.component(text, path, end, parser, &["時"], |u| u)?;
+        let hour: u32 = hour.parse().ok()?;
+        let hour = match prefix {
+            // 午前0時 and 午前12時 are both midnight, 午後0時 and 午後12時 are noon
+            Some("午前") if hour <= 12 => hour % 12,
+            Some(_) if hour <= 12 => hour % 12 + 12,
+            None if hour <= 24 => hour,
+            _ => return None,
+        };
+
+        let mut minute: u32 = 0;
+        let mut second = None;
+        if let Some((e, _)) = find_str(path, end, &["半"]) {
+            minute = 30;
+            end = e;
+        } else if let Some((e, m, _)) = self.component(text, path, end, parser, &["分"], |u| u) {
+            minute = m.parse().ok().filter(|m| *m < 60)?;
+            end = e;
+            if let Some((e, s, _)) = self.component(text, path, end, parser, &["秒"], |u| u) {
+                second = Some(s.parse::<u32>().ok().filter(|s| *s < 60)?);
+                end = e;
+            }
+        }
+
+        let mut normalized = format!("{:02}:{:02}", hour, minute);
+        if let Some(s) = second {
+            write!(normalized, ":{:02}", s).unwrap();
+        }
+        Some((end, normalized))
+    }
+
+    /// Numbers with duration units from the largest one: "3日間", "1時間30分", "2年3か月".
+    /// At least one of the units must mean a duration by itself.
+    fn duration<T: InputTextIndex>(
+        &self,
+        text: &T,
+        path: &[ResultNode],
+        idx: usize,
+        parser: &mut NumericParser,
+    ) -> Option<(usize, String)> {
+        let mut parts: Vec<(DurationUnit, String)> = Vec::new();
+        let mut is_duration = false;
+        let mut end = idx;
+        while let Some((e, number, (_, unit, marker))) =
+            self.component(text, path, end, parser, &DURATION_UNITS, |u| u.0)
+        {
+            if parts.last().is_some_and(|(u, _)| u >= unit) {
+                break;
+            }
+            parts.push((*unit, number));
+            is_duration |= marker;
+            end = e;
+        }
+        if !is_duration {
+            return None;
+        }
+
+        let mut normalized = String::from("P");
+        let mut in_time = false;
+        for (unit, number) in parts {
+            if unit.is_time() && !in_time {
+                normalized.push('T');
+                in_time = true;
+            }
+            normalized.push_str(&number);
+            normalized.push(unit.designator());
+        }
+        Some((end, normalized))
+    }
+
+    /// Finds the longest expression starting at `idx`
+    fn find<T: InputTextIndex>(
+        &self,
+        text: &T,
+        path: &[ResultNode],
+        idx: usize,
+        parser: &mut NumericParser,
+    ) -> Option<(usize, String, u16)> {
+        let kinds = [
+            (ExpressionKind::Date, self.date_pos_id),
+            (ExpressionKind::Time, self.time_pos_id),
+            (ExpressionKind::Duration, self.duration_pos_id),
+        ];
+        let mut best: Option<(usize, String, u16)> = None;
+        for (kind, pos_id) in kinds {
+            let pos_id = match pos_id {
+                Some(p) => p,
+                None => continue,
+            };
+            let found = match kind {
+                ExpressionKind::Date => self.date(text, path, idx, parser),
+                ExpressionKind::Time => self.time(text, path, idx, parser),
+                ExpressionKind::Duration => self.duration(text, path, idx, parser),
+            };
+            if let Some((end, normalized)) = found {
+                if best.as_ref().map_or(true, |b| end > b.0) {
+                    best = Some((end, normalized, pos_id));
+                }
+            }
+        }
+        best
+    }
+
+    fn rewrite_gen<T: InputTextIndex>(
+        &self,
+        text: &T,
+        mut path: Vec<ResultNode>,
+    ) -> SudachiResult<Vec<ResultNode>> {
+        let mut parser = NumericParser::new();
+        let mut i = 0;
+        while i < path.len() {
+            if let Some((end, normalized, pos_id)) = self.find(text, &path, i, &mut parser) {
+                path = concat_nodes(path, i, end, Some(normalized))?;
+                path[i].set_pos_id(pos_id);
+            }
+            i += 1;
+        }
+        Ok(path)
+    }
+}
+
+impl PathRewritePlugin for JoinDateTimePlugin {
+    fn set_up(
+        &mut self,
+        settings: &Value,
+        _config: &Config,
+        grammar: &Grammar,
+    ) -> SudachiResult<()> {
+        let settings: PluginSettings = serde_json::from_value(settings.clone())?;
+
+        self.date_pos_id = pos_id_of(grammar, settings.date.as_ref())?;
+        self.time_pos_id = pos_id_of(grammar, settings.time.as_ref())?;
+        self.duration_pos_id = pos_id_of(grammar, settings.duration.as_ref())?;
+        self.numeric_pos_id = grammar.get_part_of_speech_id(&["名詞", "数詞", "*", "*", "*", "*"]);
+
+        Ok(())
+    }
+
+    fn rewrite(
+        &self,
+        text: &InputBuffer,
+        path: Vec<ResultNode>,
+        _lattice: &Lattice,
+    ) -> SudachiResult<Vec<ResultNode>> {
+        self.rewrite_gen(text, path)
+    }
+}
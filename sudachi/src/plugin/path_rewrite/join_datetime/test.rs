@@ -0,0 +1,234 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use super::*;
+
+use crate::analysis::Node;
+use crate::dic::character_category::CharacterCategory;
+use crate::dic::lexicon::word_infos::WordInfoData;
+use crate::dic::word_id::WordId;
+use crate::test::zero_grammar;
+use lazy_static::lazy_static;
+
+const NUMERIC_POS: u16 = 7;
+const OTHER_POS: u16 = 4;
+const DATE_POS: u16 = 10;
+const TIME_POS: u16 = 11;
+const DURATION_POS: u16 = 12;
+
+fn build_plugin() -> JoinDateTimePlugin {
+    JoinDateTimePlugin {
+        date_pos_id: Some(DATE_POS),
+        time_pos_id: Some(TIME_POS),
+        duration_pos_id: Some(DURATION_POS),
+        numeric_pos_id: Some(NUMERIC_POS),
+    }
+}
+
+/// Builds the path from the surfaces of nodes,
+/// nodes which start with an ASCII digit have the numeric pos
+fn build_path(surfaces: &[&str]) -> (InputBuffer, Vec<ResultNode>) {
+    let data = surfaces.concat();
+    let mut text = InputBuffer::from(data.as_str());
+    text.build(&GRAMMAR).expect("should not fail");
+    let mut path = Vec::new();
+    let mut start_cp = 0;
+    let mut start_b = 0;
+    for s in surfaces {
+        let len_cp = s.chars().count();
+        let pos_id = if s.starts_with(|c: char| c.is_ascii_digit()) {
+            NUMERIC_POS
+        } else {
+            OTHER_POS
+        };
+        path.push(ResultNode::new(
+            Node::new(
+                start_cp as u32,
+                (start_cp + len_cp) as u32,
+                8,
+                8,
+                6000,
+                WordId::oov(pos_id as u32),
+            ),
+            9,
+            start_b as u32,
+            (start_b + s.len()) as u32,
+            WordInfoData {
+                surface: s.to_string(),
+                head_word_length: s.len() as u16,
+                pos_id,
+                normalized_form: s.to_string(),
+                dictionary_form_word_id: -1,
+                dictionary_form: s.to_string(),
+                ..Default::default()
+            }
+            .into(),
+        ));
+        start_cp += len_cp;
+        start_b += s.len();
+    }
+    (text, path)
+}
+
+/// Returns (surface, normalized form, pos id) of the rewritten path
+fn rewrite(plugin: &JoinDateTimePlugin, surfaces: &[&str]) -> Vec<(String, String, u16)> {
+    let (text, path) = build_path(surfaces);
+    plugin
+        .rewrite(&text, path, &Lattice::default())
+        .expect("Failed to rewrite path")
+        .iter()
+        .map(|n| {
+            let wi = n.word_info();
+            (
+                wi.surface().to_owned(),
+                wi.normalized_form().to_owned(),
+                wi.pos_id(),
+            )
+        })
+        .collect()
+}
+
+fn joined(plugin: &JoinDateTimePlugin, surfaces: &[&str]) -> Option<(String, u16)> {
+    match rewrite(plugin, surfaces).as_slice() {
+        [(_, norm, pos)] => Some((norm.clone(), *pos)),
+        _ => None,
+    }
+}
+
+fn t(norm: &str, pos: u16) -> Option<(String, u16)> {
+    Some((norm.to_owned(), pos))
+}
+
+#[test]
+fn dates() {
+    let p = build_plugin();
+    assert_eq!(
+        joined(&p, &["2024", "年", "10", "月", "17", "日"]),
+        t("2024-10-17", DATE_POS)
+    );
+    assert_eq!(
+        joined(&p, &["2024", "年", "2", "月"]),
+        t("2024-02", DATE_POS)
+    );
+    assert_eq!(joined(&p, &["10", "月", "7", "日"]), t("--10-07", DATE_POS));
+    assert_eq!(
+        joined(
+            &p,
+            &["二", "〇", "二", "四", "年", "十", "月", "十", "七", "日"]
+        ),
+        t("2024-10-17", DATE_POS)
+    );
+    assert_eq!(
+        joined(&p, &["2024", "年", "2", "月", "29", "日"]),
+        t("2024-02-29", DATE_POS)
+    );
+}
+
+#[test]
+fn invalid_dates() {
+    let p = build_plugin();
+    assert_eq!(joined(&p, &["2023", "年", "2", "月", "29", "日"]), None);
+    assert_eq!(joined(&p, &["13", "月", "1", "日"]), None);
+    // a single component is not a date
+    assert_eq!(rewrite(&p, &["2024", "年", "に"]).len(), 3);
+    // components must be in order
+    assert_eq!(rewrite(&p, &["17", "日", "10", "月"]).len(), 4);
+}
+
+#[test]
+fn times() {
+    let p = build_plugin();
+    assert_eq!(
+        joined(&p, &["午前", "9", "時", "30", "分"]),
+        t("09:30", TIME_POS)
+    );
+    assert_eq!(joined(&p, &["午後", "3", "時", "半"]), t("15:30", TIME_POS));
+    assert_eq!(joined(&p, &["午前", "12", "時"]), t("00:00", TIME_POS));
+    assert_eq!(
+        joined(&p, &["18", "時", "5", "分", "7", "秒"]),
+        t("18:05:07", TIME_POS)
+    );
+    assert_eq!(joined(&p, &["午後", "13", "時"]), None);
+    assert_eq!(rewrite(&p, &["9", "時", "60", "分"]).len(), 4);
+}
+
+#[test]
+fn durations() {
+    let p = build_plugin();
+    assert_eq!(joined(&p, &["3", "日間"]), t("P3D", DURATION_POS));
+    assert_eq!(joined(&p, &["3", "日", "間"]), t("P3D", DURATION_POS));
+    assert_eq!(
+        joined(&p, &["1", "時間", "30", "分"]),
+        t("PT1H30M", DURATION_POS)
+    );
+    assert_eq!(
+        joined(&p, &["2", "年", "3", "ヶ月"]),
+        t("P2Y3M", DURATION_POS)
+    );
+    assert_eq!(joined(&p, &["1.5", "時間"]), t("PT1.5H", DURATION_POS));
+    assert_eq!(joined(&p, &["2", "週間"]), t("P2W", DURATION_POS));
+    // "3日" alone is not a duration, "3日" and "3年" are not dates
+    assert_eq!(rewrite(&p, &["3", "日"]).len(), 2);
+    assert_eq!(rewrite(&p, &["3", "年"]).len(), 2);
+}
+
+#[test]
+fn longest_expression_wins() {
+    let p = build_plugin();
+    // "1時" is a time, but "1時間30分" is a longer duration
+    assert_eq!(
+        joined(&p, &["1", "時", "間", "30", "分"]),
+        t("PT1H30M", DURATION_POS)
+    );
+}
+
+#[test]
+fn expressions_in_sentence() {
+    let p = build_plugin();
+    let result = rewrite(
+        &p,
+        &[
+            "2024", "年", "10", "月", "17", "日", "午後", "3", "時", "から", "2", "時間",
+        ],
+    );
+    let norms: Vec<_> = result.iter().map(|(_, n, _)| n.as_str()).collect();
+    assert_eq!(norms, ["2024-10-17", "15:00", "から", "PT2H"]);
+    assert_eq!(result[0].0, "2024年10月17日");
+}
+
+#[test]
+fn only_configured_kinds() {
+    let p = JoinDateTimePlugin {
+        time_pos_id: None,
+        ..build_plugin()
+    };
+    assert_eq!(rewrite(&p, &["午前", "9", "時"]).len(), 3);
+    assert_eq!(joined(&p, &["10", "月", "7", "日"]), t("--10-07", DATE_POS));
+}
+
+const CHAR_DEF: &[u8] = include_bytes!("../join_numeric/test_char.def");
+
+fn build_mock_grammar() -> Grammar<'static> {
+    let mut grammar = zero_grammar();
+    let char_cat =
+        CharacterCategory::from_reader(CHAR_DEF).expect("Failed to load character category");
+    grammar.set_character_category(char_cat);
+    grammar
+}
+
+lazy_static! {
+    static ref GRAMMAR: Grammar<'static> = build_mock_grammar();
+}
@@ -137,6 +137,33 @@ fn with_noovbow() {
     assert_eq!(1, path.len());
 }
 
+#[test]
+fn joined_readings() {
+    let plugin = JoinKatakanaOovPlugin {
+        min_length: 3,
+        ..Default::default()
+    };
+    let text = build_text("アイウァアイウ");
+    let mut path = vec![
+        build_node_aiu(0, 9, 5562),
+        build_node_oov(9, 12, 13613, "ァ"),
+        build_node_aiu(12, 21, 21135),
+    ];
+    path[0].set_reading_form("アイウ".to_owned());
+    path[2].set_reading_form("アイウ".to_owned());
+    let joined = plugin
+        .rewrite(&text, path.clone(), &Lattice::default())
+        .expect("Failed to rewrite path");
+    // the OOV part has no reading, so the joined word does not have it too
+    assert_eq!("", joined[0].word_info().borrow_data().reading_form);
+
+    path[1].set_reading_form("ァ".to_owned());
+    let joined = plugin
+        .rewrite(&text, path, &Lattice::default())
+        .expect("Failed to rewrite path");
+    assert_eq!("アイウァアイウ", joined[0].word_info().reading_form());
+}
+
 fn build_node_ai(start: usize, end: usize, cost: i32) -> ResultNode {
     build_node(start, end, cost, "アイ")
 }
@@ -148,8 +175,8 @@ fn build_node_aiu(start: usize, end: usize, cost: i32) -> ResultNode {
 fn build_node(start: usize, end: usize, cost: i32, surface: &str) -> ResultNode {
     let cstart = start / 3;
     let node = Node::new(
-        cstart as u16,
-        (cstart + surface.chars().count()) as u16,
+        cstart as u32,
+        (cstart + surface.chars().count()) as u32,
         7,
         7,
         3000,
@@ -158,8 +185,8 @@ fn build_node(start: usize, end: usize, cost: i32, surface: &str) -> ResultNode
     ResultNode::new(
         node,
         cost,
-        start as u16,
-        end as u16,
+        start as u32,
+        end as u32,
         WordInfoData {
             surface: surface.to_string(),
             normalized_form: surface.to_string(),
@@ -175,8 +202,8 @@ fn build_node(start: usize, end: usize, cost: i32, surface: &str) -> ResultNode
 fn build_node_oov(start: usize, end: usize, cost: i32, surface: &str) -> ResultNode {
     let cstart = start / 3;
     let node = Node::new(
-        cstart as u16,
-        (cstart + surface.chars().count()) as u16,
+        cstart as u32,
+        (cstart + surface.chars().count()) as u32,
         8,
         8,
         6000,
@@ -185,8 +212,8 @@ fn build_node_oov(start: usize, end: usize, cost: i32, surface: &str) -> ResultN
     ResultNode::new(
         node,
         cost,
-        start as u16,
-        end as u16,
+        start as u32,
+        end as u32,
         WordInfoData {
             surface: surface.to_string(),
             normalized_form: surface.to_string(),
@@ -14,21 +14,25 @@
  * limitations under the License.
  */
 
+use std::sync::Arc;
+
 use serde::Deserialize;
 use serde_json::Value;
 
 use self::numeric_parser::NumericParser;
 use crate::analysis::lattice::Lattice;
 use crate::analysis::node::{concat_nodes, LatticeNode, ResultNode};
+use crate::analysis::numeric::NumericValue;
 use crate::config::Config;
 use crate::dic::category_type::CategoryType;
 use crate::dic::grammar::Grammar;
+use crate::dic::oov_reading::OovReading;
 use crate::input_text::InputBuffer;
 use crate::input_text::InputTextIndex;
 use crate::plugin::path_rewrite::PathRewritePlugin;
 use crate::prelude::*;
 
-mod numeric_parser;
+pub(crate) mod numeric_parser;
 #[cfg(test)]
 mod test;
 
@@ -39,41 +43,129 @@ pub struct JoinNumericPlugin {
     numeric_pos_id: u16,
     /// Whether if to normalize the normalized_form
     enable_normalize: bool,
+    /// Reading estimation for numbers which contain OOV words
+    oov_reading: Option<Arc<OovReading>>,
+    /// Units which are joined with the preceding number, empty if units are not joined
+    units: Vec<String>,
 }
 
+/// Units which are joined when `joinUnits` is enabled without the list of `units`.
+///
+/// Only words which are units after a number in almost any context are included:
+/// counters (人, 本) and single letters (m, g, t) also appear after numbers in other roles,
+/// e.g. "2g network", and can be added with `units`.
+const DEFAULT_UNITS: &[&str] = &[
+    "円",
+    "ドル",
+    "ユーロ",
+    "元",
+    "ウォン",
+    "%",
+    "パーセント",
+    "割",
+    "倍",
+    "km",
+    "cm",
+    "mm",
+    "kg",
+    "mg",
+    "ml",
+    "キロ",
+    "メートル",
+    "キロメートル",
+    "センチ",
+    "グラム",
+    "キログラム",
+    "トン",
+    "リットル",
+];
+
+/// Unit words are matched over at most this number of nodes
+const MAX_UNIT_NODES: usize = 3;
+
 /// Struct corresponds with raw config json file.
 #[allow(non_snake_case)]
 #[derive(Deserialize)]
 struct PluginSettings {
     enableNormalize: Option<bool>,
+    #[serde(default)]
+    joinUnits: bool,
+    units: Option<Vec<String>>,
 }
 
 impl JoinNumericPlugin {
+    /// Concatenates the number in the range and sets its value.
+    /// If `with_unit` is true, the unit which follows the number is joined with it.
+    /// Returns the rewritten path and whether a unit was joined.
     fn concat(
         &self,
         mut path: Vec<ResultNode>,
         begin: usize,
         end: usize,
         parser: &mut NumericParser,
-    ) -> SudachiResult<Vec<ResultNode>> {
+        with_unit: bool,
+    ) -> SudachiResult<(Vec<ResultNode>, bool)> {
         let word_info = path[begin].word_info();
 
         if word_info.pos_id() != self.numeric_pos_id {
-            return Ok(path);
+            return Ok((path, false));
         }
 
+        let has_oov = path[begin..end].iter().any(|n| n.is_oov());
+        let value = parser.get_normalized();
+
         if self.enable_normalize {
-            let normalized_form = parser.get_normalized();
-            if end - begin > 1 || normalized_form != word_info.normalized_form() {
-                path = concat_nodes(path, begin, end, Some(normalized_form))?;
+            if end - begin > 1 || value != word_info.normalized_form() {
+                path = concat_nodes(path, begin, end, Some(value.clone()))?;
             }
-            return Ok(path);
+        } else if end - begin > 1 {
+            path = concat_nodes(path, begin, end, None)?;
         }
 
-        if end - begin > 1 {
-            path = concat_nodes(path, begin, end, None)?;
+        if has_oov {
+            self.estimate_reading(&mut path[begin]);
+        }
+
+        let unit = match self.find_unit(&path, begin + 1) {
+            Some((unit_end, unit)) if with_unit => {
+                let normalized_form =
+                    format!("{}{}", path[begin].word_info().normalized_form(), unit);
+                path = concat_nodes(path, begin, unit_end, Some(normalized_form))?;
+                Some(unit)
+            }
+            _ => None,
+        };
+        let joined_unit = unit.is_some();
+        path[begin].set_numeric_value(NumericValue::new(value, unit));
+        Ok((path, joined_unit))
+    }
+
+    /// Finds the longest unit which is equal to normalized forms of the nodes starting at `idx`.
+    /// Returns the end index of the nodes and the unit.
+    fn find_unit(&self, path: &[ResultNode], idx: usize) -> Option<(usize, String)> {
+        let mut text = String::new();
+        let mut found = None;
+        for (i, node) in path.iter().enumerate().skip(idx).take(MAX_UNIT_NODES) {
+            text.push_str(node.word_info().normalized_form());
+            if self.units.contains(&text) {
+                found = Some((i + 1, text.clone()));
+            }
+            if !self.units.iter().any(|u| u.starts_with(text.as_str())) {
+                break;
+            }
+        }
+        found
+    }
+
+    /// Readings of joined OOV digits are estimated for the whole number
+    fn estimate_reading(&self, node: &mut ResultNode) {
+        let reading = self
+            .oov_reading
+            .as_ref()
+            .and_then(|r| r.estimate(node.word_info().normalized_form()));
+        if let Some(reading) = reading {
+            node.set_reading_form(reading);
         }
-        Ok(path)
     }
 
     fn rewrite_gen<T: InputTextIndex>(
@@ -128,15 +220,27 @@ impl JoinNumericPlugin {
 
             if begin_idx >= 0 {
                 if parser.done() {
-                    path = self.concat(path, begin_idx as usize, i as usize, &mut parser)?;
-                    i = begin_idx + 1;
+                    let joined_unit;
+                    (path, joined_unit) =
+                        self.concat(path, begin_idx as usize, i as usize, &mut parser, true)?;
+                    // the current node is a part of the unit, continue after it
+                    i = if joined_unit {
+                        begin_idx
+                    } else {
+                        begin_idx + 1
+                    };
                 } else {
                     let ss = path[i as usize - 1].word_info().normalized_form();
                     if (parser.error_state == numeric_parser::Error::Comma && ss == ",")
                         || (parser.error_state == numeric_parser::Error::Point && ss == ".")
                     {
-                        path =
-                            self.concat(path, begin_idx as usize, i as usize - 1, &mut parser)?;
+                        (path, _) = self.concat(
+                            path,
+                            begin_idx as usize,
+                            i as usize - 1,
+                            &mut parser,
+                            false,
+                        )?;
                         i = begin_idx + 2;
                     }
                 }
@@ -154,13 +258,14 @@ impl JoinNumericPlugin {
         if begin_idx >= 0 {
             let len = path.len();
             if parser.done() {
-                path = self.concat(path, begin_idx as usize, len, &mut parser)?;
+                (path, _) = self.concat(path, begin_idx as usize, len, &mut parser, false)?;
             } else {
                 let ss = path[len - 1].word_info().normalized_form();
                 if (parser.error_state == numeric_parser::Error::Comma && ss == ",")
                     || (parser.error_state == numeric_parser::Error::Point && ss == ".")
                 {
-                    path = self.concat(path, begin_idx as usize, len - 1, &mut parser)?;
+                    (path, _) =
+                        self.concat(path, begin_idx as usize, len - 1, &mut parser, false)?;
                 }
             }
         }
@@ -187,6 +292,12 @@ impl PathRewritePlugin for JoinNumericPlugin {
 
         self.numeric_pos_id = numeric_pos_id;
         self.enable_normalize = enable_normalize.unwrap_or(true);
+        self.oov_reading = grammar.oov_reading.clone();
+        self.units = match (settings.joinUnits, settings.units) {
+            (false, _) => Vec::new(),
+            (true, Some(units)) => units,
+            (true, None) => DEFAULT_UNITS.iter().map(|u| u.to_string()).collect(),
+        };
 
         Ok(())
     }
@@ -356,19 +356,112 @@ fn single_node() {
     assert_eq!("三", path[1].word_info().normalized_form());
 }
 
+#[test]
+fn numeric_value() {
+    let plugin = build_plugin();
+    let text = build_text("一二三万二千円");
+    let path = vec![
+        build_node_num("一", "一", 0, 0),
+        build_node_num("二", "二", 1, 3),
+        build_node_num("三", "三", 2, 6),
+        build_node_oov("万", "万", 3, 9),
+        build_node_num("二", "二", 4, 12),
+        build_node_oov("千", "千", 5, 15),
+        build_node_oov("円", "円", 6, 18),
+    ];
+    let path = plugin
+        .rewrite(&text, path, &Lattice::default())
+        .expect("Failed to rewrite path");
+    assert_eq!(2, path.len());
+    let value = path[0].numeric_value().expect("has value");
+    assert_eq!(Some(1232000), value.to_i64());
+    assert_eq!(None, value.unit());
+    assert_eq!(None, path[1].numeric_value());
+}
+
+#[test]
+fn join_units() {
+    let mut plugin = build_plugin();
+    plugin.units = vec!["円".to_owned(), "人".to_owned(), "km".to_owned()];
+    let text = build_text("3万人と1.5km");
+    let path = vec![
+        build_node_num("3", "3", 0, 0),
+        build_node_oov("万", "万", 1, 1),
+        build_node_oov("人", "人", 2, 4),
+        build_node_oov("と", "と", 3, 7),
+        build_node_num("1", "1", 4, 10),
+        build_node_oov(".", ".", 5, 11),
+        build_node_num("5", "5", 6, 12),
+        build_node_oov("k", "k", 7, 13),
+        build_node_oov("m", "m", 8, 14),
+    ];
+    let path = plugin
+        .rewrite(&text, path, &Lattice::default())
+        .expect("Failed to rewrite path");
+    assert_eq!(3, path.len());
+    assert_eq!("3万人", path[0].word_info().surface());
+    assert_eq!("30000人", path[0].word_info().normalized_form());
+    let value = path[0].numeric_value().expect("has value");
+    assert_eq!(("30000", Some("人")), (value.as_str(), value.unit()));
+    assert_eq!("1.5km", path[2].word_info().surface());
+    let value = path[2].numeric_value().expect("has value");
+    assert_eq!(("1.5", Some("km")), (value.as_str(), value.unit()));
+}
+
+#[test]
+fn join_units_in_sequence() {
+    let mut plugin = build_plugin();
+    plugin.units = vec!["円".to_owned()];
+    let text = build_text("1円2円");
+    let path = vec![
+        build_node_num("1", "1", 0, 0),
+        build_node_oov("円", "円", 1, 1),
+        build_node_num("2", "2", 2, 4),
+        build_node_oov("円", "円", 3, 5),
+    ];
+    let path = plugin
+        .rewrite(&text, path, &Lattice::default())
+        .expect("Failed to rewrite path");
+    assert_eq!(2, path.len());
+    assert_eq!("1円", path[0].word_info().surface());
+    assert_eq!("2円", path[1].word_info().surface());
+}
+
+#[test]
+fn default_units_are_unambiguous() {
+    let mut plugin = build_plugin();
+    plugin.units = DEFAULT_UNITS.iter().map(|u| u.to_string()).collect();
+    let text = build_text("2g5本3円");
+    let path = vec![
+        build_node_num("2", "2", 0, 0),
+        build_node_oov("g", "g", 1, 1),
+        build_node_num("5", "5", 2, 2),
+        build_node_oov("本", "本", 3, 3),
+        build_node_num("3", "3", 4, 6),
+        build_node_oov("円", "円", 5, 7),
+    ];
+    let path = plugin
+        .rewrite(&text, path, &Lattice::default())
+        .expect("Failed to rewrite path");
+    let surfaces: Vec<_> = path.iter().map(|n| n.word_info().surface()).collect();
+    assert_eq!(["2", "g", "5", "本", "3円"], surfaces.as_slice());
+    assert_eq!(None, path[1].numeric_value());
+    assert_eq!(Some(None), path[2].numeric_value().map(|v| v.unit()));
+}
+
 fn build_node_num(surface: &str, normalized: &str, start_cp: usize, start_b: usize) -> ResultNode {
     ResultNode::new(
         Node::new(
-            start_cp as u16,
-            (start_cp + surface.chars().count()) as u16,
+            start_cp as u32,
+            (start_cp + surface.chars().count()) as u32,
             9,
             9,
             2478,
             WordId::new(0, 1),
         ),
         9,
-        start_b as u16,
-        (start_b + surface.len()) as u16,
+        start_b as u32,
+        (start_b + surface.len()) as u32,
         WordInfoData {
             surface: surface.to_string(),
             head_word_length: surface.len() as u16,
@@ -384,16 +477,16 @@ fn build_node_num(surface: &str, normalized: &str, start_cp: usize, start_b: usi
 fn build_node_oov(surface: &str, normalized: &str, start_cp: usize, start_b: usize) -> ResultNode {
     ResultNode::new(
         Node::new(
-            start_cp as u16,
-            (start_cp + surface.chars().count()) as u16,
+            start_cp as u32,
+            (start_cp + surface.chars().count()) as u32,
             8,
             8,
             6000,
             WordId::oov(4),
         ),
         9,
-        start_b as u16,
-        (start_b + surface.len()) as u16,
+        start_b as u32,
+        (start_b + surface.len()) as u32,
         WordInfoData {
             surface: surface.to_string(),
             head_word_length: surface.len() as u16,
@@ -411,6 +504,8 @@ fn build_plugin() -> JoinNumericPlugin {
     JoinNumericPlugin {
         numeric_pos_id: 7,
         enable_normalize: true,
+        oov_reading: None,
+        units: Vec::new(),
     }
 }
 
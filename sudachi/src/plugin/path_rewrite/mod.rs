@@ -14,6 +14,8 @@
  * limitations under the License.
  */
 
+pub mod join_compound_noun;
+pub mod join_datetime;
 pub mod join_katakana_oov;
 pub mod join_numeric;
 
@@ -24,6 +26,8 @@ use crate::analysis::node::ResultNode;
 use crate::config::Config;
 use crate::dic::grammar::Grammar;
 use crate::input_text::InputBuffer;
+use crate::plugin::path_rewrite::join_compound_noun::JoinCompoundNounPlugin;
+use crate::plugin::path_rewrite::join_datetime::JoinDateTimePlugin;
 use crate::plugin::path_rewrite::join_katakana_oov::JoinKatakanaOovPlugin;
 use crate::plugin::path_rewrite::join_numeric::JoinNumericPlugin;
 use crate::plugin::PluginCategory;
@@ -55,6 +59,8 @@ impl PluginCategory for dyn PathRewritePlugin {
         match name {
             "JoinNumericPlugin" => Some(Box::<JoinNumericPlugin>::default()),
             "JoinKatakanaOovPlugin" => Some(Box::<JoinKatakanaOovPlugin>::default()),
+            "JoinDateTimePlugin" => Some(Box::<JoinDateTimePlugin>::default()),
+            "JoinCompoundNounPlugin" => Some(Box::<JoinCompoundNounPlugin>::default()),
             _ => None,
         }
     }
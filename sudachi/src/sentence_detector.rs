@@ -92,6 +92,11 @@ impl SentenceDetector {
         SentenceDetector { limit }
     }
 
+    /// Returns the maximum number of characters processed at once
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
     /// Returns the byte index of the detected end of the sentence.
     ///
     /// If NonBreakChecker is given, it is used to determine if there is a
@@ -109,12 +114,30 @@ impl SentenceDetector {
     /// assert_eq!(-15, sd.get_eos("あいうえお", None).unwrap());
     /// ```
     pub fn get_eos(&self, input: &str, checker: Option<&NonBreakChecker>) -> SudachiResult<isize> {
+        self.get_eos_resumable(input, &mut EosSearchState::default(), checker)
+    }
+
+    /// Same as [`SentenceDetector::get_eos`], but when no boundary is found,
+    /// `state` remembers the part of the input which does not need to be checked again.
+    ///
+    /// It is used when text is appended to the input until a boundary is found,
+    /// the state must be reset when the input is changed in any other way.
+    pub(crate) fn get_eos_resumable(
+        &self,
+        input: &str,
+        state: &mut EosSearchState,
+        checker: Option<&NonBreakChecker>,
+    ) -> SudachiResult<isize> {
         if input.is_empty() {
             return Ok(0);
         }
 
         // handle at most self.limit chars at once
-        let s: String = input.chars().take(self.limit).collect();
+        let limit_end = input[state.start..]
+            .char_indices()
+            .nth(self.limit.saturating_sub(state.start_chars))
+            .map_or(input.len(), |(i, _)| state.start + i);
+        let s = &input[..limit_end];
         let input_exceeds_limit = s.len() < input.len();
 
         lazy_static! {
@@ -135,19 +158,30 @@ impl SentenceDetector {
                 Regex::new(&format!("^([{}])([{}])$", ALPHABET_OR_NUMBER, DOT)).unwrap();
         }
 
-        for mat in SENTENCE_BREAKER.find_iter(&s) {
+        // parenthesis level at the end of the last match
+        let (mut level_pos, mut level) = (state.start, state.level);
+        // candidates which can be accepted when more text is appended start after it
+        let mut restart = s.len();
+        let mut pos = state.start;
+        while let Some(mat) = SENTENCE_BREAKER.find_from_pos(s, pos)? {
+            pos = mat.end();
             // check if we can split at the match
-            let mut eos = mat?.end();
-            if parenthesis_level(&s[..eos])? > 0 {
-                continue;
-            }
+            let mut eos = mat.end();
+            level = parenthesis_level_from(level, &s[level_pos..eos])?;
+            level_pos = eos;
             if eos < s.len() {
                 eos += prohibited_bos(&s[eos..])?;
             }
-            if ITEMIZE_HEADER.is_match(&s)? {
+            if eos == s.len() {
+                restart = restart.min(mat.start());
+            }
+            if level > 0 {
+                continue;
+            }
+            if ITEMIZE_HEADER.is_match(s)? {
                 continue;
             }
-            if eos < s.len() && is_continuous_phrase(&s, eos)? {
+            if eos < s.len() && is_continuous_phrase(s, eos)? {
                 continue;
             }
             if let Some(ck) = checker {
@@ -163,17 +197,43 @@ impl SentenceDetector {
             lazy_static! {
                 static ref SPACES: Regex = Regex::new(".+\\s+").unwrap();
             }
-            if let Some(mat) = SPACES.find(&s)? {
+            if let Some(mat) = SPACES.find(s)? {
                 return Ok(-(mat.end() as isize));
             }
+        } else {
+            // the last chars can become a part of a new match, e.g. "・・" or "<br><b"
+            let tail = s
+                .char_indices()
+                .rev()
+                .nth(RESUME_MARGIN - 1)
+                .map_or(0, |(i, _)| i);
+            let restart = restart.min(tail).max(state.start);
+            state.level = parenthesis_level_from(state.level, &s[state.start..restart])?;
+            state.start_chars += s[state.start..restart].chars().count();
+            state.start = restart;
         }
 
         Ok(-(s.len() as isize))
     }
 }
 
-/// Returns the count of non-closed open parentheses remaining at the end of input.
-fn parenthesis_level(s: &str) -> SudachiResult<usize> {
+/// Part of the input which was already checked by [`SentenceDetector::get_eos_resumable`]
+#[derive(Default, Clone, Debug)]
+pub(crate) struct EosSearchState {
+    /// Byte offset from which boundary candidates are searched
+    start: usize,
+    /// Number of chars before `start`
+    start_chars: usize,
+    /// Parenthesis level at `start`
+    level: usize,
+}
+
+/// Number of last chars which are checked again by the resumed search
+const RESUME_MARGIN: usize = 8;
+
+/// Returns the count of non-closed open parentheses remaining at the end of input,
+/// which follows text with `level` non-closed parentheses.
+fn parenthesis_level_from(mut level: usize, s: &str) -> SudachiResult<usize> {
     lazy_static! {
         static ref PARENTHESIS: Regex = Regex::new(&format!(
             "([{}])|([{}])",
@@ -181,7 +241,6 @@ fn parenthesis_level(s: &str) -> SudachiResult<usize> {
         ))
         .unwrap();
     }
-    let mut level: usize = 0;
     for caps in PARENTHESIS.captures_iter(s) {
         if caps?.get(1).is_some() {
             // open
@@ -309,4 +368,31 @@ mod tests {
         assert_eq!(sd.get_eos("1.やb.から。", None).unwrap(), 16);
         assert_eq!(sd.get_eos("1.の12.が。", None).unwrap(), 14);
     }
+
+    #[test]
+    fn resumed_search_is_the_same_as_full() {
+        let sd = SentenceDetector::new();
+        // text is appended char by char, boundaries are not consumed
+        for text in [
+            "あいう。えお",
+            "あ（いう。え）お。か",
+            "あいう・・・えお",
+            "あいう<br><br>えお",
+            "あいう！？」と言った。",
+            "1. あいう。えお",
+            "3.141です。",
+            "あいう。。。。。。。。。。」えお",
+        ] {
+            // search is resumed only after the first few chars
+            let text = format!("かきくけこさしすせそ{}", text);
+            let mut state = EosSearchState::default();
+            let ends = text.char_indices().skip(1).map(|(i, _)| i);
+            for end in ends.chain([text.len()]) {
+                let prefix = &text[..end];
+                let expected = sd.get_eos(prefix, None).unwrap();
+                let actual = sd.get_eos_resumable(prefix, &mut state, None).unwrap();
+                assert_eq!(expected, actual, "{}", prefix);
+            }
+        }
+    }
 }
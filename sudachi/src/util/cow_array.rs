@@ -17,6 +17,7 @@
 use std::array::TryFromSliceError;
 use std::convert::TryInto;
 use std::ops::Deref;
+use std::sync::Arc;
 
 pub trait ReadLE {
     fn from_le_bytes(bytes: &[u8]) -> Result<Self, TryFromSliceError>
@@ -47,16 +48,17 @@ impl ReadLE for u32 {
 /// See: https://rust.godbolt.org/z/r4a9efjqh
 ///
 /// It implements Deref for `&[T]`, so it can be used as slice.
+/// Owned data is shared between clones until one of them is modified.
 pub struct CowArray<'a, T> {
     slice: &'a [T],
-    storage: Option<Vec<T>>,
+    storage: Option<Arc<Vec<T>>>,
 }
 
 impl<T: ReadLE + Clone> CowArray<'static, T> {
     /// Creates from the owned data
     pub fn from_owned<D: Into<Vec<T>>>(data: D) -> Self {
-        let data = data.into();
-        let slice1: &[T] = &data;
+        let data = Arc::new(data.into());
+        let slice1: &[T] = data.as_slice();
         let slice: &'static [T] = unsafe { std::mem::transmute(slice1) };
         Self {
             storage: Some(data),
@@ -84,7 +86,7 @@ impl<'a, T: ReadLE + Clone> CowArray<'a, T> {
                 storage: None,
             }
         } else {
-            let data = copy_of_bytes::<T>(real_slice);
+            let data = Arc::new(copy_of_bytes::<T>(real_slice));
             let slice_1: &[T] = data.as_slice();
             // we need transmute to make correct lifetime
             // slice will always point to vector contents and it is impossible to have
@@ -105,14 +107,24 @@ impl<'a, T: ReadLE + Clone> CowArray<'a, T> {
     /// 1. this object does not expose any references outside
     /// 2. usage of data still follows the pattern 1-mut xor many-read
     pub fn set(&mut self, offset: usize, value: T) {
-        if self.storage.is_none() {
-            self.storage = Some(self.slice.to_vec());
-            //refresh slice
-            let slice: &[T] = self.storage.as_ref().unwrap().as_slice();
-            self.slice = unsafe { std::mem::transmute(slice) };
-        }
-        if let Some(s) = self.storage.as_mut() {
-            s[offset] = value;
+        let storage = self
+            .storage
+            .get_or_insert_with(|| Arc::new(self.slice.to_vec()));
+        // copies the data if it is shared with a clone
+        let data = Arc::make_mut(storage);
+        data[offset] = value;
+        //refresh slice
+        let slice: &[T] = data.as_slice();
+        self.slice = unsafe { std::mem::transmute(slice) };
+    }
+}
+
+impl<'a, T> Clone for CowArray<'a, T> {
+    fn clone(&self) -> Self {
+        // slice points to the shared storage, so it stays valid
+        Self {
+            slice: self.slice,
+            storage: self.storage.clone(),
         }
     }
 }
@@ -146,6 +158,19 @@ fn copy_of_bytes<T: ReadLE>(data: &[u8]) -> Vec<T> {
 mod test {
     use super::*;
 
+    #[test]
+    fn clone_is_not_affected_by_set() {
+        let mut a = CowArray::from_owned(vec![1i16, 2, 3]);
+        let b = a.clone();
+        a.set(1, 5);
+        assert_eq!(&[1, 5, 3], &a[..]);
+        assert_eq!(&[1, 2, 3], &b[..]);
+        let mut c = b.clone();
+        c.set(0, 4);
+        assert_eq!(&[4, 2, 3], &c[..]);
+        assert_eq!(&[1, 2, 3], &b[..]);
+    }
+
     #[test]
     fn aligned_1() {
         assert!(is_aligned(0, 1));
@@ -0,0 +1,92 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use sudachi::analysis::batch_tokenizer::BatchTokenizer;
+use sudachi::analysis::stateless_tokenizer::DictionaryAccess;
+use sudachi::dic::dictionary::JapaneseDictionary;
+use sudachi::prelude::*;
+
+mod common;
+use common::{TestTokenizer, TEST_CONFIG};
+
+fn dictionary() -> JapaneseDictionary {
+    JapaneseDictionary::from_cfg(&TEST_CONFIG).expect("failed to make dictionary")
+}
+
+fn surfaces<D: DictionaryAccess>(list: &MorphemeList<D>) -> Vec<String> {
+    list.iter().map(|m| m.surface().to_string()).collect()
+}
+
+#[test]
+fn batch_results_are_ordered() {
+    let dict = dictionary();
+    let inputs: Vec<String> = (0..100)
+        .map(|i| match i % 4 {
+            0 => "東京都".to_owned(),
+            1 => "京都に行った".to_owned(),
+            2 => format!("{}", i),
+            _ => "".to_owned(),
+        })
+        .collect();
+    let results = dict.tokenize_batch(&inputs, Mode::C).expect("works");
+    assert_eq!(inputs.len(), results.len());
+
+    let reference = TestTokenizer::new();
+    for (input, result) in inputs.iter().zip(results.iter()) {
+        assert_eq!(input, result.surface().deref());
+        let expected = reference.tokenize(input, Mode::C);
+        assert_eq!(surfaces(&expected), surfaces(result));
+    }
+}
+
+#[test]
+fn batch_uses_mode() {
+    let dict = dictionary();
+    let results = dict.tokenize_batch(&["東京都"], Mode::A).expect("works");
+    assert_eq!(vec!["東京", "都"], surfaces(&results[0]));
+}
+
+#[test]
+fn batch_tokenizer_is_reusable() {
+    let dict = Arc::new(dictionary());
+    let tok = BatchTokenizer::new(dict, Mode::C).with_threads(3);
+    assert_eq!(3, tok.num_threads());
+    for _ in 0..3 {
+        let results = tok.tokenize(&["東京都", "京都", "都"]).expect("works");
+        let all: Vec<_> = results.iter().map(surfaces).collect();
+        assert_eq!(vec![vec!["東京都"], vec!["京都"], vec!["都"]], all);
+    }
+}
+
+#[test]
+fn batch_single_thread() {
+    let dict = dictionary();
+    let tok = BatchTokenizer::new(&dict, Mode::C).with_threads(1);
+    let results = tok.tokenize(&["京都", "東京都"]).expect("works");
+    assert_eq!(2, results.len());
+    assert_eq!("東京都", results[1].get(0).surface().deref());
+}
+
+#[test]
+fn empty_batch() {
+    let dict = dictionary();
+    let inputs: [&str; 0] = [];
+    let results = dict.tokenize_batch(&inputs, Mode::C).expect("works");
+    assert!(results.is_empty());
+}
@@ -80,7 +80,7 @@ lazy_static! {
             .expect("Failed to read grammar for tests");
     pub static ref LEXICON: Lexicon<'static> = {
         let offset = Header::STORAGE_SIZE + GRAMMAR.storage_size;
-        let mut lex = Lexicon::parse(&DICTIONARY_BYTES, offset, HEADER.has_synonym_group_ids())
+        let mut lex = Lexicon::parse_with_header(&DICTIONARY_BYTES, offset, &HEADER)
             .expect("Failed to read lexicon for tests");
         lex.set_dic_id(0);
         lex
@@ -108,6 +108,16 @@ impl TestTokenizer {
         result.expect("tokenization failed")
     }
 
+    pub fn tokenize_n_best<'a>(
+        &'a self,
+        data: &'a str,
+        mode: Mode,
+        n: usize,
+    ) -> Vec<(MorphemeList<Arc<JapaneseDictionary>>, i32)> {
+        let result = self.tok.tokenize_n_best(data, mode, n, false);
+        result.expect("tokenization failed")
+    }
+
     pub fn dict(&self) -> &JapaneseDictionary {
         self.tok.as_dict()
     }
@@ -0,0 +1,127 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::ops::Deref;
+use sudachi::analysis::constraints::Constraints;
+use sudachi::prelude::*;
+
+mod common;
+use crate::common::{TestStatefulTokenizer as TestTokenizer, LEX_CSV};
+
+/// MeCab provider is followed by a regex provider, which matches only alphanumeric words
+const REGEX_LAST_CONFIG: &[u8] = include_bytes!("resources/sudachi.regex_last.json");
+
+fn tokenize_with(tok: &mut TestTokenizer, data: &str, constraints: Constraints) -> Vec<String> {
+    tok.tok.reset().push_str(data);
+    tok.tok.set_constraints(constraints);
+    tok.tok.do_tokenize().expect("tokenization failed");
+    tok.result.collect_results(&mut tok.tok).expect("works");
+    tok.result.iter().map(|m| m.surface().to_string()).collect()
+}
+
+#[test]
+fn forced_boundary() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    assert_eq!(1, tok.tokenize("東京都").len());
+    let mut c = Constraints::new();
+    c.add_boundary(3);
+    assert_eq!(vec!["東", "京都"], tokenize_with(&mut tok, "東京都", c));
+}
+
+#[test]
+fn forced_word_from_dictionary() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    let mut c = Constraints::new();
+    c.add_word(0..6, None);
+    assert_eq!(vec!["東京", "都"], tokenize_with(&mut tok, "東京都", c));
+    assert!(!tok.result.get(0).is_oov());
+}
+
+#[test]
+fn forced_word_is_created_as_oov() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    let mut c = Constraints::new();
+    c.add_word(3..9, None);
+    assert_eq!(vec!["京", "都に"], tokenize_with(&mut tok, "京都に", c));
+    assert!(tok.result.get(1).is_oov());
+}
+
+#[test]
+fn forced_word_when_last_provider_creates_nothing() {
+    let mut tok = TestTokenizer::builder(LEX_CSV)
+        .config(REGEX_LAST_CONFIG)
+        .mode(Mode::C)
+        .build();
+    let mut c = Constraints::new();
+    c.add_word(6..10, None);
+    assert_eq!(vec!["京都", "★a"], tokenize_with(&mut tok, "京都★a", c));
+    assert!(tok.result.get(1).is_oov());
+    assert_eq!("補助記号", tok.result.get(1).part_of_speech()[0]);
+}
+
+#[test]
+fn forced_word_with_pos() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    let pos = tok
+        .dict()
+        .grammar()
+        .get_part_of_speech_id(&["名詞", "普通名詞", "一般", "*", "*", "*"])
+        .unwrap();
+    let mut c = Constraints::new();
+    c.add_word(0..6, Some(pos));
+    assert_eq!(vec!["京都", "に"], tokenize_with(&mut tok, "京都に", c));
+    let m = tok.result.get(0);
+    assert_eq!(pos, m.part_of_speech_id());
+    assert!(m.is_oov());
+}
+
+#[test]
+fn forbidden_boundary() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    let mut c = Constraints::new();
+    c.add_non_boundary(6);
+    let surfaces = tokenize_with(&mut tok, "京都東京都", c);
+    assert_eq!("京都東京都", surfaces.concat());
+    for m in tok.result.iter() {
+        assert_ne!(6, m.begin(), "{:?}", m);
+    }
+}
+
+#[test]
+fn constraints_are_cleared_by_reset() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    let mut c = Constraints::new();
+    c.add_boundary(3);
+    assert_eq!(2, tokenize_with(&mut tok, "東京都", c).len());
+    let ms = tok.tokenize("東京都");
+    assert_eq!(1, ms.len());
+    assert_eq!("東京都", ms.get(0).surface().deref());
+}
+
+#[test]
+fn invalid_constraints() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都");
+    tok.tok.constraints_mut().add_boundary(1);
+    assert!(tok.tok.do_tokenize().is_err());
+
+    tok.tok.reset().push_str("東京都");
+    tok.tok
+        .constraints_mut()
+        .add_boundary(3)
+        .add_non_boundary(3);
+    assert!(tok.tok.do_tokenize().is_err());
+}
@@ -16,8 +16,16 @@
 
 extern crate lazy_static;
 
+use sudachi::analysis::stateless_tokenizer::DictionaryAccess;
+use sudachi::dic::build::DictBuilder;
+use sudachi::dic::dictionary::JapaneseDictionary;
+use sudachi::dic::storage::{Storage, SudachiDicData};
+use sudachi::dic::subset::InfoSubset;
+use sudachi::dic::word_id::WordId;
+use sudachi::dic::DictionaryLoader;
+
 mod common;
-use common::{TestTokenizer, GRAMMAR};
+use common::{TestStatefulTokenizer, TestTokenizer, GRAMMAR, LEX_CSV, TEST_CONFIG, USER1_CSV};
 
 #[test]
 fn get_part_of_speech_size() {
@@ -36,5 +44,102 @@ fn get_part_of_speech_string() {
     assert_eq!("助動詞", pos[0]);
 }
 
+#[test]
+fn more_than_fifteen_user_dictionaries() {
+    let num_dicts = 40;
+    let lexicons: Vec<String> = (0..num_dicts)
+        .map(|i| {
+            format!(
+                "すだち{i},6,6,2816,すだち{i},被子植物門,双子葉植物綱,ムクロジ目,ミカン科,ミカン属,種{i},スダチ,すだち{i},*,A,*,*,*,*\n"
+            )
+        })
+        .collect();
+    let mut builder = TestStatefulTokenizer::builder(LEX_CSV);
+    for lex in lexicons.iter() {
+        builder = builder.user(lex.as_bytes());
+    }
+    let mut tok = builder.build();
+    let num_pos = TestStatefulTokenizer::builder(LEX_CSV)
+        .build()
+        .dict()
+        .grammar()
+        .pos_list
+        .len();
+    assert_eq!(num_pos + num_dicts, tok.dict().grammar().pos_list.len());
+
+    for i in [0, 14, 15, num_dicts - 1] {
+        let surface = format!("すだち{}", i);
+        let result = tok.entries(&surface);
+        assert_eq!(1, result.len());
+        let m = result.get(0);
+        assert_eq!(i + 1, m.dictionary_id() as usize);
+        assert_eq!(format!("種{}", i), m.part_of_speech()[5]);
+        assert_eq!(surface, m.normalized_form());
+    }
+
+    let result = tok.tokenize("すだち39");
+    assert_eq!(1, result.len());
+    let wid = result.get(0).word_id();
+    assert_eq!(40, wid.dic());
+    let info = tok
+        .dict()
+        .lexicon()
+        .get_word_info_subset(wid, InfoSubset::all())
+        .expect("works");
+    assert_eq!("すだち39", info.surface());
+}
+
+fn build_user<D: DictionaryAccess>(system: D) -> (Vec<u8>, Vec<(String, i16)>) {
+    let mut bld = DictBuilder::new_user(system);
+    bld.read_lexicon(USER1_CSV).unwrap();
+    bld.resolve().unwrap();
+    let mut user = Vec::new();
+    bld.compile(&mut user).unwrap();
+    let costs = bld
+        .report()
+        .iter()
+        .flat_map(|r| r.estimated_costs())
+        .map(|c| (c.surface.clone(), c.cost))
+        .collect();
+    (user, costs)
+}
+
+#[test]
+fn placeholder_costs_are_estimated_by_builder() {
+    let mut bld = DictBuilder::new_system();
+    bld.read_conn(include_bytes!("resources/matrix_10x10.def"))
+        .unwrap();
+    bld.read_lexicon(LEX_CSV).unwrap();
+    bld.resolve().unwrap();
+    let mut system = Vec::new();
+    bld.compile(&mut system).unwrap();
+
+    let data = SudachiDicData::new(Storage::Owned(system.clone()));
+    let dict = JapaneseDictionary::from_cfg_storage(&TEST_CONFIG, data).expect("works");
+    let (user, costs) = build_user(&dict);
+    assert_eq!(1, costs.len());
+    assert_eq!("ぴらる", costs[0].0);
+    assert_ne!(i16::MIN, costs[0].1);
+    let estimated = costs[0].1;
+    let udic = DictionaryLoader::read_user_dictionary(&user).unwrap();
+    assert_eq!(estimated, udic.lexicon.get_word_param(0).2);
+
+    // without OOV providers the placeholder is kept and the cost is estimated on load,
+    // which gives the same value
+    let loaded = DictionaryLoader::read_system_dictionary(&system)
+        .unwrap()
+        .to_loaded()
+        .unwrap();
+    let (user, costs) = build_user(&loaded);
+    assert!(costs.is_empty());
+    let udic = DictionaryLoader::read_user_dictionary(&user).unwrap();
+    assert_eq!(i16::MIN, udic.lexicon.get_word_param(0).2);
+    let mut data = SudachiDicData::new(Storage::Owned(system));
+    data.add_user(Storage::Owned(user));
+    let dict = JapaneseDictionary::from_cfg_storage(&TEST_CONFIG, data).expect("works");
+    let params = dict.lexicon().get_word_param(WordId::new(1, 0));
+    assert_eq!(estimated, params.2);
+}
+
 // fn creat_with_merging_settings
 // fn creat_with_merging_null_settings
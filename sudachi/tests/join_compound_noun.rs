@@ -0,0 +1,75 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use crate::common::{TestStatefulTokenizer, LEX_CSV};
+use sudachi::analysis::mlist::MorphemeList;
+use sudachi::analysis::Mode;
+
+mod common;
+
+const COMPOUND_CONFIG: &[u8] = include_bytes!("resources/sudachi.compound.json");
+
+fn surfaces<T>(list: &MorphemeList<T>) -> Vec<String>
+where
+    T: sudachi::analysis::stateless_tokenizer::DictionaryAccess,
+{
+    list.iter().map(|m| m.surface().to_string()).collect()
+}
+
+#[test]
+fn compounds_are_joined() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(COMPOUND_CONFIG)
+        .build();
+    let result = tok.tokenize("東京都東に行く");
+    assert_eq!(surfaces(result), ["東京都東", "に", "行く"]);
+    let compound = result.get(0);
+    assert_eq!("トウキョウトヒガシ", compound.reading_form());
+    assert_eq!(["名詞", "普通名詞", "一般"], compound.part_of_speech()[..3]);
+}
+
+#[test]
+fn constituents_are_kept() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(COMPOUND_CONFIG)
+        .build();
+    let result = tok.tokenize("東京都東");
+    let compound = result.get(0);
+
+    let mut split = result.empty_clone();
+    assert!(compound.split_into(Mode::B, &mut split).unwrap());
+    assert_eq!(surfaces(&split), ["東京都", "東"]);
+    assert_eq!(
+        ["名詞", "固有名詞", "地名"],
+        split.get(0).part_of_speech()[..3]
+    );
+
+    let mut split = result.empty_clone();
+    assert!(compound.split_into(Mode::A, &mut split).unwrap());
+    assert_eq!(surfaces(&split), ["東京", "都", "東"]);
+    assert_eq!(9, split.get(2).begin());
+}
+
+#[test]
+fn analysis_in_a_mode_is_not_changed() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(COMPOUND_CONFIG)
+        .mode(Mode::A)
+        .build();
+    assert_eq!(surfaces(tok.tokenize("東京都東")), ["東京", "都", "東"]);
+    tok.set_mode(Mode::B);
+    assert_eq!(surfaces(tok.tokenize("東京都東")), ["東京都", "東"]);
+}
@@ -0,0 +1,67 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use crate::common::{TestStatefulTokenizer, LEX_CSV};
+
+mod common;
+
+const DATETIME_CONFIG: &[u8] = include_bytes!("resources/sudachi.datetime.json");
+
+fn tokens(tok: &mut TestStatefulTokenizer, data: &str) -> Vec<(String, String, String)> {
+    tok.tokenize(data)
+        .iter()
+        .map(|m| {
+            (
+                m.surface().to_string(),
+                m.normalized_form().to_owned(),
+                m.part_of_speech()[1].clone(),
+            )
+        })
+        .collect()
+}
+
+fn t(surface: &str, normalized: &str, pos: &str) -> (String, String, String) {
+    (surface.to_owned(), normalized.to_owned(), pos.to_owned())
+}
+
+#[test]
+fn dates_times_and_durations_are_joined() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(DATETIME_CONFIG)
+        .build();
+    assert_eq!(
+        tokens(&mut tok, "2024年10月17日"),
+        [t("2024年10月17日", "2024-10-17", "固有名詞")]
+    );
+    assert_eq!(
+        tokens(&mut tok, "午前9時30分に"),
+        [
+            t("午前9時30分", "09:30", "固有名詞"),
+            t("に", "に", "格助詞")
+        ]
+    );
+    assert_eq!(tokens(&mut tok, "三日間"), [t("三日間", "P3D", "数詞")]);
+}
+
+#[test]
+fn numbers_are_not_joined_alone() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(DATETIME_CONFIG)
+        .build();
+    let result = tokens(&mut tok, "2024年");
+    assert_eq!(2, result.len());
+    assert_eq!(result[0], t("2024", "2024", "数詞"));
+}
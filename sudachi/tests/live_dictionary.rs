@@ -0,0 +1,231 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use sudachi::analysis::stateful_tokenizer::StatefulTokenizer;
+use sudachi::analysis::stateless_tokenizer::DictionaryAccess;
+use sudachi::dic::build::DictBuilder;
+use sudachi::dic::dictionary::JapaneseDictionary;
+use sudachi::dic::live_dictionary::LiveDictionary;
+use sudachi::dic::mutable_lexicon::{MutableUserLexicon, UserWord};
+use sudachi::dic::storage::{Storage, SudachiDicData};
+use sudachi::dic::word_id::WordId;
+use sudachi::dic::DictionaryLoader;
+use sudachi::prelude::*;
+
+mod common;
+use common::{LEX_CSV, TEST_CONFIG, USER1_CSV, USER2_CSV};
+
+struct Fixture {
+    system: Vec<u8>,
+}
+
+impl Fixture {
+    fn new() -> Fixture {
+        let mut sys = DictBuilder::new_system();
+        sys.read_conn(include_bytes!("resources/matrix_10x10.def"))
+            .unwrap();
+        sys.read_lexicon(LEX_CSV).unwrap();
+        sys.resolve().unwrap();
+        let mut system = Vec::new();
+        sys.compile(&mut system).unwrap();
+        Fixture { system }
+    }
+
+    fn user(&self, csv: &[u8]) -> Storage {
+        let dic = DictionaryLoader::read_system_dictionary(&self.system)
+            .unwrap()
+            .to_loaded()
+            .unwrap();
+        let mut bld = DictBuilder::new_user(&dic);
+        bld.read_lexicon(csv).unwrap();
+        bld.resolve().unwrap();
+        let mut user = Vec::new();
+        bld.compile(&mut user).unwrap();
+        Storage::Owned(user)
+    }
+
+    fn live(&self) -> LiveDictionary {
+        let data = SudachiDicData::new(Storage::Owned(self.system.clone()));
+        let dic = JapaneseDictionary::from_cfg_storage(&TEST_CONFIG, data).expect("works");
+        LiveDictionary::new(dic)
+    }
+}
+
+fn analyze(dict: &Arc<JapaneseDictionary>, data: &str) -> Vec<(String, String)> {
+    let mut tok = StatefulTokenizer::new(dict.clone(), Mode::C);
+    tok.reset().push_str(data);
+    tok.do_tokenize().expect("works");
+    let mut result = MorphemeList::empty(dict.clone());
+    result.collect_results(&mut tok).expect("works");
+    result
+        .iter()
+        .map(|m| (m.surface().to_string(), m.part_of_speech()[0].clone()))
+        .collect()
+}
+
+fn word(surface: &str, pos: &str) -> (String, String) {
+    (surface.to_owned(), pos.to_owned())
+}
+
+#[test]
+fn add_user_dict_keeps_old_snapshots() {
+    let fixture = Fixture::new();
+    let live = fixture.live();
+    let before = live.snapshot();
+    assert_eq!(0, live.generation());
+
+    live.add_user_dict(fixture.user(USER1_CSV)).expect("works");
+    let after = live.snapshot();
+    assert_eq!(1, live.generation());
+    assert_eq!(1, live.num_user_dicts());
+    assert_eq!(0, before.num_user_dicts());
+
+    assert_eq!(
+        vec![word("すだち", "被子植物門")],
+        analyze(&after, "すだち")
+    );
+    assert_ne!(
+        vec![word("すだち", "被子植物門")],
+        analyze(&before, "すだち")
+    );
+    // cost of -32768 is computed for the new generation
+    assert_eq!(vec![word("ぴらる", "名詞")], analyze(&after, "ぴらる"));
+}
+
+#[test]
+fn reload_user_dict_replaces_words() {
+    let fixture = Fixture::new();
+    let live = fixture.live();
+    live.add_user_dict(fixture.user(USER1_CSV)).expect("works");
+    live.add_user_dict(fixture.user(USER2_CSV)).expect("works");
+    let dict = live.snapshot();
+    assert_eq!(vec![word("すだち", "被子植物門")], analyze(&dict, "すだち"));
+    assert_eq!(vec![word("かぼす", "被子植物門")], analyze(&dict, "かぼす"));
+
+    live.reload_user_dict(0, fixture.user(USER2_CSV))
+        .expect("works");
+    let dict = live.snapshot();
+    assert_eq!(2, dict.num_user_dicts());
+    assert_ne!(vec![word("すだち", "被子植物門")], analyze(&dict, "すだち"));
+    assert_eq!(vec![word("かぼす", "被子植物門")], analyze(&dict, "かぼす"));
+    // pos of the replaced dictionary is taken from the new one
+    let pos = [
+        "被子植物門",
+        "双子葉植物綱",
+        "ムクロジ目",
+        "ミカン科",
+        "ミカン属",
+        "カボス",
+    ];
+    assert!(dict.grammar().get_part_of_speech_id(&pos).is_some());
+}
+
+#[test]
+fn remove_user_dict() {
+    let fixture = Fixture::new();
+    let live = fixture.live();
+    live.add_user_dict(fixture.user(USER1_CSV)).expect("works");
+    let with_user = live.snapshot();
+    live.remove_user_dict(0).expect("works");
+    let dict = live.snapshot();
+    assert_eq!(0, dict.num_user_dicts());
+    assert_eq!(2, live.generation());
+    assert_ne!(vec![word("すだち", "被子植物門")], analyze(&dict, "すだち"));
+    assert_eq!(
+        vec![word("すだち", "被子植物門")],
+        analyze(&with_user, "すだち")
+    );
+}
+
+#[test]
+fn invalid_index_does_not_change_generation() {
+    let fixture = Fixture::new();
+    let live = fixture.live();
+    assert!(live.remove_user_dict(0).is_err());
+    assert!(live.reload_user_dict(1, fixture.user(USER1_CSV)).is_err());
+    assert_eq!(0, live.generation());
+}
+
+#[test]
+fn mutable_lexicon_is_kept() {
+    let fixture = Fixture::new();
+    let data = SudachiDicData::new(Storage::Owned(fixture.system.clone()));
+    let mut dic = JapaneseDictionary::from_cfg_storage(&TEST_CONFIG, data).expect("works");
+    let lexicon = Arc::new(MutableUserLexicon::new(&dic));
+    dic.attach_mutable_lexicon(lexicon.clone()).expect("works");
+    let live = LiveDictionary::new(dic);
+    let pos = live
+        .snapshot()
+        .grammar()
+        .get_part_of_speech_id(&["名詞", "固有名詞", "地名", "一般", "*", "*"])
+        .unwrap();
+    let place = |surface: &str| UserWord::new(surface, pos, 6, 6, 1000);
+    // the lexicon has dictionary id 1 without user dictionaries, 京都 is a system word
+    let kita = lexicon.add_word(place("北山")).expect("works");
+    let split = vec![WordId::new(1, kita), WordId::new(0, 3)];
+    lexicon
+        .add_word(place("北山京都").with_splits(split, Vec::new()))
+        .expect("works");
+
+    // ids of the lexicon and its words change with the number of user dictionaries
+    let check = |dic_id: u8| {
+        let dict = live.snapshot();
+        assert_eq!(vec![word("北山京都", "名詞")], analyze(&dict, "北山京都"));
+        let mut tok = StatefulTokenizer::new(dict.clone(), Mode::C);
+        tok.reset().push_str("北山京都");
+        tok.do_tokenize().expect("works");
+        let mut result = MorphemeList::empty(dict.clone());
+        result.collect_results(&mut tok).expect("works");
+        assert_eq!(dic_id, result.get(0).word_id().dic());
+        let mut parts = MorphemeList::empty(dict.clone());
+        assert!(result
+            .get(0)
+            .split_into(Mode::A, &mut parts)
+            .expect("works"));
+        let parts: Vec<_> = parts.iter().map(|m| m.surface().to_string()).collect();
+        assert_eq!(vec!["北山", "京都"], parts);
+        assert!(lexicon
+            .add_word(place("北山2").with_splits(vec![WordId::new(dic_id, kita)], Vec::new()))
+            .is_ok());
+        assert!(lexicon
+            .add_word(place("北山3").with_splits(vec![WordId::new(dic_id + 1, 0)], Vec::new()))
+            .is_err());
+    };
+    check(1);
+
+    live.add_user_dict(fixture.user(USER1_CSV)).expect("works");
+    check(2);
+    assert_eq!(
+        vec![word("すだち", "被子植物門")],
+        analyze(&live.snapshot(), "すだち")
+    );
+    // 府 is the second word of the user dictionary
+    let fu = lexicon
+        .add_word(place("北山府").with_splits(vec![WordId::new(1, 1)], Vec::new()))
+        .expect("works");
+    assert!(lexicon
+        .add_word(place("北山府2").with_splits(vec![WordId::new(1, 100)], Vec::new()))
+        .is_err());
+
+    // the word refers to the user dictionary, which would be removed
+    assert!(live.remove_user_dict(0).is_err());
+    assert_eq!(1, live.generation());
+    assert!(lexicon.remove_word(fu));
+    live.remove_user_dict(0).expect("works");
+    check(1);
+}
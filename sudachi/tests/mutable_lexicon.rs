@@ -0,0 +1,243 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use sudachi::analysis::stateful_tokenizer::StatefulTokenizer;
+use sudachi::analysis::stateless_tokenizer::DictionaryAccess;
+use sudachi::dic::dictionary::JapaneseDictionary;
+use sudachi::dic::mutable_lexicon::{MutableUserLexicon, UserWord};
+use sudachi::dic::word_id::WordId;
+use sudachi::prelude::*;
+
+mod common;
+use common::TEST_CONFIG;
+
+fn dictionary() -> JapaneseDictionary {
+    JapaneseDictionary::from_cfg(&TEST_CONFIG).expect("failed to make dictionary")
+}
+
+fn place_name(dict: &JapaneseDictionary, surface: &str) -> UserWord {
+    let pos = dict
+        .grammar()
+        .get_part_of_speech_id(&["名詞", "固有名詞", "地名", "一般", "*", "*"])
+        .expect("pos exists");
+    UserWord::new(surface, pos, 6, 6, 1000)
+}
+
+fn surfaces<D: DictionaryAccess>(list: &MorphemeList<D>) -> Vec<String> {
+    list.iter().map(|m| m.surface().to_string()).collect()
+}
+
+#[test]
+fn added_words_are_used_by_tokenizer() {
+    let mut dict = dictionary();
+    let lexicon = Arc::new(MutableUserLexicon::new(&dict));
+    let word = place_name(&dict, "京都府").with_reading_form("キョウトフ");
+    let wid = lexicon.add_word(word).expect("works");
+    dict.attach_mutable_lexicon(lexicon.clone()).expect("works");
+
+    let mut tok = StatefulTokenizer::new(&dict, Mode::C);
+    tok.reset().push_str("京都府に行く");
+    tok.do_tokenize().expect("works");
+    let mut result = MorphemeList::empty(&dict);
+    result.collect_results(&mut tok).expect("works");
+    assert_eq!(vec!["京都府", "に", "行く"], surfaces(&result));
+
+    let m = result.get(0);
+    assert_eq!("キョウトフ", m.reading_form());
+    assert_eq!("京都府", m.dictionary_form());
+    assert_eq!(
+        ["名詞", "固有名詞", "地名", "一般", "*", "*"],
+        m.part_of_speech()
+    );
+    // system and user dictionary come first
+    assert_eq!(WordId::new(2, wid), m.word_id());
+    assert_eq!(2, m.dictionary_id());
+}
+
+#[test]
+fn words_can_be_modified_while_dictionary_is_shared() {
+    let mut dict = dictionary();
+    let lexicon = Arc::new(MutableUserLexicon::new(&dict));
+    dict.attach_mutable_lexicon(lexicon.clone()).expect("works");
+    let dict = Arc::new(dict);
+
+    let mut tok = StatefulTokenizer::new(dict.clone(), Mode::C);
+    let mut analyze = |data: &str| {
+        tok.reset().push_str(data);
+        tok.do_tokenize().expect("works");
+        let mut result = MorphemeList::empty(dict.clone());
+        result.collect_results(&mut tok).expect("works");
+        surfaces(&result)
+    };
+
+    assert_eq!(vec!["京都", "府"], analyze("京都府"));
+    let wid = lexicon
+        .add_word(place_name(&dict, "京都府"))
+        .expect("works");
+    assert_eq!(1, lexicon.len());
+    assert_eq!(vec!["京都府"], analyze("京都府"));
+    assert!(lexicon.remove_word(wid));
+    assert!(!lexicon.remove_word(wid));
+    assert!(lexicon.is_empty());
+    assert_eq!(vec!["京都", "府"], analyze("京都府"));
+}
+
+#[test]
+fn remove_by_surface() {
+    let dict = dictionary();
+    let lexicon = MutableUserLexicon::new(&dict);
+    let a = lexicon
+        .add_word(place_name(&dict, "京都府"))
+        .expect("works");
+    let b = lexicon
+        .add_word(place_name(&dict, "京都府"))
+        .expect("works");
+    lexicon
+        .add_word(place_name(&dict, "京都市"))
+        .expect("works");
+    assert_eq!(vec![a, b], lexicon.find_surface("京都府"));
+    assert_eq!(2, lexicon.remove_surface("京都府"));
+    assert_eq!(0, lexicon.remove_surface("京都府"));
+    assert!(lexicon.find_surface("京都府").is_empty());
+    assert_eq!(1, lexicon.len());
+}
+
+#[test]
+fn lookup_returns_prefixes() {
+    let dict = dictionary();
+    let lexicon = MutableUserLexicon::new(&dict);
+    let a = lexicon.add_word(place_name(&dict, "京")).expect("works");
+    let b = lexicon
+        .add_word(place_name(&dict, "京都府"))
+        .expect("works");
+    lexicon.add_word(place_name(&dict, "都府")).expect("works");
+
+    let input = "東京都府".as_bytes();
+    let entries = lexicon.lookup(input, 3, 5);
+    assert_eq!(2, entries.len());
+    assert_eq!(WordId::new(5, a), entries[0].word_id);
+    assert_eq!(6, entries[0].end);
+    assert_eq!(WordId::new(5, b), entries[1].word_id);
+    assert_eq!(12, entries[1].end);
+}
+
+#[test]
+fn invalid_words_are_rejected() {
+    let dict = dictionary();
+    let lexicon = MutableUserLexicon::new(&dict);
+    let mut word = place_name(&dict, "京都府");
+    word.left_id = 100;
+    assert!(lexicon.add_word(word).is_err());
+    let mut word = place_name(&dict, "京都府");
+    word.pos_id = u16::MAX;
+    assert!(lexicon.add_word(word).is_err());
+    assert!(lexicon.add_word(place_name(&dict, "")).is_err());
+    let word = place_name(&dict, "京都府").with_splits(vec![WordId::EOS], vec![]);
+    assert!(lexicon.add_word(word).is_err());
+    assert!(lexicon.is_empty());
+}
+
+#[test]
+fn splits_must_refer_to_existing_words() {
+    let dict = dictionary();
+    let lexicon = MutableUserLexicon::new(&dict);
+    let num_words = dict.lexicon().size();
+    for split in [
+        WordId::new(5, 0),
+        WordId::new(0, num_words),
+        WordId::new(1, 1000),
+        // this lexicon is empty
+        WordId::new(2, 0),
+    ] {
+        let word = place_name(&dict, "京都府").with_splits(vec![], vec![split]);
+        assert!(
+            lexicon.add_word(word).is_err(),
+            "split {} is accepted",
+            split
+        );
+    }
+    assert!(lexicon.is_empty());
+
+    let wid = lexicon.add_word(place_name(&dict, "京都")).expect("works");
+    let word = place_name(&dict, "京都府")
+        .with_splits(vec![WordId::new(2, wid), WordId::new(0, 0)], vec![]);
+    assert!(lexicon.add_word(word).is_ok());
+}
+
+#[test]
+fn clear_does_not_reuse_ids() {
+    let dict = dictionary();
+    let lexicon = MutableUserLexicon::new(&dict);
+    let a = lexicon
+        .add_word(place_name(&dict, "京都府"))
+        .expect("works");
+    lexicon.clear();
+    assert!(lexicon.is_empty());
+    assert!(lexicon.find_surface("京都府").is_empty());
+    assert!(!lexicon.remove_word(a));
+    // removed words are still readable
+    assert_eq!("京都府", lexicon.get_word_info(a).expect("works").surface());
+    let b = lexicon
+        .add_word(place_name(&dict, "京都府"))
+        .expect("works");
+    assert_ne!(a, b);
+    assert_eq!(vec![b], lexicon.find_surface("京都府"));
+}
+
+#[test]
+fn clear_while_tokenizing() {
+    let mut dict = dictionary();
+    let lexicon = Arc::new(MutableUserLexicon::new(&dict));
+    dict.attach_mutable_lexicon(lexicon.clone()).expect("works");
+    let dict = Arc::new(dict);
+    let word = place_name(&dict, "京都府").with_reading_form("キョウトフ");
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                let mut tok = StatefulTokenizer::new(dict.clone(), Mode::A);
+                for _ in 0..500 {
+                    tok.reset().push_str("東京都と京都府に行く");
+                    tok.do_tokenize().expect("works");
+                    let mut result = MorphemeList::empty(dict.clone());
+                    result.collect_results(&mut tok).expect("works");
+                    for m in result.iter() {
+                        m.reading_form();
+                    }
+                }
+            });
+        }
+        s.spawn(|| {
+            for _ in 0..500 {
+                lexicon.add_word(word.clone()).expect("works");
+                lexicon.clear();
+            }
+        });
+    });
+    assert!(lexicon.is_empty());
+}
+
+#[test]
+fn only_one_mutable_lexicon_can_be_attached() {
+    let mut dict = dictionary();
+    let lexicon = Arc::new(MutableUserLexicon::new(&dict));
+    dict.attach_mutable_lexicon(lexicon.clone()).expect("works");
+    assert!(dict.attach_mutable_lexicon(lexicon.clone()).is_err());
+    assert!(dict.detach_mutable_lexicon().is_some());
+    assert!(dict.attach_mutable_lexicon(lexicon).is_ok());
+}
@@ -0,0 +1,89 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use crate::common::{TestStatefulTokenizer, LEX_CSV};
+use sudachi::analysis::Mode;
+
+mod common;
+
+const UNITS_CONFIG: &[u8] = include_bytes!("resources/sudachi.units.json");
+
+/// (surface, value, unit) of morphemes
+fn values(
+    tok: &mut TestStatefulTokenizer,
+    data: &str,
+) -> Vec<(String, Option<String>, Option<String>)> {
+    tok.tokenize(data)
+        .iter()
+        .map(|m| {
+            let value = m.numeric_value();
+            (
+                m.surface().to_string(),
+                value.map(|v| v.as_str().to_owned()),
+                value.and_then(|v| v.unit().map(|u| u.to_owned())),
+            )
+        })
+        .collect()
+}
+
+fn v(
+    surface: &str,
+    value: Option<&str>,
+    unit: Option<&str>,
+) -> (String, Option<String>, Option<String>) {
+    (
+        surface.to_owned(),
+        value.map(|s| s.to_owned()),
+        unit.map(|s| s.to_owned()),
+    )
+}
+
+#[test]
+fn numbers_have_values() {
+    let mut tok = TestStatefulTokenizer::new_built(Mode::C);
+    assert_eq!(
+        values(&mut tok, "東京に1,234.5"),
+        [
+            v("東京", None, None),
+            v("に", None, None),
+            v("1,234.5", Some("1234.5"), None)
+        ]
+    );
+}
+
+#[test]
+fn units_are_joined() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(UNITS_CONFIG)
+        .build();
+    assert_eq!(
+        values(&mut tok, "300人に1,000円"),
+        [
+            // counters are not units by default
+            v("300", Some("300"), None),
+            v("人", None, None),
+            v("に", None, None),
+            v("1,000円", Some("1000"), Some("円"))
+        ]
+    );
+    assert_eq!(
+        values(&mut tok, "2.5km"),
+        [v("2.5km", Some("2.5"), Some("km"))]
+    );
+    let morphemes = tok.tokenize("10％");
+    assert_eq!("10%", morphemes.get(0).normalized_form());
+    assert_eq!(Some("%"), morphemes.get(0).numeric_value().unwrap().unit());
+}
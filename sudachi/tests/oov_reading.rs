@@ -0,0 +1,71 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use crate::common::{TestStatefulTokenizer, LEX_CSV};
+
+mod common;
+
+const OOV_READING_CONFIG: &[u8] = include_bytes!("resources/sudachi.oov_reading.json");
+
+fn readings(tok: &mut TestStatefulTokenizer, data: &str) -> Vec<(String, String, bool)> {
+    tok.tokenize(data)
+        .iter()
+        .map(|m| {
+            let surface = m.surface().to_string();
+            (surface, m.reading_form().to_owned(), m.is_oov())
+        })
+        .collect()
+}
+
+#[test]
+fn oov_words_have_readings() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(OOV_READING_CONFIG)
+        .build();
+    assert_eq!(
+        readings(&mut tok, "東京Python"),
+        [
+            ("東京".to_owned(), "トウキョウ".to_owned(), false),
+            ("Python".to_owned(), "パイソン".to_owned(), true),
+        ]
+    );
+    let found = readings(&mut tok, "すだXYZ");
+    assert_eq!(found[0].1, "ス");
+    assert_eq!(found[1].1, "ダ");
+    assert_eq!(found[2].1, "エックスワイゼット");
+    // joined katakana OOV
+    assert_eq!(
+        readings(&mut tok, "アイウエオカキ"),
+        [(
+            "アイウエオカキ".to_owned(),
+            "アイウエオカキ".to_owned(),
+            true
+        )]
+    );
+}
+
+#[test]
+fn oov_reading_is_disabled_by_default() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV).build();
+    let tokens = tok.tokenize("Python");
+    assert!(tokens.get(0).is_oov());
+    assert!(tokens
+        .get(0)
+        .get_word_info()
+        .borrow_data()
+        .reading_form
+        .is_empty());
+}
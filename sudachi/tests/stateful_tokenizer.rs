@@ -18,6 +18,8 @@ extern crate lazy_static;
 extern crate sudachi;
 
 use std::ops::Deref;
+use sudachi::config::ConfigError;
+use sudachi::error::SudachiError;
 use sudachi::prelude::Mode;
 
 mod common;
@@ -191,3 +193,217 @@ fn morpheme_extraction() {
     assert_eq!(0, e.begin_c());
     assert_eq!(3, e.end_c());
 }
+
+#[test]
+fn n_best_starts_with_best_path() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    let best_len = tok.tokenize("東京都").len();
+    tok.tok.reset().push_str("東京都");
+    let results = tok.tok.n_best(3).expect("works");
+    assert_eq!(3, results.len());
+    assert_eq!(best_len, results[0].0.len());
+    assert_eq!("東京都", results[0].0.get(0).surface().deref());
+    assert_eq!("東京都", results[1].0.surface().deref());
+    for w in results.windows(2) {
+        assert!(w[0].1 <= w[1].1);
+    }
+    let segmentations: Vec<Vec<String>> = results
+        .iter()
+        .map(|(ms, _)| ms.iter().map(|m| m.surface().to_string()).collect())
+        .collect();
+    assert!(segmentations.contains(&vec!["東京".to_owned(), "都".to_owned()]));
+
+    // tokenizer is usable after n-best analysis
+    assert_eq!(1, tok.tokenize("京都").len());
+}
+
+#[test]
+fn n_best_results_are_distinct() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("京都東京都");
+    let results = tok.tok.n_best(100).expect("works");
+    assert!(results.len() > 1);
+    assert!(results.len() <= 100);
+    for (i, (a, _)) in results.iter().enumerate() {
+        for (b, _) in results.iter().skip(i + 1) {
+            let a: Vec<_> = a
+                .iter()
+                .map(|m| (m.begin(), m.end(), m.word_id()))
+                .collect();
+            let b: Vec<_> = b
+                .iter()
+                .map(|m| (m.begin(), m.end(), m.word_id()))
+                .collect();
+            assert_ne!(a, b);
+        }
+    }
+}
+
+#[test]
+fn n_best_empty() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset();
+    assert!(tok.tok.n_best(3).expect("works").is_empty());
+    tok.tok.reset().push_str("京都");
+    assert!(tok.tok.n_best(0).expect("works").is_empty());
+}
+
+#[test]
+fn confidence_is_disabled_by_default() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    let ms = tok.tokenize("京都東京都");
+    assert_eq!(None, ms.get(0).confidence());
+}
+
+#[test]
+fn confidence_is_probability() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok
+        .set_confidence_temperature(Some(1000.0))
+        .expect("valid");
+    let ms = tok.tokenize("京都東京都に行った");
+    for m in ms.iter() {
+        let c = m.confidence().expect("confidence is computed");
+        assert!(c > 0.0 && c <= 1.0, "{:?} has confidence {}", m, c);
+    }
+}
+
+#[test]
+fn confidence_depends_on_temperature() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok
+        .set_confidence_temperature(Some(1.0))
+        .expect("valid");
+    let cold = tok.tokenize("東京都").get(0).confidence().unwrap();
+    assert!(cold > 0.99);
+    tok.tok
+        .set_confidence_temperature(Some(100000.0))
+        .expect("valid");
+    let hot = tok.tokenize("東京都").get(0).confidence().unwrap();
+    assert!(hot < cold);
+}
+
+#[test]
+fn invalid_confidence_temperature() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    for t in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+        let err = tok.tok.set_confidence_temperature(Some(t)).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                SudachiError::ConfigError(ConfigError::InvalidValue(..))
+            ),
+            "{:?}",
+            err
+        );
+        assert!(err.to_string().contains(&t.to_string()), "{}", err);
+    }
+    assert_eq!(
+        None,
+        tok.tok
+            .set_confidence_temperature(Some(1.0))
+            .expect("valid")
+    );
+    assert!(tok.tok.set_confidence_temperature(Some(0.0)).is_err());
+    assert_eq!(
+        Some(1.0),
+        tok.tok.set_confidence_temperature(None).expect("valid")
+    );
+}
+
+#[test]
+fn confidence_of_split_morphemes() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok
+        .set_confidence_temperature(Some(1000.0))
+        .expect("valid");
+    let whole = tok.tokenize("東京都").get(0).confidence();
+    tok.set_mode(Mode::A);
+    let ms = tok.tokenize("東京都");
+    assert_eq!(2, ms.len());
+    assert_eq!(whole, ms.get(0).confidence());
+    assert_eq!(whole, ms.get(1).confidence());
+}
+
+#[test]
+fn long_input_is_accepted() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    let sentence = "京都東京都に行った";
+    let data = sentence.repeat(10000);
+    assert!(data.len() > u16::MAX as usize);
+    let per_sentence = tok.tokenize(sentence).len();
+    let ms = tok.tokenize(&data);
+    assert_eq!(per_sentence * 10000, ms.len());
+    let last = ms.get(ms.len() - 1);
+    assert_eq!(data.len(), last.end());
+    assert_eq!(data.chars().count(), last.end_c());
+    assert_eq!("た", last.surface().deref());
+}
+
+#[test]
+fn lattice_view_contains_all_nodes() {
+    use sudachi::analysis::lattice_view::LatticeNodeKind;
+
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("東京都");
+    tok.tok.do_tokenize().expect("works");
+    let view = tok.tok.lattice_view().expect("works");
+    let nodes = view.nodes();
+    assert_eq!(LatticeNodeKind::Bos, nodes[0].kind);
+    assert_eq!(LatticeNodeKind::Eos, nodes[nodes.len() - 1].kind);
+    let surfaces: Vec<_> = nodes[1..nodes.len() - 1]
+        .iter()
+        .map(|n| n.surface.as_str())
+        .collect();
+    assert_eq!(vec!["東", "東京", "東京都", "京都", "都"], surfaces);
+
+    let best: Vec<_> = view.best_path().map(|n| n.surface.as_str()).collect();
+    assert_eq!(vec!["東京都"], best);
+
+    for (i, n) in nodes.iter().enumerate() {
+        assert_eq!(i, n.id);
+        // the best path cost is the minimum over all predecessors
+        if let Some(prev) = n.best_prev {
+            let conn = n.connections.iter().find(|c| c.from == prev).unwrap();
+            let expected = nodes[prev].total_cost.unwrap() + conn.cost as i32 + n.word_cost as i32;
+            assert_eq!(Some(expected), n.total_cost);
+            for c in n.connections.iter() {
+                let cost = nodes[c.from].total_cost.unwrap() + c.cost as i32 + n.word_cost as i32;
+                assert!(cost >= expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn lattice_view_serialization() {
+    use sudachi::analysis::lattice_view::LatticeFormat;
+
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset().push_str("京都");
+    tok.tok.do_tokenize().expect("works");
+    let view = tok.tok.lattice_view().expect("works");
+
+    let mut json = Vec::new();
+    view.write(LatticeFormat::Json, &mut json).expect("works");
+    let value: serde_json::Value = serde_json::from_slice(&json).expect("valid json");
+    let nodes = value["nodes"].as_array().unwrap();
+    assert_eq!(view.nodes().len(), nodes.len());
+    assert_eq!("bos", nodes[0]["kind"]);
+    assert_eq!("京都", nodes[nodes.len() - 2]["surface"]);
+
+    let mut dot = Vec::new();
+    view.write(LatticeFormat::Dot, &mut dot).expect("works");
+    let dot = String::from_utf8(dot).unwrap();
+    assert!(dot.starts_with("digraph lattice {"));
+    assert!(dot.contains("京都"));
+    assert!(dot.trim_end().ends_with('}'));
+}
+
+#[test]
+fn lattice_view_of_empty_input() {
+    let mut tok = TestTokenizer::new_built(Mode::C);
+    tok.tok.reset();
+    tok.tok.do_tokenize().expect("works");
+    assert!(tok.tok.lattice_view().expect("works").is_empty());
+}
@@ -18,7 +18,12 @@ extern crate lazy_static;
 extern crate sudachi;
 
 use std::ops::Deref;
-use sudachi::prelude::Mode;
+use std::sync::Arc;
+
+use sudachi::analysis::stateless_tokenizer::StatelessTokenizer;
+use sudachi::analysis::Tokenize;
+use sudachi::dic::dictionary::JapaneseDictionary;
+use sudachi::prelude::*;
 
 mod common;
 use crate::common::TestTokenizer;
@@ -114,3 +119,48 @@ fn tokenizer_morpheme_split() {
     assert_eq!("東京", ms.get(0).surface().deref());
     assert_eq!("都", ms.get(1).surface().deref());
 }
+
+#[test]
+fn tokenize_n_best() {
+    let tok = TestTokenizer::new();
+    let results = tok.tokenize_n_best("東京都", Mode::A, 2);
+    assert_eq!(2, results.len());
+    let best = &results[0].0;
+    assert_eq!(2, best.len());
+    assert_eq!("東京", best.get(0).surface().deref());
+    assert_eq!("都", best.get(1).surface().deref());
+    assert!(results[0].1 <= results[1].1);
+}
+
+/// Implements only the required method of the trait
+struct OneBest(StatelessTokenizer<Arc<JapaneseDictionary>>);
+
+impl Tokenize for OneBest {
+    type Dictionary = Arc<JapaneseDictionary>;
+
+    fn tokenize(
+        &self,
+        input: &str,
+        mode: Mode,
+        enable_debug: bool,
+    ) -> SudachiResult<MorphemeList<Self::Dictionary>> {
+        self.0.tokenize(input, mode, enable_debug)
+    }
+}
+
+#[test]
+fn tokenize_n_best_default_returns_best() {
+    let dict = JapaneseDictionary::from_cfg(&common::TEST_CONFIG).expect("works");
+    let tok = OneBest(StatelessTokenizer::new(Arc::new(dict)));
+    let results = tok
+        .tokenize_n_best("東京都", Mode::A, 2, false)
+        .expect("works");
+    assert_eq!(1, results.len());
+    let best = &results[0].0;
+    assert_eq!(2, best.len());
+    assert_eq!(best.get_internal_cost(), results[0].1);
+    assert!(tok
+        .tokenize_n_best("東京都", Mode::A, 0, false)
+        .expect("works")
+        .is_empty());
+}
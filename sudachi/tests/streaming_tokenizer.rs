@@ -0,0 +1,136 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use std::io::BufReader;
+use std::ops::Deref;
+
+use sudachi::analysis::stateful_tokenizer::StatefulTokenizer;
+use sudachi::analysis::streaming_tokenizer::StreamingTokenizer;
+use sudachi::dic::dictionary::JapaneseDictionary;
+use sudachi::prelude::*;
+
+mod common;
+use common::TEST_CONFIG;
+
+fn dictionary() -> JapaneseDictionary {
+    JapaneseDictionary::from_cfg(&TEST_CONFIG).expect("failed to make dictionary")
+}
+
+#[test]
+fn sentences_spanning_chunks() {
+    let dict = dictionary();
+    let chunks = ["京都に行", "った。東京", "都に", "行った"];
+    let tok = StreamingTokenizer::from_chunks(&dict, Mode::C, chunks);
+    let sentences: Vec<_> = tok.map(|r| r.expect("works")).collect();
+    assert_eq!(2, sentences.len());
+    assert_eq!("京都に行った。", sentences[0].surface().deref());
+    assert_eq!("東京都に行った", sentences[1].surface().deref());
+    let m = sentences[1].get(0);
+    assert_eq!("東京都", m.surface().deref());
+    assert_eq!(21, m.begin());
+    assert_eq!(30, m.end());
+    assert_eq!(7, m.begin_c());
+    assert_eq!(10, m.end_c());
+}
+
+#[test]
+fn sentence_end_in_next_chunk() {
+    let dict = dictionary();
+    let chunks = ["京都に行った！", "！東京都"];
+    let tok = StreamingTokenizer::from_chunks(&dict, Mode::C, chunks);
+    let sentences: Vec<String> = tok
+        .map(|r| r.expect("works").surface().to_string())
+        .collect();
+    assert_eq!(vec!["京都に行った！！", "東京都"], sentences);
+}
+
+#[test]
+fn reader_with_split_characters() {
+    let dict = dictionary();
+    let data = "京都に行った。東京都に行った。".repeat(3);
+    // capacity 4 splits multibyte characters between reads
+    let reader = BufReader::with_capacity(4, data.as_bytes());
+    let tok = StreamingTokenizer::from_reader(&dict, Mode::C, reader);
+    let mut num_sentences = 0;
+    let mut last_end = 0;
+    for sentence in tok {
+        let sentence = sentence.expect("works");
+        for m in sentence.iter() {
+            assert_eq!(last_end, m.begin());
+            assert_eq!(&data[m.begin()..m.end()], m.surface().deref());
+            last_end = m.end();
+        }
+        num_sentences += 1;
+    }
+    assert_eq!(6, num_sentences);
+    assert_eq!(data.len(), last_end);
+}
+
+#[test]
+fn sentence_without_boundary_is_split_at_limit() {
+    let dict = dictionary();
+    let data = "京都".repeat(20);
+    let tok = StreamingTokenizer::from_chunks(&dict, Mode::C, [data.as_str()]).with_limit(10);
+    let sentences: Vec<_> = tok.map(|r| r.expect("works")).collect();
+    assert_eq!(4, sentences.len());
+    assert_eq!(60, sentences[2].get(0).begin());
+}
+
+#[test]
+fn invalid_utf8_is_an_error() {
+    let dict = dictionary();
+    let data: &[u8] = b"\xe4\xba\xac\xe9";
+    let mut tok = StreamingTokenizer::from_reader(&dict, Mode::C, data);
+    assert!(tok.next().expect("has result").is_err());
+}
+
+#[test]
+fn empty_stream() {
+    let dict = dictionary();
+    let mut tok = StreamingTokenizer::from_chunks(&dict, Mode::C, Vec::<String>::new());
+    assert!(tok.next().is_none());
+}
+
+#[test]
+fn offset_is_reset_when_list_is_reused() {
+    let dict = dictionary();
+    let mut tok = StreamingTokenizer::from_chunks(&dict, Mode::C, ["京都に行った。", "東京都"]);
+    tok.next_sentence().expect("works");
+    let mut list = tok.next_sentence().expect("works").expect("has sentence");
+    assert_eq!((21, 7), list.offset());
+
+    let mut stateful = StatefulTokenizer::new(&dict, Mode::C);
+    stateful.reset().push_str("東京都");
+    stateful.do_tokenize().expect("works");
+    list.collect_results(&mut stateful).expect("works");
+    assert_eq!((0, 0), list.offset());
+    assert_eq!(0, list.get(0).begin());
+}
+
+#[test]
+fn sentence_end_split_between_many_chunks() {
+    let dict = dictionary();
+    let text = "京都（東京都。）に行った・・・東京都<br><br>京都";
+    let chunks: Vec<String> = text.chars().map(|c| c.to_string()).collect();
+    let tok = StreamingTokenizer::from_chunks(&dict, Mode::C, chunks);
+    let sentences: Vec<String> = tok
+        .map(|r| r.expect("works").surface().to_string())
+        .collect();
+    assert_eq!(
+        vec!["京都（東京都。）に行った・・・", "東京都<br><br>", "京都"],
+        sentences
+    );
+}
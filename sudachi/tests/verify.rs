@@ -0,0 +1,165 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use sudachi::dic::build::DictBuilder;
+use sudachi::dic::dictionary::JapaneseDictionary;
+use sudachi::dic::grammar::Grammar;
+use sudachi::dic::header::Header;
+use sudachi::dic::verify::{verify_system, verify_user};
+use sudachi::dic::DictionaryLoader;
+use sudachi::error::SudachiError;
+
+mod common;
+use common::{LEX_CSV, TEST_CONFIG, USER1_CSV};
+
+fn build_system(checksum: bool) -> Vec<u8> {
+    let mut bld = DictBuilder::new_system();
+    bld.set_checksum(checksum);
+    bld.read_conn(include_bytes!("resources/matrix_10x10.def"))
+        .unwrap();
+    bld.read_lexicon(LEX_CSV).unwrap();
+    bld.resolve().unwrap();
+    let mut data = Vec::new();
+    bld.compile(&mut data).unwrap();
+    data
+}
+
+fn build_user(system: &[u8], checksum: bool) -> Vec<u8> {
+    let dic = DictionaryLoader::read_system_dictionary(system)
+        .unwrap()
+        .to_loaded()
+        .unwrap();
+    let mut bld = DictBuilder::new_user(&dic);
+    bld.set_checksum(checksum);
+    bld.read_lexicon(USER1_CSV).unwrap();
+    bld.resolve().unwrap();
+    let mut data = Vec::new();
+    bld.compile(&mut data).unwrap();
+    data
+}
+
+/// Offset of the lexicon in the system dictionary
+fn lexicon_offset(data: &[u8]) -> usize {
+    let grammar = Grammar::parse(data, Header::STORAGE_SIZE).unwrap();
+    Header::STORAGE_SIZE + grammar.storage_size
+}
+
+fn assert_invalid(data: &[u8]) {
+    match verify_system(data) {
+        Err(SudachiError::InvalidDictionary(_)) => {}
+        r => panic!(
+            "expected InvalidDictionary, got {:?}",
+            r.map(|r| r.num_words)
+        ),
+    }
+}
+
+#[test]
+fn built_dictionaries_are_valid() {
+    let system = build_system(false);
+    let report = verify_system(&system).expect("works");
+    assert_eq!(None, report.header.checksum);
+    assert_eq!((10, 10), report.matrix_size);
+    assert!(report.num_words > 0);
+    assert!(report.num_indexed_words <= report.num_words as usize);
+
+    let user = build_user(&system, false);
+    let user_report = verify_user(&user, &report).expect("works");
+    assert!(user_report.num_words > 0);
+    // user dictionaries can't be verified without system
+    assert!(verify_system(&user).is_err());
+    assert!(verify_user(&system, &report).is_err());
+}
+
+#[test]
+fn test_dictionaries_are_valid() {
+    let dict = JapaneseDictionary::from_cfg(&TEST_CONFIG).expect("works");
+    let reports = dict.verify().expect("works");
+    assert_eq!(2, reports.len());
+}
+
+#[test]
+fn checksum_is_written_and_checked() {
+    let system = build_system(true);
+    let report = verify_system(&system).expect("works");
+    assert!(report.header.checksum.is_some());
+    let user = build_user(&system, true);
+    assert!(verify_user(&user, &report).is_ok());
+
+    // checksum does not change the contents
+    let plain = build_system(false);
+    assert_eq!(
+        plain[Header::STORAGE_SIZE..],
+        system[Header::STORAGE_SIZE..]
+    );
+
+    // flipping a byte which is not checked structurally (a string) is detected
+    let mut corrupted = system.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0x40;
+    assert_invalid(&corrupted);
+    assert!(verify_system(&plain).is_ok());
+}
+
+#[test]
+fn truncated_dictionary_is_invalid() {
+    let system = build_system(false);
+    for len in [
+        Header::STORAGE_SIZE + 10,
+        system.len() / 2,
+        system.len() - 1,
+    ] {
+        assert!(verify_system(&system[..len]).is_err());
+    }
+    assert!(verify_system(&system[..10]).is_err());
+}
+
+#[test]
+fn corrupted_trie_is_invalid() {
+    let mut system = build_system(false);
+    // root unit points outside of the trie
+    let root = lexicon_offset(&system) + 4;
+    system[root + 3] = 0x7f;
+    assert_invalid(&system);
+}
+
+#[test]
+fn corrupted_word_params_are_invalid() {
+    let mut system = build_system(false);
+    let mut offset = lexicon_offset(&system);
+    let read = |data: &[u8], at: usize| {
+        u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]) as usize
+    };
+    offset += 4 + read(&system, offset) * 4;
+    offset += 4 + read(&system, offset);
+    // left id of the first word is outside of the 10x10 matrix
+    system[offset + 4..offset + 6].copy_from_slice(&10i16.to_le_bytes());
+    assert_invalid(&system);
+}
+
+#[test]
+fn verification_does_not_panic() {
+    let system = build_system(false);
+    let mut data = system.clone();
+    // the stride is prime, so all positions in 4-byte values are covered
+    for i in (Header::STORAGE_SIZE..data.len()).step_by(7) {
+        for v in [0x00, 0xff] {
+            let old = std::mem::replace(&mut data[i], v);
+            let _ = verify_system(&data);
+            data[i] = old;
+        }
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ *  Copyright (c) 2024 Works Applications Co., Ltd.
+ *
+ *  Licensed under the Apache License, Version 2.0 (the "License");
+ *  you may not use this file except in compliance with the License.
+ *  You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *   Unless required by applicable law or agreed to in writing, software
+ *  distributed under the License is distributed on an "AS IS" BASIS,
+ *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *  See the License for the specific language governing permissions and
+ *  limitations under the License.
+ */
+
+use crate::common::{TestStatefulTokenizer, LEX_CSV};
+use std::ops::Deref;
+use sudachi::analysis::constraints::Constraints;
+use sudachi::analysis::Mode;
+
+mod common;
+
+const WEB_CONFIG: &[u8] = include_bytes!("resources/sudachi.web.json");
+
+fn tokens(tok: &mut TestStatefulTokenizer, data: &str) -> Vec<(String, String)> {
+    tok.tokenize(data)
+        .iter()
+        .map(|m| (m.surface().to_string(), m.part_of_speech()[2].clone()))
+        .collect()
+}
+
+#[test]
+fn web_tokens_are_single_morphemes() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(WEB_CONFIG)
+        .mode(Mode::A)
+        .build();
+    // the cost of web tokens is high, but they are still kept whole
+    let result = tokens(&mut tok, "東京https://example.com/a-b_c?q=1京都");
+    assert_eq!(3, result.len());
+    assert_eq!(
+        result[1],
+        ("https://example.com/a-b_c?q=1".to_owned(), "URL".to_owned())
+    );
+    let result = tokens(&mut tok, "東京@sudachi #京都 a@example.com");
+    let web: Vec<_> = result
+        .iter()
+        .filter(|(_, pos)| pos.chars().all(|c| c.is_ascii_uppercase()))
+        .map(|(s, p)| (s.as_str(), p.as_str()))
+        .collect();
+    assert_eq!(
+        web,
+        [
+            ("@sudachi", "MENTION"),
+            ("#京都", "HASHTAG"),
+            ("a@example.com", "EMAIL")
+        ]
+    );
+}
+
+#[test]
+fn constraints_override_web_tokens() {
+    let mut tok = TestStatefulTokenizer::builder(LEX_CSV)
+        .config(WEB_CONFIG)
+        .build();
+    let mut c = Constraints::new();
+    c.add_boundary(5);
+    tok.tok.reset().push_str("@user_name");
+    tok.tok.set_constraints(c);
+    tok.tok.do_tokenize().unwrap();
+    tok.result.collect_results(&mut tok.tok).unwrap();
+    // the mention is not kept whole, because it crosses the required boundary
+    let ends: Vec<_> = tok.result.iter().map(|m| m.end()).collect();
+    assert!(ends.contains(&5), "{:?}", ends);
+    assert_ne!("@user_name", tok.result.get(0).surface().deref());
+}